@@ -0,0 +1,1174 @@
+//! https://www.consul.io/api-docs/acl
+
+use std::collections::HashMap;
+
+#[cfg(feature = "async")]
+use async_trait::async_trait;
+
+use crate::errors::{Error, Result};
+#[cfg(feature = "async")]
+use crate::request::async_requests;
+use crate::request::delete_requests::delete;
+use crate::request::get_requests::get;
+use crate::request::post_requests::post;
+use crate::request::put_requests::put;
+#[cfg(feature = "async")]
+use crate::AsyncClient;
+use crate::{Client, Config, QueryMeta, QueryOptions, WriteMeta, WriteOptions};
+
+/// A policy or role attached to a token or role, by ID or by name.
+#[derive(Clone, Default, Eq, PartialEq, Serialize, Deserialize, Debug)]
+#[serde(default)]
+pub struct ACLLink {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub ID: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub Name: Option<String>,
+}
+
+/// Grants the permissions a service named `ServiceName` needs.
+#[derive(Clone, Default, Eq, PartialEq, Serialize, Deserialize, Debug)]
+#[serde(default)]
+pub struct ACLServiceIdentity {
+    pub ServiceName: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub Datacenters: Option<Vec<String>>,
+}
+
+/// Grants the permissions the agent on node `NodeName` needs.
+#[derive(Clone, Default, Eq, PartialEq, Serialize, Deserialize, Debug)]
+#[serde(default)]
+pub struct ACLNodeIdentity {
+    pub NodeName: String,
+    pub Datacenter: String,
+}
+
+/// https://www.consul.io/api-docs/acl/tokens
+#[derive(Clone, Default, Eq, PartialEq, Serialize, Deserialize, Debug)]
+#[serde(default)]
+pub struct ACLToken {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub AccessorID: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub SecretID: Option<String>,
+    pub Description: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub Policies: Option<Vec<ACLLink>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub Roles: Option<Vec<ACLLink>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub ServiceIdentities: Option<Vec<ACLServiceIdentity>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub NodeIdentities: Option<Vec<ACLNodeIdentity>>,
+    pub Local: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub AuthMethod: Option<String>,
+    /// A duration such as `24h`, only when creating a token.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub ExpirationTTL: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub ExpirationTime: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub CreateTime: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub Hash: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub Namespace: Option<String>,
+    pub CreateIndex: u64,
+    pub ModifyIndex: u64,
+}
+
+/// https://www.consul.io/api-docs/acl/policies
+#[derive(Clone, Default, Eq, PartialEq, Serialize, Deserialize, Debug)]
+#[serde(default)]
+pub struct ACLPolicy {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub ID: Option<String>,
+    pub Name: String,
+    pub Description: String,
+    /// The policy's rules, in HCL or JSON. Left out of policy listings.
+    pub Rules: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub Datacenters: Option<Vec<String>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub Hash: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub Namespace: Option<String>,
+    pub CreateIndex: u64,
+    pub ModifyIndex: u64,
+}
+
+/// https://www.consul.io/api-docs/acl/roles
+#[derive(Clone, Default, Eq, PartialEq, Serialize, Deserialize, Debug)]
+#[serde(default)]
+pub struct ACLRole {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub ID: Option<String>,
+    pub Name: String,
+    pub Description: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub Policies: Option<Vec<ACLLink>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub ServiceIdentities: Option<Vec<ACLServiceIdentity>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub NodeIdentities: Option<Vec<ACLNodeIdentity>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub Hash: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub Namespace: Option<String>,
+    pub CreateIndex: u64,
+    pub ModifyIndex: u64,
+}
+
+/// https://www.consul.io/api-docs/acl/binding-rules
+#[derive(Clone, Default, Eq, PartialEq, Serialize, Deserialize, Debug)]
+#[serde(default)]
+pub struct ACLBindingRule {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub ID: Option<String>,
+    pub Description: String,
+    pub AuthMethod: String,
+    pub Selector: String,
+    /// `service`, `node`, `role` or `policy`.
+    pub BindType: String,
+    pub BindName: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub Namespace: Option<String>,
+    pub CreateIndex: u64,
+    pub ModifyIndex: u64,
+}
+
+/// https://www.consul.io/api-docs/acl/auth-methods
+#[derive(Clone, Default, PartialEq, Serialize, Deserialize, Debug)]
+#[serde(default)]
+pub struct ACLAuthMethod {
+    pub Name: String,
+    /// `kubernetes`, `jwt` or `oidc`.
+    pub Type: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub DisplayName: Option<String>,
+    pub Description: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub MaxTokenTTL: Option<String>,
+    /// `local` or `global`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub TokenLocality: Option<String>,
+    /// Settings specific to `Type`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub Config: Option<serde_json::Value>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub Namespace: Option<String>,
+    pub CreateIndex: u64,
+    pub ModifyIndex: u64,
+}
+
+/// https://www.consul.io/api-docs/acl#login-to-auth-method
+#[derive(Clone, Default, Eq, PartialEq, Serialize, Deserialize, Debug)]
+#[serde(default)]
+pub struct ACLLoginParams {
+    pub AuthMethod: String,
+    pub BearerToken: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub Meta: Option<HashMap<String, String>>,
+}
+
+#[derive(Serialize)]
+struct CloneRequest<'a> {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    Description: Option<&'a str>,
+}
+
+/// The ID an update is sent to, which must be set in the updated entry.
+fn id_for_update<'a>(id: &'a Option<String>, what: &str) -> Result<&'a str> {
+    id.as_deref()
+        .ok_or_else(|| Error::from(format!("Cannot update {} without an ID", what)))
+}
+
+/// `config` authenticating with `token`, for logging it out.
+fn with_token(config: &Config, token: &str) -> Config {
+    let mut config = config.clone();
+    config.token = Some(token.to_owned());
+    config
+}
+
+fn binding_rule_params(auth_method: Option<&str>) -> HashMap<String, String> {
+    let mut params = HashMap::new();
+    if let Some(auth_method) = auth_method {
+        params.insert(String::from("authmethod"), auth_method.to_owned());
+    }
+    params
+}
+
+pub trait ACL {
+    /// Creates the initial management token. Only succeeds once per cluster.
+    fn bootstrap(&self, options: Option<&WriteOptions>) -> Result<(ACLToken, WriteMeta)>;
+
+    fn token_create(
+        &self,
+        token: &ACLToken,
+        options: Option<&WriteOptions>,
+    ) -> Result<(ACLToken, WriteMeta)>;
+    fn token_read(
+        &self,
+        accessor_id: &str,
+        options: Option<&QueryOptions>,
+    ) -> Result<(ACLToken, QueryMeta)>;
+    /// Reads the token the request is made with.
+    fn token_read_self(&self, options: Option<&QueryOptions>) -> Result<(ACLToken, QueryMeta)>;
+    /// Replaces the token with `token.AccessorID`.
+    fn token_update(
+        &self,
+        token: &ACLToken,
+        options: Option<&WriteOptions>,
+    ) -> Result<(ACLToken, WriteMeta)>;
+    /// Creates a token with the same policies, roles and identities, with a
+    /// new description when given.
+    fn token_clone(
+        &self,
+        accessor_id: &str,
+        description: Option<&str>,
+        options: Option<&WriteOptions>,
+    ) -> Result<(ACLToken, WriteMeta)>;
+    fn token_delete(
+        &self,
+        accessor_id: &str,
+        options: Option<&WriteOptions>,
+    ) -> Result<(bool, WriteMeta)>;
+    fn token_list(&self, options: Option<&QueryOptions>) -> Result<(Vec<ACLToken>, QueryMeta)>;
+
+    fn policy_create(
+        &self,
+        policy: &ACLPolicy,
+        options: Option<&WriteOptions>,
+    ) -> Result<(ACLPolicy, WriteMeta)>;
+    fn policy_read(
+        &self,
+        id: &str,
+        options: Option<&QueryOptions>,
+    ) -> Result<(ACLPolicy, QueryMeta)>;
+    fn policy_read_by_name(
+        &self,
+        name: &str,
+        options: Option<&QueryOptions>,
+    ) -> Result<(ACLPolicy, QueryMeta)>;
+    /// Replaces the policy with `policy.ID`.
+    fn policy_update(
+        &self,
+        policy: &ACLPolicy,
+        options: Option<&WriteOptions>,
+    ) -> Result<(ACLPolicy, WriteMeta)>;
+    fn policy_delete(&self, id: &str, options: Option<&WriteOptions>) -> Result<(bool, WriteMeta)>;
+    fn policy_list(&self, options: Option<&QueryOptions>) -> Result<(Vec<ACLPolicy>, QueryMeta)>;
+
+    fn role_create(
+        &self,
+        role: &ACLRole,
+        options: Option<&WriteOptions>,
+    ) -> Result<(ACLRole, WriteMeta)>;
+    fn role_read(&self, id: &str, options: Option<&QueryOptions>) -> Result<(ACLRole, QueryMeta)>;
+    fn role_read_by_name(
+        &self,
+        name: &str,
+        options: Option<&QueryOptions>,
+    ) -> Result<(ACLRole, QueryMeta)>;
+    /// Replaces the role with `role.ID`.
+    fn role_update(
+        &self,
+        role: &ACLRole,
+        options: Option<&WriteOptions>,
+    ) -> Result<(ACLRole, WriteMeta)>;
+    fn role_delete(&self, id: &str, options: Option<&WriteOptions>) -> Result<(bool, WriteMeta)>;
+    fn role_list(&self, options: Option<&QueryOptions>) -> Result<(Vec<ACLRole>, QueryMeta)>;
+
+    fn binding_rule_create(
+        &self,
+        rule: &ACLBindingRule,
+        options: Option<&WriteOptions>,
+    ) -> Result<(ACLBindingRule, WriteMeta)>;
+    fn binding_rule_read(
+        &self,
+        id: &str,
+        options: Option<&QueryOptions>,
+    ) -> Result<(ACLBindingRule, QueryMeta)>;
+    /// Replaces the binding rule with `rule.ID`.
+    fn binding_rule_update(
+        &self,
+        rule: &ACLBindingRule,
+        options: Option<&WriteOptions>,
+    ) -> Result<(ACLBindingRule, WriteMeta)>;
+    fn binding_rule_delete(
+        &self,
+        id: &str,
+        options: Option<&WriteOptions>,
+    ) -> Result<(bool, WriteMeta)>;
+    /// Lists the binding rules, only those of `auth_method` when given.
+    fn binding_rule_list(
+        &self,
+        auth_method: Option<&str>,
+        options: Option<&QueryOptions>,
+    ) -> Result<(Vec<ACLBindingRule>, QueryMeta)>;
+
+    fn auth_method_create(
+        &self,
+        method: &ACLAuthMethod,
+        options: Option<&WriteOptions>,
+    ) -> Result<(ACLAuthMethod, WriteMeta)>;
+    fn auth_method_read(
+        &self,
+        name: &str,
+        options: Option<&QueryOptions>,
+    ) -> Result<(ACLAuthMethod, QueryMeta)>;
+    /// Replaces the auth method named `method.Name`.
+    fn auth_method_update(
+        &self,
+        method: &ACLAuthMethod,
+        options: Option<&WriteOptions>,
+    ) -> Result<(ACLAuthMethod, WriteMeta)>;
+    fn auth_method_delete(
+        &self,
+        name: &str,
+        options: Option<&WriteOptions>,
+    ) -> Result<(bool, WriteMeta)>;
+    fn auth_method_list(
+        &self,
+        options: Option<&QueryOptions>,
+    ) -> Result<(Vec<ACLAuthMethod>, QueryMeta)>;
+
+    /// Exchanges a bearer token from an auth method for a Consul token.
+    fn login(
+        &self,
+        params: &ACLLoginParams,
+        options: Option<&WriteOptions>,
+    ) -> Result<(ACLToken, WriteMeta)>;
+    /// Destroys a token created by `login`, given its secret ID.
+    fn logout(&self, token: &str, options: Option<&WriteOptions>) -> Result<((), WriteMeta)>;
+}
+
+impl ACL for Client {
+    fn bootstrap(&self, options: Option<&WriteOptions>) -> Result<(ACLToken, WriteMeta)> {
+        put(
+            "/v1/acl/bootstrap",
+            None as Option<&()>,
+            &self.config,
+            HashMap::new(),
+            options,
+        )
+    }
+
+    fn token_create(
+        &self,
+        token: &ACLToken,
+        options: Option<&WriteOptions>,
+    ) -> Result<(ACLToken, WriteMeta)> {
+        put(
+            "/v1/acl/token",
+            Some(token),
+            &self.config,
+            HashMap::new(),
+            options,
+        )
+    }
+    fn token_read(
+        &self,
+        accessor_id: &str,
+        options: Option<&QueryOptions>,
+    ) -> Result<(ACLToken, QueryMeta)> {
+        let path = format!("/v1/acl/token/{}", accessor_id);
+        get(&path, &self.config, HashMap::new(), options)
+    }
+    fn token_read_self(&self, options: Option<&QueryOptions>) -> Result<(ACLToken, QueryMeta)> {
+        get("/v1/acl/token/self", &self.config, HashMap::new(), options)
+    }
+    fn token_update(
+        &self,
+        token: &ACLToken,
+        options: Option<&WriteOptions>,
+    ) -> Result<(ACLToken, WriteMeta)> {
+        let path = format!(
+            "/v1/acl/token/{}",
+            id_for_update(&token.AccessorID, "token")?
+        );
+        put(&path, Some(token), &self.config, HashMap::new(), options)
+    }
+    fn token_clone(
+        &self,
+        accessor_id: &str,
+        description: Option<&str>,
+        options: Option<&WriteOptions>,
+    ) -> Result<(ACLToken, WriteMeta)> {
+        let path = format!("/v1/acl/token/{}/clone", accessor_id);
+        let body = CloneRequest {
+            Description: description,
+        };
+        put(&path, Some(&body), &self.config, HashMap::new(), options)
+    }
+    fn token_delete(
+        &self,
+        accessor_id: &str,
+        options: Option<&WriteOptions>,
+    ) -> Result<(bool, WriteMeta)> {
+        let path = format!("/v1/acl/token/{}", accessor_id);
+        delete(&path, &self.config, HashMap::new(), options)
+    }
+    fn token_list(&self, options: Option<&QueryOptions>) -> Result<(Vec<ACLToken>, QueryMeta)> {
+        get("/v1/acl/tokens", &self.config, HashMap::new(), options)
+    }
+
+    fn policy_create(
+        &self,
+        policy: &ACLPolicy,
+        options: Option<&WriteOptions>,
+    ) -> Result<(ACLPolicy, WriteMeta)> {
+        put(
+            "/v1/acl/policy",
+            Some(policy),
+            &self.config,
+            HashMap::new(),
+            options,
+        )
+    }
+    fn policy_read(
+        &self,
+        id: &str,
+        options: Option<&QueryOptions>,
+    ) -> Result<(ACLPolicy, QueryMeta)> {
+        let path = format!("/v1/acl/policy/{}", id);
+        get(&path, &self.config, HashMap::new(), options)
+    }
+    fn policy_read_by_name(
+        &self,
+        name: &str,
+        options: Option<&QueryOptions>,
+    ) -> Result<(ACLPolicy, QueryMeta)> {
+        let path = format!("/v1/acl/policy/name/{}", name);
+        get(&path, &self.config, HashMap::new(), options)
+    }
+    fn policy_update(
+        &self,
+        policy: &ACLPolicy,
+        options: Option<&WriteOptions>,
+    ) -> Result<(ACLPolicy, WriteMeta)> {
+        let path = format!("/v1/acl/policy/{}", id_for_update(&policy.ID, "policy")?);
+        put(&path, Some(policy), &self.config, HashMap::new(), options)
+    }
+    fn policy_delete(&self, id: &str, options: Option<&WriteOptions>) -> Result<(bool, WriteMeta)> {
+        let path = format!("/v1/acl/policy/{}", id);
+        delete(&path, &self.config, HashMap::new(), options)
+    }
+    fn policy_list(&self, options: Option<&QueryOptions>) -> Result<(Vec<ACLPolicy>, QueryMeta)> {
+        get("/v1/acl/policies", &self.config, HashMap::new(), options)
+    }
+
+    fn role_create(
+        &self,
+        role: &ACLRole,
+        options: Option<&WriteOptions>,
+    ) -> Result<(ACLRole, WriteMeta)> {
+        put(
+            "/v1/acl/role",
+            Some(role),
+            &self.config,
+            HashMap::new(),
+            options,
+        )
+    }
+    fn role_read(&self, id: &str, options: Option<&QueryOptions>) -> Result<(ACLRole, QueryMeta)> {
+        let path = format!("/v1/acl/role/{}", id);
+        get(&path, &self.config, HashMap::new(), options)
+    }
+    fn role_read_by_name(
+        &self,
+        name: &str,
+        options: Option<&QueryOptions>,
+    ) -> Result<(ACLRole, QueryMeta)> {
+        let path = format!("/v1/acl/role/name/{}", name);
+        get(&path, &self.config, HashMap::new(), options)
+    }
+    fn role_update(
+        &self,
+        role: &ACLRole,
+        options: Option<&WriteOptions>,
+    ) -> Result<(ACLRole, WriteMeta)> {
+        let path = format!("/v1/acl/role/{}", id_for_update(&role.ID, "role")?);
+        put(&path, Some(role), &self.config, HashMap::new(), options)
+    }
+    fn role_delete(&self, id: &str, options: Option<&WriteOptions>) -> Result<(bool, WriteMeta)> {
+        let path = format!("/v1/acl/role/{}", id);
+        delete(&path, &self.config, HashMap::new(), options)
+    }
+    fn role_list(&self, options: Option<&QueryOptions>) -> Result<(Vec<ACLRole>, QueryMeta)> {
+        get("/v1/acl/roles", &self.config, HashMap::new(), options)
+    }
+
+    fn binding_rule_create(
+        &self,
+        rule: &ACLBindingRule,
+        options: Option<&WriteOptions>,
+    ) -> Result<(ACLBindingRule, WriteMeta)> {
+        put(
+            "/v1/acl/binding-rule",
+            Some(rule),
+            &self.config,
+            HashMap::new(),
+            options,
+        )
+    }
+    fn binding_rule_read(
+        &self,
+        id: &str,
+        options: Option<&QueryOptions>,
+    ) -> Result<(ACLBindingRule, QueryMeta)> {
+        let path = format!("/v1/acl/binding-rule/{}", id);
+        get(&path, &self.config, HashMap::new(), options)
+    }
+    fn binding_rule_update(
+        &self,
+        rule: &ACLBindingRule,
+        options: Option<&WriteOptions>,
+    ) -> Result<(ACLBindingRule, WriteMeta)> {
+        let path = format!(
+            "/v1/acl/binding-rule/{}",
+            id_for_update(&rule.ID, "binding rule")?
+        );
+        put(&path, Some(rule), &self.config, HashMap::new(), options)
+    }
+    fn binding_rule_delete(
+        &self,
+        id: &str,
+        options: Option<&WriteOptions>,
+    ) -> Result<(bool, WriteMeta)> {
+        let path = format!("/v1/acl/binding-rule/{}", id);
+        delete(&path, &self.config, HashMap::new(), options)
+    }
+    fn binding_rule_list(
+        &self,
+        auth_method: Option<&str>,
+        options: Option<&QueryOptions>,
+    ) -> Result<(Vec<ACLBindingRule>, QueryMeta)> {
+        get(
+            "/v1/acl/binding-rules",
+            &self.config,
+            binding_rule_params(auth_method),
+            options,
+        )
+    }
+
+    fn auth_method_create(
+        &self,
+        method: &ACLAuthMethod,
+        options: Option<&WriteOptions>,
+    ) -> Result<(ACLAuthMethod, WriteMeta)> {
+        put(
+            "/v1/acl/auth-method",
+            Some(method),
+            &self.config,
+            HashMap::new(),
+            options,
+        )
+    }
+    fn auth_method_read(
+        &self,
+        name: &str,
+        options: Option<&QueryOptions>,
+    ) -> Result<(ACLAuthMethod, QueryMeta)> {
+        let path = format!("/v1/acl/auth-method/{}", name);
+        get(&path, &self.config, HashMap::new(), options)
+    }
+    fn auth_method_update(
+        &self,
+        method: &ACLAuthMethod,
+        options: Option<&WriteOptions>,
+    ) -> Result<(ACLAuthMethod, WriteMeta)> {
+        let path = format!("/v1/acl/auth-method/{}", method.Name);
+        put(&path, Some(method), &self.config, HashMap::new(), options)
+    }
+    fn auth_method_delete(
+        &self,
+        name: &str,
+        options: Option<&WriteOptions>,
+    ) -> Result<(bool, WriteMeta)> {
+        let path = format!("/v1/acl/auth-method/{}", name);
+        delete(&path, &self.config, HashMap::new(), options)
+    }
+    fn auth_method_list(
+        &self,
+        options: Option<&QueryOptions>,
+    ) -> Result<(Vec<ACLAuthMethod>, QueryMeta)> {
+        get(
+            "/v1/acl/auth-methods",
+            &self.config,
+            HashMap::new(),
+            options,
+        )
+    }
+
+    fn login(
+        &self,
+        params: &ACLLoginParams,
+        options: Option<&WriteOptions>,
+    ) -> Result<(ACLToken, WriteMeta)> {
+        post(
+            "/v1/acl/login",
+            Some(params),
+            &self.config,
+            HashMap::new(),
+            options,
+        )
+    }
+    fn logout(&self, token: &str, options: Option<&WriteOptions>) -> Result<((), WriteMeta)> {
+        post(
+            "/v1/acl/logout",
+            None as Option<&()>,
+            &with_token(&self.config, token),
+            HashMap::new(),
+            options,
+        )
+    }
+}
+
+#[cfg(feature = "async")]
+#[async_trait]
+pub trait AsyncACL {
+    async fn bootstrap(&self, options: Option<&WriteOptions>) -> Result<(ACLToken, WriteMeta)>;
+
+    async fn token_create(
+        &self,
+        token: &ACLToken,
+        options: Option<&WriteOptions>,
+    ) -> Result<(ACLToken, WriteMeta)>;
+    async fn token_read(
+        &self,
+        accessor_id: &str,
+        options: Option<&QueryOptions>,
+    ) -> Result<(ACLToken, QueryMeta)>;
+    async fn token_read_self(
+        &self,
+        options: Option<&QueryOptions>,
+    ) -> Result<(ACLToken, QueryMeta)>;
+    async fn token_update(
+        &self,
+        token: &ACLToken,
+        options: Option<&WriteOptions>,
+    ) -> Result<(ACLToken, WriteMeta)>;
+    async fn token_clone(
+        &self,
+        accessor_id: &str,
+        description: Option<&str>,
+        options: Option<&WriteOptions>,
+    ) -> Result<(ACLToken, WriteMeta)>;
+    async fn token_delete(
+        &self,
+        accessor_id: &str,
+        options: Option<&WriteOptions>,
+    ) -> Result<(bool, WriteMeta)>;
+    async fn token_list(
+        &self,
+        options: Option<&QueryOptions>,
+    ) -> Result<(Vec<ACLToken>, QueryMeta)>;
+
+    async fn policy_create(
+        &self,
+        policy: &ACLPolicy,
+        options: Option<&WriteOptions>,
+    ) -> Result<(ACLPolicy, WriteMeta)>;
+    async fn policy_read(
+        &self,
+        id: &str,
+        options: Option<&QueryOptions>,
+    ) -> Result<(ACLPolicy, QueryMeta)>;
+    async fn policy_read_by_name(
+        &self,
+        name: &str,
+        options: Option<&QueryOptions>,
+    ) -> Result<(ACLPolicy, QueryMeta)>;
+    async fn policy_update(
+        &self,
+        policy: &ACLPolicy,
+        options: Option<&WriteOptions>,
+    ) -> Result<(ACLPolicy, WriteMeta)>;
+    async fn policy_delete(
+        &self,
+        id: &str,
+        options: Option<&WriteOptions>,
+    ) -> Result<(bool, WriteMeta)>;
+    async fn policy_list(
+        &self,
+        options: Option<&QueryOptions>,
+    ) -> Result<(Vec<ACLPolicy>, QueryMeta)>;
+
+    async fn role_create(
+        &self,
+        role: &ACLRole,
+        options: Option<&WriteOptions>,
+    ) -> Result<(ACLRole, WriteMeta)>;
+    async fn role_read(
+        &self,
+        id: &str,
+        options: Option<&QueryOptions>,
+    ) -> Result<(ACLRole, QueryMeta)>;
+    async fn role_read_by_name(
+        &self,
+        name: &str,
+        options: Option<&QueryOptions>,
+    ) -> Result<(ACLRole, QueryMeta)>;
+    async fn role_update(
+        &self,
+        role: &ACLRole,
+        options: Option<&WriteOptions>,
+    ) -> Result<(ACLRole, WriteMeta)>;
+    async fn role_delete(
+        &self,
+        id: &str,
+        options: Option<&WriteOptions>,
+    ) -> Result<(bool, WriteMeta)>;
+    async fn role_list(&self, options: Option<&QueryOptions>) -> Result<(Vec<ACLRole>, QueryMeta)>;
+
+    async fn binding_rule_create(
+        &self,
+        rule: &ACLBindingRule,
+        options: Option<&WriteOptions>,
+    ) -> Result<(ACLBindingRule, WriteMeta)>;
+    async fn binding_rule_read(
+        &self,
+        id: &str,
+        options: Option<&QueryOptions>,
+    ) -> Result<(ACLBindingRule, QueryMeta)>;
+    async fn binding_rule_update(
+        &self,
+        rule: &ACLBindingRule,
+        options: Option<&WriteOptions>,
+    ) -> Result<(ACLBindingRule, WriteMeta)>;
+    async fn binding_rule_delete(
+        &self,
+        id: &str,
+        options: Option<&WriteOptions>,
+    ) -> Result<(bool, WriteMeta)>;
+    async fn binding_rule_list(
+        &self,
+        auth_method: Option<&str>,
+        options: Option<&QueryOptions>,
+    ) -> Result<(Vec<ACLBindingRule>, QueryMeta)>;
+
+    async fn auth_method_create(
+        &self,
+        method: &ACLAuthMethod,
+        options: Option<&WriteOptions>,
+    ) -> Result<(ACLAuthMethod, WriteMeta)>;
+    async fn auth_method_read(
+        &self,
+        name: &str,
+        options: Option<&QueryOptions>,
+    ) -> Result<(ACLAuthMethod, QueryMeta)>;
+    async fn auth_method_update(
+        &self,
+        method: &ACLAuthMethod,
+        options: Option<&WriteOptions>,
+    ) -> Result<(ACLAuthMethod, WriteMeta)>;
+    async fn auth_method_delete(
+        &self,
+        name: &str,
+        options: Option<&WriteOptions>,
+    ) -> Result<(bool, WriteMeta)>;
+    async fn auth_method_list(
+        &self,
+        options: Option<&QueryOptions>,
+    ) -> Result<(Vec<ACLAuthMethod>, QueryMeta)>;
+
+    async fn login(
+        &self,
+        params: &ACLLoginParams,
+        options: Option<&WriteOptions>,
+    ) -> Result<(ACLToken, WriteMeta)>;
+    async fn logout(&self, token: &str, options: Option<&WriteOptions>) -> Result<((), WriteMeta)>;
+}
+
+#[cfg(feature = "async")]
+#[async_trait]
+impl AsyncACL for AsyncClient {
+    async fn bootstrap(&self, options: Option<&WriteOptions>) -> Result<(ACLToken, WriteMeta)> {
+        async_requests::put(
+            "/v1/acl/bootstrap",
+            None as Option<&()>,
+            &self.config,
+            HashMap::new(),
+            options,
+        )
+        .await
+    }
+
+    async fn token_create(
+        &self,
+        token: &ACLToken,
+        options: Option<&WriteOptions>,
+    ) -> Result<(ACLToken, WriteMeta)> {
+        async_requests::put(
+            "/v1/acl/token",
+            Some(token),
+            &self.config,
+            HashMap::new(),
+            options,
+        )
+        .await
+    }
+    async fn token_read(
+        &self,
+        accessor_id: &str,
+        options: Option<&QueryOptions>,
+    ) -> Result<(ACLToken, QueryMeta)> {
+        let path = format!("/v1/acl/token/{}", accessor_id);
+        async_requests::get(&path, &self.config, HashMap::new(), options).await
+    }
+    async fn token_read_self(
+        &self,
+        options: Option<&QueryOptions>,
+    ) -> Result<(ACLToken, QueryMeta)> {
+        async_requests::get("/v1/acl/token/self", &self.config, HashMap::new(), options).await
+    }
+    async fn token_update(
+        &self,
+        token: &ACLToken,
+        options: Option<&WriteOptions>,
+    ) -> Result<(ACLToken, WriteMeta)> {
+        let path = format!(
+            "/v1/acl/token/{}",
+            id_for_update(&token.AccessorID, "token")?
+        );
+        async_requests::put(&path, Some(token), &self.config, HashMap::new(), options).await
+    }
+    async fn token_clone(
+        &self,
+        accessor_id: &str,
+        description: Option<&str>,
+        options: Option<&WriteOptions>,
+    ) -> Result<(ACLToken, WriteMeta)> {
+        let path = format!("/v1/acl/token/{}/clone", accessor_id);
+        let body = CloneRequest {
+            Description: description,
+        };
+        async_requests::put(&path, Some(&body), &self.config, HashMap::new(), options).await
+    }
+    async fn token_delete(
+        &self,
+        accessor_id: &str,
+        options: Option<&WriteOptions>,
+    ) -> Result<(bool, WriteMeta)> {
+        let path = format!("/v1/acl/token/{}", accessor_id);
+        async_requests::delete(&path, &self.config, HashMap::new(), options).await
+    }
+    async fn token_list(
+        &self,
+        options: Option<&QueryOptions>,
+    ) -> Result<(Vec<ACLToken>, QueryMeta)> {
+        async_requests::get("/v1/acl/tokens", &self.config, HashMap::new(), options).await
+    }
+
+    async fn policy_create(
+        &self,
+        policy: &ACLPolicy,
+        options: Option<&WriteOptions>,
+    ) -> Result<(ACLPolicy, WriteMeta)> {
+        async_requests::put(
+            "/v1/acl/policy",
+            Some(policy),
+            &self.config,
+            HashMap::new(),
+            options,
+        )
+        .await
+    }
+    async fn policy_read(
+        &self,
+        id: &str,
+        options: Option<&QueryOptions>,
+    ) -> Result<(ACLPolicy, QueryMeta)> {
+        let path = format!("/v1/acl/policy/{}", id);
+        async_requests::get(&path, &self.config, HashMap::new(), options).await
+    }
+    async fn policy_read_by_name(
+        &self,
+        name: &str,
+        options: Option<&QueryOptions>,
+    ) -> Result<(ACLPolicy, QueryMeta)> {
+        let path = format!("/v1/acl/policy/name/{}", name);
+        async_requests::get(&path, &self.config, HashMap::new(), options).await
+    }
+    async fn policy_update(
+        &self,
+        policy: &ACLPolicy,
+        options: Option<&WriteOptions>,
+    ) -> Result<(ACLPolicy, WriteMeta)> {
+        let path = format!("/v1/acl/policy/{}", id_for_update(&policy.ID, "policy")?);
+        async_requests::put(&path, Some(policy), &self.config, HashMap::new(), options).await
+    }
+    async fn policy_delete(
+        &self,
+        id: &str,
+        options: Option<&WriteOptions>,
+    ) -> Result<(bool, WriteMeta)> {
+        let path = format!("/v1/acl/policy/{}", id);
+        async_requests::delete(&path, &self.config, HashMap::new(), options).await
+    }
+    async fn policy_list(
+        &self,
+        options: Option<&QueryOptions>,
+    ) -> Result<(Vec<ACLPolicy>, QueryMeta)> {
+        async_requests::get("/v1/acl/policies", &self.config, HashMap::new(), options).await
+    }
+
+    async fn role_create(
+        &self,
+        role: &ACLRole,
+        options: Option<&WriteOptions>,
+    ) -> Result<(ACLRole, WriteMeta)> {
+        async_requests::put(
+            "/v1/acl/role",
+            Some(role),
+            &self.config,
+            HashMap::new(),
+            options,
+        )
+        .await
+    }
+    async fn role_read(
+        &self,
+        id: &str,
+        options: Option<&QueryOptions>,
+    ) -> Result<(ACLRole, QueryMeta)> {
+        let path = format!("/v1/acl/role/{}", id);
+        async_requests::get(&path, &self.config, HashMap::new(), options).await
+    }
+    async fn role_read_by_name(
+        &self,
+        name: &str,
+        options: Option<&QueryOptions>,
+    ) -> Result<(ACLRole, QueryMeta)> {
+        let path = format!("/v1/acl/role/name/{}", name);
+        async_requests::get(&path, &self.config, HashMap::new(), options).await
+    }
+    async fn role_update(
+        &self,
+        role: &ACLRole,
+        options: Option<&WriteOptions>,
+    ) -> Result<(ACLRole, WriteMeta)> {
+        let path = format!("/v1/acl/role/{}", id_for_update(&role.ID, "role")?);
+        async_requests::put(&path, Some(role), &self.config, HashMap::new(), options).await
+    }
+    async fn role_delete(
+        &self,
+        id: &str,
+        options: Option<&WriteOptions>,
+    ) -> Result<(bool, WriteMeta)> {
+        let path = format!("/v1/acl/role/{}", id);
+        async_requests::delete(&path, &self.config, HashMap::new(), options).await
+    }
+    async fn role_list(&self, options: Option<&QueryOptions>) -> Result<(Vec<ACLRole>, QueryMeta)> {
+        async_requests::get("/v1/acl/roles", &self.config, HashMap::new(), options).await
+    }
+
+    async fn binding_rule_create(
+        &self,
+        rule: &ACLBindingRule,
+        options: Option<&WriteOptions>,
+    ) -> Result<(ACLBindingRule, WriteMeta)> {
+        async_requests::put(
+            "/v1/acl/binding-rule",
+            Some(rule),
+            &self.config,
+            HashMap::new(),
+            options,
+        )
+        .await
+    }
+    async fn binding_rule_read(
+        &self,
+        id: &str,
+        options: Option<&QueryOptions>,
+    ) -> Result<(ACLBindingRule, QueryMeta)> {
+        let path = format!("/v1/acl/binding-rule/{}", id);
+        async_requests::get(&path, &self.config, HashMap::new(), options).await
+    }
+    async fn binding_rule_update(
+        &self,
+        rule: &ACLBindingRule,
+        options: Option<&WriteOptions>,
+    ) -> Result<(ACLBindingRule, WriteMeta)> {
+        let path = format!(
+            "/v1/acl/binding-rule/{}",
+            id_for_update(&rule.ID, "binding rule")?
+        );
+        async_requests::put(&path, Some(rule), &self.config, HashMap::new(), options).await
+    }
+    async fn binding_rule_delete(
+        &self,
+        id: &str,
+        options: Option<&WriteOptions>,
+    ) -> Result<(bool, WriteMeta)> {
+        let path = format!("/v1/acl/binding-rule/{}", id);
+        async_requests::delete(&path, &self.config, HashMap::new(), options).await
+    }
+    async fn binding_rule_list(
+        &self,
+        auth_method: Option<&str>,
+        options: Option<&QueryOptions>,
+    ) -> Result<(Vec<ACLBindingRule>, QueryMeta)> {
+        async_requests::get(
+            "/v1/acl/binding-rules",
+            &self.config,
+            binding_rule_params(auth_method),
+            options,
+        )
+        .await
+    }
+
+    async fn auth_method_create(
+        &self,
+        method: &ACLAuthMethod,
+        options: Option<&WriteOptions>,
+    ) -> Result<(ACLAuthMethod, WriteMeta)> {
+        async_requests::put(
+            "/v1/acl/auth-method",
+            Some(method),
+            &self.config,
+            HashMap::new(),
+            options,
+        )
+        .await
+    }
+    async fn auth_method_read(
+        &self,
+        name: &str,
+        options: Option<&QueryOptions>,
+    ) -> Result<(ACLAuthMethod, QueryMeta)> {
+        let path = format!("/v1/acl/auth-method/{}", name);
+        async_requests::get(&path, &self.config, HashMap::new(), options).await
+    }
+    async fn auth_method_update(
+        &self,
+        method: &ACLAuthMethod,
+        options: Option<&WriteOptions>,
+    ) -> Result<(ACLAuthMethod, WriteMeta)> {
+        let path = format!("/v1/acl/auth-method/{}", method.Name);
+        async_requests::put(&path, Some(method), &self.config, HashMap::new(), options).await
+    }
+    async fn auth_method_delete(
+        &self,
+        name: &str,
+        options: Option<&WriteOptions>,
+    ) -> Result<(bool, WriteMeta)> {
+        let path = format!("/v1/acl/auth-method/{}", name);
+        async_requests::delete(&path, &self.config, HashMap::new(), options).await
+    }
+    async fn auth_method_list(
+        &self,
+        options: Option<&QueryOptions>,
+    ) -> Result<(Vec<ACLAuthMethod>, QueryMeta)> {
+        async_requests::get(
+            "/v1/acl/auth-methods",
+            &self.config,
+            HashMap::new(),
+            options,
+        )
+        .await
+    }
+
+    async fn login(
+        &self,
+        params: &ACLLoginParams,
+        options: Option<&WriteOptions>,
+    ) -> Result<(ACLToken, WriteMeta)> {
+        async_requests::post(
+            "/v1/acl/login",
+            Some(params),
+            &self.config,
+            HashMap::new(),
+            options,
+        )
+        .await
+    }
+    async fn logout(&self, token: &str, options: Option<&WriteOptions>) -> Result<((), WriteMeta)> {
+        async_requests::post(
+            "/v1/acl/logout",
+            None as Option<&()>,
+            &with_token(&self.config, token),
+            HashMap::new(),
+            options,
+        )
+        .await
+    }
+}
+
+#[cfg(test)]
+mod acl_tests {
+    use super::*;
+
+    #[test]
+    fn token_serialize_test() {
+        let token = ACLToken {
+            Description: String::from("billing service"),
+            ServiceIdentities: Some(vec![ACLServiceIdentity {
+                ServiceName: String::from("billing"),
+                Datacenters: None,
+            }]),
+            Policies: Some(vec![ACLLink {
+                ID: None,
+                Name: Some(String::from("read-kv")),
+            }]),
+            ExpirationTTL: Some(String::from("24h")),
+            ..Default::default()
+        };
+
+        let json = serde_json::to_value(&token).unwrap();
+
+        assert_eq!(
+            json,
+            serde_json::json!({
+                "Description": "billing service",
+                "Policies": [{"Name": "read-kv"}],
+                "ServiceIdentities": [{"ServiceName": "billing"}],
+                "Local": false,
+                "ExpirationTTL": "24h",
+                "CreateIndex": 0,
+                "ModifyIndex": 0,
+            })
+        );
+    }
+
+    #[test]
+    fn token_deserialize_test() {
+        let json = r#"{
+            "AccessorID": "6a1253d2-1785-24fd-91c2-f8e78c745511",
+            "SecretID": "45a3bd52-07c7-47a4-52fd-0745e0cfe967",
+            "Description": "Agent token for 'node1'",
+            "Policies": [{"ID": "165d4317-e379-f732-ce70-86278c4558f7", "Name": "node1-write"}],
+            "NodeIdentities": [{"NodeName": "node1", "Datacenter": "dc1"}],
+            "Local": false,
+            "CreateTime": "2018-10-24T12:25:06.921933-04:00",
+            "Hash": "UuiRkOQPRCvoRZHRtUxxbrmwZ5crYrOdZ0Z1FTFbTbA=",
+            "CreateIndex": 59,
+            "ModifyIndex": 59
+        }"#;
+
+        let token: ACLToken = serde_json::from_str(json).unwrap();
+
+        assert_eq!(
+            token.SecretID.as_deref(),
+            Some("45a3bd52-07c7-47a4-52fd-0745e0cfe967")
+        );
+        assert_eq!(
+            token.Policies.unwrap()[0].Name.as_deref(),
+            Some("node1-write")
+        );
+        assert_eq!(token.NodeIdentities.unwrap()[0].NodeName, "node1");
+        assert_eq!(token.ServiceIdentities, None);
+        assert_eq!(token.CreateIndex, 59);
+    }
+
+    #[test]
+    fn id_for_update_test() {
+        assert_eq!(
+            id_for_update(&Some(String::from("abc")), "token").unwrap(),
+            "abc"
+        );
+        assert!(id_for_update(&None, "policy").is_err());
+    }
+}
@@ -1,12 +1,22 @@
 use std::collections::HashMap;
 
+#[cfg(feature = "async")]
+use async_trait::async_trait;
+
+use serde_json::Value;
+
+use crate::catalog::Weights;
 use crate::errors::Result;
+#[cfg(feature = "async")]
+use crate::request::async_requests;
 use crate::request::get_requests::{get, get_vec};
 use crate::request::put_requests::put;
+#[cfg(feature = "async")]
+use crate::AsyncClient;
 use crate::Client;
 
-#[serde(default)]
 #[derive(Clone, Default, Eq, PartialEq, Serialize, Deserialize, Debug)]
+#[serde(default)]
 pub struct AgentCheck {
     pub Node: String,
     pub CheckID: String,
@@ -18,8 +28,8 @@ pub struct AgentCheck {
     pub ServiceName: String,
 }
 
-#[serde(default)]
 #[derive(Clone, Default, Eq, PartialEq, Serialize, Deserialize, Debug)]
+#[serde(default)]
 pub struct AgentMember {
     pub Name: String,
     pub Addr: String,
@@ -34,23 +44,198 @@ pub struct AgentMember {
     pub DelegateCur: u8,
 }
 
+#[derive(Clone, Eq, Default, PartialEq, Serialize, Deserialize, Debug)]
 #[serde(default)]
-#[derive(Eq, Default, PartialEq, Serialize, Deserialize, Debug)]
 pub struct AgentService {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub Kind: Option<String>,
     pub ID: String,
     pub Service: String,
     pub Tags: Option<Vec<String>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub Meta: Option<HashMap<String, String>>,
     pub Port: u16,
     pub Address: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub TaggedAddresses: Option<HashMap<String, ServiceAddress>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub Weights: Option<Weights>,
     pub EnableTagOverride: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub Proxy: Option<AgentServiceConnectProxyConfig>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub Connect: Option<AgentServiceConnect>,
     pub CreateIndex: u64,
     pub ModifyIndex: u64,
 }
 
+#[derive(Clone, Eq, Default, PartialEq, Serialize, Deserialize, Debug)]
+#[serde(default)]
+pub struct ServiceAddress {
+    pub Address: String,
+    pub Port: u16,
+}
+
+/// https://www.consul.io/api-docs/agent/service#register-service
+#[derive(Clone, Eq, Default, PartialEq, Serialize, Deserialize, Debug)]
+#[serde(default)]
+pub struct AgentServiceRegistration {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub Kind: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub ID: Option<String>,
+    pub Name: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub Tags: Option<Vec<String>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub Port: Option<u16>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub Address: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub TaggedAddresses: Option<HashMap<String, ServiceAddress>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub EnableTagOverride: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub Meta: Option<HashMap<String, String>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub Weights: Option<Weights>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub Check: Option<AgentServiceCheck>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub Checks: Option<Vec<AgentServiceCheck>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub Proxy: Option<AgentServiceConnectProxyConfig>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub Connect: Option<AgentServiceConnect>,
+}
+
+/// A check definition, used both inside a service registration and on its own
+/// through `AgentCheckRegistration`. Which fields are set decides the kind of
+/// check: `HTTP`, `TCP`, `GRPC`, `TTL` or `Args` (script).
+///
+/// https://www.consul.io/api-docs/agent/check#register-check
+#[derive(Clone, Eq, Default, PartialEq, Serialize, Deserialize, Debug)]
+#[serde(default)]
+pub struct AgentServiceCheck {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub CheckID: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub Name: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub Notes: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub Status: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub Args: Option<Vec<String>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub DockerContainerID: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub Shell: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub Interval: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub Timeout: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub TTL: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub HTTP: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub Header: Option<HashMap<String, Vec<String>>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub Method: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub Body: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub TLSSkipVerify: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub TCP: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub GRPC: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub GRPCUseTLS: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub AliasNode: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub AliasService: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub SuccessBeforePassing: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub FailuresBeforeCritical: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub DeregisterCriticalServiceAfter: Option<String>,
+}
+
+#[derive(Clone, Eq, Default, PartialEq, Serialize, Deserialize, Debug)]
+#[serde(default)]
+pub struct AgentCheckRegistration {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub ID: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub ServiceID: Option<String>,
+    #[serde(flatten)]
+    pub Check: AgentServiceCheck,
+}
+
+#[derive(Clone, Eq, Default, PartialEq, Serialize, Deserialize, Debug)]
+#[serde(default)]
+pub struct AgentServiceConnect {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub Native: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub SidecarService: Option<Box<AgentServiceRegistration>>,
+}
+
+#[derive(Clone, Eq, Default, PartialEq, Serialize, Deserialize, Debug)]
+#[serde(default)]
+pub struct AgentServiceConnectProxyConfig {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub DestinationServiceName: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub DestinationServiceID: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub LocalServiceAddress: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub LocalServicePort: Option<u16>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub Config: Option<HashMap<String, Value>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub Upstreams: Option<Vec<Upstream>>,
+}
+
+#[derive(Clone, Eq, Default, PartialEq, Serialize, Deserialize, Debug)]
+#[serde(default)]
+pub struct Upstream {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub DestinationType: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub DestinationNamespace: Option<String>,
+    pub DestinationName: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub Datacenter: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub LocalBindAddress: Option<String>,
+    pub LocalBindPort: u16,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub Config: Option<HashMap<String, Value>>,
+}
+
+#[derive(Serialize)]
+struct CheckUpdate<'a> {
+    Status: &'a str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    Output: Option<&'a str>,
+}
+
 //I haven't implemetned https://www.consul.io/api/agent.html#read-configuration
 //I haven't implemetned https://www.consul.io/api/agent.html#stream-logs
 pub trait Agent {
     fn checks(&self) -> Result<HashMap<String, AgentCheck>>;
+    fn check_register(&self, check: &AgentCheckRegistration) -> Result<()>;
+    fn check_deregister(&self, check_id: &str) -> Result<()>;
+    fn check_pass(&self, check_id: &str, note: Option<&str>) -> Result<()>;
+    fn check_warn(&self, check_id: &str, note: Option<&str>) -> Result<()>;
+    fn check_fail(&self, check_id: &str, note: Option<&str>) -> Result<()>;
+    fn check_update(&self, check_id: &str, status: &str, output: Option<&str>) -> Result<()>;
     fn force_leave(&self) -> Result<()>;
     fn join(&self, address: &str, wan: bool) -> Result<()>;
     fn leave(&self) -> Result<()>;
@@ -58,6 +243,10 @@ pub trait Agent {
     fn members(&self, wan: bool) -> Result<Vec<AgentMember>>;
     //fn read_configuration(&self) -> Result<String>;
     fn reload(&self) -> Result<()>;
+    fn services(&self) -> Result<HashMap<String, AgentService>>;
+    fn service_by_id(&self, service_id: &str) -> Result<AgentService>;
+    fn service_register(&self, service: &AgentServiceRegistration) -> Result<()>;
+    fn service_deregister(&self, service_id: &str) -> Result<()>;
 }
 
 impl Agent for Client {
@@ -66,6 +255,80 @@ impl Agent for Client {
         get("/v1/agent/checks", &self.config, HashMap::new(), None).map(|x| x.0)
     }
 
+    /// https://www.consul.io/api-docs/agent/check#register-check
+    fn check_register(&self, check: &AgentCheckRegistration) -> Result<()> {
+        put(
+            "/v1/agent/check/register",
+            Some(check),
+            &self.config,
+            HashMap::new(),
+            None,
+        )
+        .map(|x| x.0)
+    }
+
+    /// https://www.consul.io/api-docs/agent/check#deregister-check
+    fn check_deregister(&self, check_id: &str) -> Result<()> {
+        let path = format!("/v1/agent/check/deregister/{}", check_id);
+        put(
+            &path,
+            None as Option<&()>,
+            &self.config,
+            HashMap::new(),
+            None,
+        )
+        .map(|x| x.0)
+    }
+
+    /// https://www.consul.io/api-docs/agent/check#ttl-check-pass
+    fn check_pass(&self, check_id: &str, note: Option<&str>) -> Result<()> {
+        let path = format!("/v1/agent/check/pass/{}", check_id);
+        put(
+            &path,
+            None as Option<&()>,
+            &self.config,
+            note_params(note),
+            None,
+        )
+        .map(|x| x.0)
+    }
+
+    /// https://www.consul.io/api-docs/agent/check#ttl-check-warn
+    fn check_warn(&self, check_id: &str, note: Option<&str>) -> Result<()> {
+        let path = format!("/v1/agent/check/warn/{}", check_id);
+        put(
+            &path,
+            None as Option<&()>,
+            &self.config,
+            note_params(note),
+            None,
+        )
+        .map(|x| x.0)
+    }
+
+    /// https://www.consul.io/api-docs/agent/check#ttl-check-fail
+    fn check_fail(&self, check_id: &str, note: Option<&str>) -> Result<()> {
+        let path = format!("/v1/agent/check/fail/{}", check_id);
+        put(
+            &path,
+            None as Option<&()>,
+            &self.config,
+            note_params(note),
+            None,
+        )
+        .map(|x| x.0)
+    }
+
+    /// https://www.consul.io/api-docs/agent/check#ttl-check-update
+    fn check_update(&self, check_id: &str, status: &str, output: Option<&str>) -> Result<()> {
+        let path = format!("/v1/agent/check/update/{}", check_id);
+        let update = CheckUpdate {
+            Status: status,
+            Output: output,
+        };
+        put(&path, Some(&update), &self.config, HashMap::new(), None).map(|x| x.0)
+    }
+
     /// https://www.consul.io/api/agent.html#force-leave-and-shutdown
     fn force_leave(&self) -> Result<()> {
         put(
@@ -148,4 +411,369 @@ impl Agent for Client {
         )
         .map(|x| x.0)
     }
+
+    /// https://www.consul.io/api-docs/agent/service#list-services
+    fn services(&self) -> Result<HashMap<String, AgentService>> {
+        get("/v1/agent/services", &self.config, HashMap::new(), None).map(|x| x.0)
+    }
+
+    /// https://www.consul.io/api-docs/agent/service#get-service-configuration
+    fn service_by_id(&self, service_id: &str) -> Result<AgentService> {
+        let path = format!("/v1/agent/service/{}", service_id);
+        get(&path, &self.config, HashMap::new(), None).map(|x| x.0)
+    }
+
+    /// https://www.consul.io/api-docs/agent/service#register-service
+    fn service_register(&self, service: &AgentServiceRegistration) -> Result<()> {
+        put(
+            "/v1/agent/service/register",
+            Some(service),
+            &self.config,
+            HashMap::new(),
+            None,
+        )
+        .map(|x| x.0)
+    }
+
+    /// https://www.consul.io/api-docs/agent/service#deregister-service
+    fn service_deregister(&self, service_id: &str) -> Result<()> {
+        let path = format!("/v1/agent/service/deregister/{}", service_id);
+        put(
+            &path,
+            None as Option<&()>,
+            &self.config,
+            HashMap::new(),
+            None,
+        )
+        .map(|x| x.0)
+    }
+}
+
+#[cfg(feature = "async")]
+#[async_trait]
+pub trait AsyncAgent {
+    async fn checks(&self) -> Result<HashMap<String, AgentCheck>>;
+    async fn check_register(&self, check: &AgentCheckRegistration) -> Result<()>;
+    async fn check_deregister(&self, check_id: &str) -> Result<()>;
+    async fn check_pass(&self, check_id: &str, note: Option<&str>) -> Result<()>;
+    async fn check_warn(&self, check_id: &str, note: Option<&str>) -> Result<()>;
+    async fn check_fail(&self, check_id: &str, note: Option<&str>) -> Result<()>;
+    async fn check_update(&self, check_id: &str, status: &str, output: Option<&str>) -> Result<()>;
+    async fn force_leave(&self) -> Result<()>;
+    async fn join(&self, address: &str, wan: bool) -> Result<()>;
+    async fn leave(&self) -> Result<()>;
+    async fn maintenance_mode(&self, enable: bool, reason: Option<&str>) -> Result<()>;
+    async fn members(&self, wan: bool) -> Result<Vec<AgentMember>>;
+    async fn reload(&self) -> Result<()>;
+    async fn services(&self) -> Result<HashMap<String, AgentService>>;
+    async fn service_by_id(&self, service_id: &str) -> Result<AgentService>;
+    async fn service_register(&self, service: &AgentServiceRegistration) -> Result<()>;
+    async fn service_deregister(&self, service_id: &str) -> Result<()>;
+}
+
+#[cfg(feature = "async")]
+#[async_trait]
+impl AsyncAgent for AsyncClient {
+    /// https://www.consul.io/api/agent/check.html#list-checks
+    async fn checks(&self) -> Result<HashMap<String, AgentCheck>> {
+        async_requests::get("/v1/agent/checks", &self.config, HashMap::new(), None)
+            .await
+            .map(|x| x.0)
+    }
+
+    /// https://www.consul.io/api-docs/agent/check#register-check
+    async fn check_register(&self, check: &AgentCheckRegistration) -> Result<()> {
+        async_requests::put(
+            "/v1/agent/check/register",
+            Some(check),
+            &self.config,
+            HashMap::new(),
+            None,
+        )
+        .await
+        .map(|x| x.0)
+    }
+
+    /// https://www.consul.io/api-docs/agent/check#deregister-check
+    async fn check_deregister(&self, check_id: &str) -> Result<()> {
+        let path = format!("/v1/agent/check/deregister/{}", check_id);
+        async_requests::put(
+            &path,
+            None as Option<&()>,
+            &self.config,
+            HashMap::new(),
+            None,
+        )
+        .await
+        .map(|x| x.0)
+    }
+
+    /// https://www.consul.io/api-docs/agent/check#ttl-check-pass
+    async fn check_pass(&self, check_id: &str, note: Option<&str>) -> Result<()> {
+        let path = format!("/v1/agent/check/pass/{}", check_id);
+        async_requests::put(
+            &path,
+            None as Option<&()>,
+            &self.config,
+            note_params(note),
+            None,
+        )
+        .await
+        .map(|x| x.0)
+    }
+
+    /// https://www.consul.io/api-docs/agent/check#ttl-check-warn
+    async fn check_warn(&self, check_id: &str, note: Option<&str>) -> Result<()> {
+        let path = format!("/v1/agent/check/warn/{}", check_id);
+        async_requests::put(
+            &path,
+            None as Option<&()>,
+            &self.config,
+            note_params(note),
+            None,
+        )
+        .await
+        .map(|x| x.0)
+    }
+
+    /// https://www.consul.io/api-docs/agent/check#ttl-check-fail
+    async fn check_fail(&self, check_id: &str, note: Option<&str>) -> Result<()> {
+        let path = format!("/v1/agent/check/fail/{}", check_id);
+        async_requests::put(
+            &path,
+            None as Option<&()>,
+            &self.config,
+            note_params(note),
+            None,
+        )
+        .await
+        .map(|x| x.0)
+    }
+
+    /// https://www.consul.io/api-docs/agent/check#ttl-check-update
+    async fn check_update(&self, check_id: &str, status: &str, output: Option<&str>) -> Result<()> {
+        let path = format!("/v1/agent/check/update/{}", check_id);
+        let update = CheckUpdate {
+            Status: status,
+            Output: output,
+        };
+        async_requests::put(&path, Some(&update), &self.config, HashMap::new(), None)
+            .await
+            .map(|x| x.0)
+    }
+
+    /// https://www.consul.io/api/agent.html#force-leave-and-shutdown
+    async fn force_leave(&self) -> Result<()> {
+        async_requests::put(
+            "/v1/agent/force-leave",
+            None as Option<&()>,
+            &self.config,
+            HashMap::new(),
+            None,
+        )
+        .await
+        .map(|x| x.0)
+    }
+
+    /// https://www.consul.io/api/agent.html#join-agent
+    async fn join(&self, address: &str, wan: bool) -> Result<()> {
+        let mut params = HashMap::new();
+
+        if wan {
+            params.insert(String::from("wan"), String::from("true"));
+        }
+        let path = format!("/v1/agent/join/{}", address);
+        async_requests::put(&path, None as Option<&()>, &self.config, params, None)
+            .await
+            .map(|x| x.0)
+    }
+
+    /// https://www.consul.io/api/agent.html#graceful-leave-and-shutdown
+    async fn leave(&self) -> Result<()> {
+        async_requests::put(
+            "/v1/agent/leave",
+            None as Option<&()>,
+            &self.config,
+            HashMap::new(),
+            None,
+        )
+        .await
+        .map(|x| x.0)
+    }
+
+    /// https://www.consul.io/api-docs/agent#enable-maintenance-mode
+    async fn maintenance_mode(&self, enable: bool, reason: Option<&str>) -> Result<()> {
+        let mut params = HashMap::new();
+        let enable_str = if enable {
+            String::from("true")
+        } else {
+            String::from("false")
+        };
+        params.insert(String::from("enable"), enable_str);
+        if let Some(r) = reason {
+            params.insert(String::from("reason"), r.to_owned());
+        }
+        async_requests::put(
+            "/v1/agent/maintenance",
+            None as Option<&()>,
+            &self.config,
+            params,
+            None,
+        )
+        .await
+        .map(|x| x.0)
+    }
+
+    /// https://www.consul.io/api/agent.html#list-members
+    async fn members(&self, wan: bool) -> Result<Vec<AgentMember>> {
+        let mut params = HashMap::new();
+        if wan {
+            params.insert(String::from("wan"), String::from("1"));
+        }
+
+        async_requests::get_vec("/v1/agent/members", &self.config, params, None)
+            .await
+            .map(|x| x.0)
+    }
+
+    /// https://www.consul.io/api/agent.html#reload-agent
+    async fn reload(&self) -> Result<()> {
+        async_requests::put(
+            "/v1/agent/reload",
+            None as Option<&()>,
+            &self.config,
+            HashMap::new(),
+            None,
+        )
+        .await
+        .map(|x| x.0)
+    }
+
+    /// https://www.consul.io/api-docs/agent/service#list-services
+    async fn services(&self) -> Result<HashMap<String, AgentService>> {
+        async_requests::get("/v1/agent/services", &self.config, HashMap::new(), None)
+            .await
+            .map(|x| x.0)
+    }
+
+    /// https://www.consul.io/api-docs/agent/service#get-service-configuration
+    async fn service_by_id(&self, service_id: &str) -> Result<AgentService> {
+        let path = format!("/v1/agent/service/{}", service_id);
+        async_requests::get(&path, &self.config, HashMap::new(), None)
+            .await
+            .map(|x| x.0)
+    }
+
+    /// https://www.consul.io/api-docs/agent/service#register-service
+    async fn service_register(&self, service: &AgentServiceRegistration) -> Result<()> {
+        async_requests::put(
+            "/v1/agent/service/register",
+            Some(service),
+            &self.config,
+            HashMap::new(),
+            None,
+        )
+        .await
+        .map(|x| x.0)
+    }
+
+    /// https://www.consul.io/api-docs/agent/service#deregister-service
+    async fn service_deregister(&self, service_id: &str) -> Result<()> {
+        let path = format!("/v1/agent/service/deregister/{}", service_id);
+        async_requests::put(
+            &path,
+            None as Option<&()>,
+            &self.config,
+            HashMap::new(),
+            None,
+        )
+        .await
+        .map(|x| x.0)
+    }
+}
+
+fn note_params(note: Option<&str>) -> HashMap<String, String> {
+    let mut params = HashMap::new();
+    if let Some(n) = note {
+        params.insert(String::from("note"), n.to_owned());
+    }
+    params
+}
+
+#[cfg(test)]
+mod agent_tests {
+    use super::*;
+
+    #[test]
+    fn serialize_service_registration_test() {
+        let registration = AgentServiceRegistration {
+            ID: Some(String::from("web-1")),
+            Name: String::from("web"),
+            Port: Some(8080),
+            Weights: Some(Weights {
+                Passing: 10,
+                Warning: 1,
+            }),
+            Checks: Some(vec![
+                AgentServiceCheck {
+                    TTL: Some(String::from("15s")),
+                    ..Default::default()
+                },
+                AgentServiceCheck {
+                    HTTP: Some(String::from("http://localhost:8080/health")),
+                    Interval: Some(String::from("10s")),
+                    ..Default::default()
+                },
+            ]),
+            Connect: Some(AgentServiceConnect {
+                SidecarService: Some(Box::new(AgentServiceRegistration::default())),
+                ..Default::default()
+            }),
+            ..Default::default()
+        };
+
+        let json = serde_json::to_value(&registration).unwrap();
+
+        assert_eq!(
+            json,
+            serde_json::json!({
+                "ID": "web-1",
+                "Name": "web",
+                "Port": 8080,
+                "Weights": {"Passing": 10, "Warning": 1},
+                "Checks": [
+                    {"TTL": "15s"},
+                    {"HTTP": "http://localhost:8080/health", "Interval": "10s"},
+                ],
+                "Connect": {"SidecarService": {"Name": ""}},
+            })
+        );
+    }
+
+    #[test]
+    fn serialize_check_registration_test() {
+        let registration = AgentCheckRegistration {
+            ID: Some(String::from("db-tcp")),
+            ServiceID: Some(String::from("db-1")),
+            Check: AgentServiceCheck {
+                Name: Some(String::from("db port")),
+                TCP: Some(String::from("localhost:5432")),
+                Interval: Some(String::from("5s")),
+                ..Default::default()
+            },
+        };
+
+        let json = serde_json::to_value(&registration).unwrap();
+
+        assert_eq!(
+            json,
+            serde_json::json!({
+                "ID": "db-tcp",
+                "ServiceID": "db-1",
+                "Name": "db port",
+                "TCP": "localhost:5432",
+                "Interval": "5s",
+            })
+        );
+    }
 }
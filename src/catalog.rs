@@ -1,100 +1,201 @@
 use std::collections::HashMap;
 
+#[cfg(feature = "async")]
+use async_trait::async_trait;
+
 use crate::agent::{AgentCheck, AgentService};
 use crate::errors::Result;
-use crate::request::get_requests::get;
+#[cfg(feature = "async")]
+use crate::request::async_requests;
+use crate::request::get_requests::{get, get_vec};
 use crate::request::put_requests::put;
+#[cfg(feature = "async")]
+use crate::AsyncClient;
 use crate::{Client, QueryMeta, QueryOptions, WriteMeta, WriteOptions};
 
+#[derive(Clone, Eq, Default, PartialEq, Serialize, Deserialize, Debug)]
 #[serde(default)]
-#[derive(Eq, Default, PartialEq, Serialize, Deserialize, Debug)]
 pub struct Weights {
-    Passing: u32,
-    Warning: u32,
+    pub Passing: u32,
+    pub Warning: u32,
 }
 
+#[derive(Clone, Eq, Default, PartialEq, Serialize, Deserialize, Debug)]
 #[serde(default)]
-#[derive(Eq, Default, PartialEq, Serialize, Deserialize, Debug)]
 pub struct Node {
-    ID: String,
-    Node: String,
-    Address: String,
-    Datacenter: String,
-    TaggedAddresses: HashMap<String, String>,
-    Meta: HashMap<String, String>,
-    CreateIndex: u64,
-    ModifyIndex: u64,
+    pub ID: String,
+    pub Node: String,
+    pub Address: String,
+    pub Datacenter: String,
+    pub TaggedAddresses: HashMap<String, String>,
+    pub Meta: HashMap<String, String>,
+    pub CreateIndex: u64,
+    pub ModifyIndex: u64,
 }
 
+/// An instance of a service, with the node it runs on.
+#[derive(Clone, Eq, Default, PartialEq, Serialize, Deserialize, Debug)]
 #[serde(default)]
-#[derive(Eq, Default, PartialEq, Serialize, Deserialize, Debug)]
 pub struct CatalogService {
-    ID: String,
-    Node: String,
-    Address: String,
-    Datacenter: String,
-    TaggedAddresses: HashMap<String, String>,
-    NodeMeta: HashMap<String, String>,
-    ServiceID: String,
-    ServiceName: String,
-    ServiceAddress: String,
-    ServiceTags: Vec<String>,
-    ServiceMeta: HashMap<String, String>,
-    ServicePort: u32,
-    ServiceWeights: Weights,
-    ServiceEnableTagOverride: bool,
-    CreateIndex: u64,
-    ModifyIndex: u64,
+    pub ID: String,
+    pub Node: String,
+    pub Address: String,
+    pub Datacenter: String,
+    pub TaggedAddresses: HashMap<String, String>,
+    pub NodeMeta: HashMap<String, String>,
+    /// Empty for a typical service, otherwise e.g. `connect-proxy`.
+    pub ServiceKind: String,
+    pub ServiceID: String,
+    pub ServiceName: String,
+    pub ServiceAddress: String,
+    pub ServiceTags: Vec<String>,
+    pub ServiceMeta: HashMap<String, String>,
+    pub ServicePort: u32,
+    pub ServiceWeights: Weights,
+    pub ServiceEnableTagOverride: bool,
+    pub CreateIndex: u64,
+    pub ModifyIndex: u64,
 }
 
+/// A node and its services, keyed by service ID.
+#[derive(Clone, Eq, Default, PartialEq, Serialize, Deserialize, Debug)]
 #[serde(default)]
-#[derive(Eq, Default, PartialEq, Serialize, Deserialize, Debug)]
 pub struct CatalogNode {
-    Node: Option<Node>,
-    Services: HashMap<String, AgentService>,
+    pub Node: Option<Node>,
+    pub Services: HashMap<String, AgentService>,
+}
+
+/// A node and its services, as a list.
+#[derive(Clone, Eq, Default, PartialEq, Serialize, Deserialize, Debug)]
+#[serde(default)]
+pub struct CatalogNodeServiceList {
+    pub Node: Option<Node>,
+    pub Services: Vec<AgentService>,
 }
 
+/// https://www.consul.io/api-docs/catalog#register-entity
+#[derive(Clone, Eq, Default, PartialEq, Serialize, Deserialize, Debug)]
 #[serde(default)]
-#[derive(Eq, Default, PartialEq, Serialize, Deserialize, Debug)]
 pub struct CatalogRegistration {
-    ID: String,
-    Node: String,
-    Address: String,
-    TaggedAddresses: HashMap<String, String>,
-    NodeMeta: HashMap<String, String>,
-    Datacenter: String,
-    Service: Option<AgentService>,
-    Check: Option<AgentCheck>,
-    SkipNodeUpdate: bool,
+    #[serde(skip_serializing_if = "String::is_empty")]
+    pub ID: String,
+    pub Node: String,
+    pub Address: String,
+    pub TaggedAddresses: HashMap<String, String>,
+    pub NodeMeta: HashMap<String, String>,
+    #[serde(skip_serializing_if = "String::is_empty")]
+    pub Datacenter: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub Service: Option<AgentService>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub Check: Option<AgentCheck>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub Checks: Option<Vec<AgentCheck>>,
+    pub SkipNodeUpdate: bool,
 }
 
+/// https://www.consul.io/api-docs/catalog#deregister-entity
+///
+/// Removes the service `ServiceID`, else the check `CheckID`, else the whole
+/// node.
+#[derive(Clone, Eq, Default, PartialEq, Serialize, Deserialize, Debug)]
 #[serde(default)]
-#[derive(Eq, Default, PartialEq, Serialize, Deserialize, Debug)]
 pub struct CatalogDeregistration {
-    Node: String,
-    Address: String,
-    Datacenter: String,
-    ServiceID: String,
-    CheckID: String,
+    pub Node: String,
+    pub Address: String,
+    #[serde(skip_serializing_if = "String::is_empty")]
+    pub Datacenter: String,
+    #[serde(skip_serializing_if = "String::is_empty")]
+    pub ServiceID: String,
+    #[serde(skip_serializing_if = "String::is_empty")]
+    pub CheckID: String,
+}
+
+#[derive(Clone, Eq, Default, PartialEq, Serialize, Deserialize, Debug)]
+#[serde(default)]
+pub struct CompoundServiceName {
+    pub Name: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub Namespace: Option<String>,
+}
+
+/// A service routed through a terminating or ingress gateway.
+#[derive(Clone, Eq, Default, PartialEq, Serialize, Deserialize, Debug)]
+#[serde(default)]
+pub struct GatewayService {
+    pub Gateway: CompoundServiceName,
+    pub Service: CompoundServiceName,
+    /// `terminating-gateway` or `ingress-gateway`.
+    pub GatewayKind: String,
+    pub Port: u16,
+    pub Protocol: String,
+    pub Hosts: Vec<String>,
+    pub CAFile: String,
+    pub CertFile: String,
+    pub KeyFile: String,
+    pub SNI: String,
+    pub FromWildcard: bool,
+}
+
+fn tag_params(tag: Option<&str>) -> HashMap<String, String> {
+    let mut params = HashMap::new();
+    if let Some(tag) = tag {
+        params.insert(String::from("tag"), tag.to_owned());
+    }
+    params
 }
 
+/// The reads take the node metadata and filter expression of `QueryOptions`
+/// into account, see https://www.consul.io/api-docs/features/filtering.
 pub trait Catalog {
     fn register(
         &self,
         reg: &CatalogRegistration,
         q: Option<&WriteOptions>,
-    ) -> Result<((), WriteMeta)>;
+    ) -> Result<(bool, WriteMeta)>;
     fn deregister(
         &self,
         dereg: &CatalogDeregistration,
         q: Option<&WriteOptions>,
-    ) -> Result<((), WriteMeta)>;
+    ) -> Result<(bool, WriteMeta)>;
     fn datacenters(&self) -> Result<(Vec<String>, QueryMeta)>;
     fn nodes(&self, q: Option<&QueryOptions>) -> Result<(Vec<Node>, QueryMeta)>;
     fn services(
         &self,
         q: Option<&QueryOptions>,
     ) -> Result<(HashMap<String, Vec<String>>, QueryMeta)>;
+    /// The instances of the service `name`, only those tagged `tag` when given.
+    fn service(
+        &self,
+        name: &str,
+        tag: Option<&str>,
+        q: Option<&QueryOptions>,
+    ) -> Result<(Vec<CatalogService>, QueryMeta)>;
+    /// The node `name` and its services, `None` when there is no such node.
+    fn node(
+        &self,
+        name: &str,
+        q: Option<&QueryOptions>,
+    ) -> Result<(Option<CatalogNode>, QueryMeta)>;
+    /// Like `node`, with the services as a list.
+    fn node_services(
+        &self,
+        name: &str,
+        q: Option<&QueryOptions>,
+    ) -> Result<(Option<CatalogNodeServiceList>, QueryMeta)>;
+    /// The Connect capable instances of `service`: its proxies and native
+    /// instances.
+    fn connect(
+        &self,
+        service: &str,
+        q: Option<&QueryOptions>,
+    ) -> Result<(Vec<CatalogService>, QueryMeta)>;
+    /// The services behind the gateway `gateway`.
+    fn gateway_services(
+        &self,
+        gateway: &str,
+        q: Option<&QueryOptions>,
+    ) -> Result<(Vec<GatewayService>, QueryMeta)>;
 }
 
 impl Catalog for Client {
@@ -103,9 +204,9 @@ impl Catalog for Client {
         &self,
         reg: &CatalogRegistration,
         q: Option<&WriteOptions>,
-    ) -> Result<((), WriteMeta)> {
+    ) -> Result<(bool, WriteMeta)> {
         put(
-            "/v1/session/create",
+            "/v1/catalog/register",
             Some(reg),
             &self.config,
             HashMap::new(),
@@ -118,7 +219,7 @@ impl Catalog for Client {
         &self,
         dereg: &CatalogDeregistration,
         q: Option<&WriteOptions>,
-    ) -> Result<((), WriteMeta)> {
+    ) -> Result<(bool, WriteMeta)> {
         put(
             "/v1/catalog/deregister",
             Some(dereg),
@@ -149,4 +250,212 @@ impl Catalog for Client {
     ) -> Result<(HashMap<String, Vec<String>>, QueryMeta)> {
         get("/v1/catalog/services", &self.config, HashMap::new(), q)
     }
+
+    /// https://www.consul.io/api-docs/catalog#list-nodes-for-service
+    fn service(
+        &self,
+        name: &str,
+        tag: Option<&str>,
+        q: Option<&QueryOptions>,
+    ) -> Result<(Vec<CatalogService>, QueryMeta)> {
+        let path = format!("/v1/catalog/service/{}", name);
+        get_vec(&path, &self.config, tag_params(tag), q)
+    }
+
+    /// https://www.consul.io/api-docs/catalog#retrieve-map-of-services-for-a-node
+    fn node(
+        &self,
+        name: &str,
+        q: Option<&QueryOptions>,
+    ) -> Result<(Option<CatalogNode>, QueryMeta)> {
+        let path = format!("/v1/catalog/node/{}", name);
+        get(&path, &self.config, HashMap::new(), q)
+    }
+
+    /// https://www.consul.io/api-docs/catalog#list-services-for-node
+    fn node_services(
+        &self,
+        name: &str,
+        q: Option<&QueryOptions>,
+    ) -> Result<(Option<CatalogNodeServiceList>, QueryMeta)> {
+        let path = format!("/v1/catalog/node-services/{}", name);
+        get(&path, &self.config, HashMap::new(), q)
+    }
+
+    /// https://www.consul.io/api-docs/catalog#list-nodes-for-connect-capable-service
+    fn connect(
+        &self,
+        service: &str,
+        q: Option<&QueryOptions>,
+    ) -> Result<(Vec<CatalogService>, QueryMeta)> {
+        let path = format!("/v1/catalog/connect/{}", service);
+        get_vec(&path, &self.config, HashMap::new(), q)
+    }
+
+    /// https://www.consul.io/api-docs/catalog#list-services-for-gateway
+    fn gateway_services(
+        &self,
+        gateway: &str,
+        q: Option<&QueryOptions>,
+    ) -> Result<(Vec<GatewayService>, QueryMeta)> {
+        let path = format!("/v1/catalog/gateway-services/{}", gateway);
+        get_vec(&path, &self.config, HashMap::new(), q)
+    }
+}
+
+#[cfg(feature = "async")]
+#[async_trait]
+pub trait AsyncCatalog {
+    async fn register(
+        &self,
+        reg: &CatalogRegistration,
+        q: Option<&WriteOptions>,
+    ) -> Result<(bool, WriteMeta)>;
+    async fn deregister(
+        &self,
+        dereg: &CatalogDeregistration,
+        q: Option<&WriteOptions>,
+    ) -> Result<(bool, WriteMeta)>;
+    async fn datacenters(&self) -> Result<(Vec<String>, QueryMeta)>;
+    async fn nodes(&self, q: Option<&QueryOptions>) -> Result<(Vec<Node>, QueryMeta)>;
+    async fn services(
+        &self,
+        q: Option<&QueryOptions>,
+    ) -> Result<(HashMap<String, Vec<String>>, QueryMeta)>;
+    async fn service(
+        &self,
+        name: &str,
+        tag: Option<&str>,
+        q: Option<&QueryOptions>,
+    ) -> Result<(Vec<CatalogService>, QueryMeta)>;
+    async fn node(
+        &self,
+        name: &str,
+        q: Option<&QueryOptions>,
+    ) -> Result<(Option<CatalogNode>, QueryMeta)>;
+    async fn node_services(
+        &self,
+        name: &str,
+        q: Option<&QueryOptions>,
+    ) -> Result<(Option<CatalogNodeServiceList>, QueryMeta)>;
+    async fn connect(
+        &self,
+        service: &str,
+        q: Option<&QueryOptions>,
+    ) -> Result<(Vec<CatalogService>, QueryMeta)>;
+    async fn gateway_services(
+        &self,
+        gateway: &str,
+        q: Option<&QueryOptions>,
+    ) -> Result<(Vec<GatewayService>, QueryMeta)>;
+}
+
+#[cfg(feature = "async")]
+#[async_trait]
+impl AsyncCatalog for AsyncClient {
+    /// https://www.consul.io/api/catalog.html#register-entity
+    async fn register(
+        &self,
+        reg: &CatalogRegistration,
+        q: Option<&WriteOptions>,
+    ) -> Result<(bool, WriteMeta)> {
+        async_requests::put(
+            "/v1/catalog/register",
+            Some(reg),
+            &self.config,
+            HashMap::new(),
+            q,
+        )
+        .await
+    }
+
+    /// https://www.consul.io/api/catalog.html#deregister-entity
+    async fn deregister(
+        &self,
+        dereg: &CatalogDeregistration,
+        q: Option<&WriteOptions>,
+    ) -> Result<(bool, WriteMeta)> {
+        async_requests::put(
+            "/v1/catalog/deregister",
+            Some(dereg),
+            &self.config,
+            HashMap::new(),
+            q,
+        )
+        .await
+    }
+
+    /// https://www.consul.io/api/catalog.html#list-datacenters
+    async fn datacenters(&self) -> Result<(Vec<String>, QueryMeta)> {
+        async_requests::get(
+            "/v1/catalog/datacenters",
+            &self.config,
+            HashMap::new(),
+            None,
+        )
+        .await
+    }
+
+    /// https://www.consul.io/api/catalog.html#list-nodes
+    async fn nodes(&self, q: Option<&QueryOptions>) -> Result<(Vec<Node>, QueryMeta)> {
+        async_requests::get("/v1/catalog/nodes", &self.config, HashMap::new(), q).await
+    }
+
+    async fn services(
+        &self,
+        q: Option<&QueryOptions>,
+    ) -> Result<(HashMap<String, Vec<String>>, QueryMeta)> {
+        async_requests::get("/v1/catalog/services", &self.config, HashMap::new(), q).await
+    }
+
+    /// https://www.consul.io/api-docs/catalog#list-nodes-for-service
+    async fn service(
+        &self,
+        name: &str,
+        tag: Option<&str>,
+        q: Option<&QueryOptions>,
+    ) -> Result<(Vec<CatalogService>, QueryMeta)> {
+        let path = format!("/v1/catalog/service/{}", name);
+        async_requests::get_vec(&path, &self.config, tag_params(tag), q).await
+    }
+
+    /// https://www.consul.io/api-docs/catalog#retrieve-map-of-services-for-a-node
+    async fn node(
+        &self,
+        name: &str,
+        q: Option<&QueryOptions>,
+    ) -> Result<(Option<CatalogNode>, QueryMeta)> {
+        let path = format!("/v1/catalog/node/{}", name);
+        async_requests::get(&path, &self.config, HashMap::new(), q).await
+    }
+
+    /// https://www.consul.io/api-docs/catalog#list-services-for-node
+    async fn node_services(
+        &self,
+        name: &str,
+        q: Option<&QueryOptions>,
+    ) -> Result<(Option<CatalogNodeServiceList>, QueryMeta)> {
+        let path = format!("/v1/catalog/node-services/{}", name);
+        async_requests::get(&path, &self.config, HashMap::new(), q).await
+    }
+
+    /// https://www.consul.io/api-docs/catalog#list-nodes-for-connect-capable-service
+    async fn connect(
+        &self,
+        service: &str,
+        q: Option<&QueryOptions>,
+    ) -> Result<(Vec<CatalogService>, QueryMeta)> {
+        let path = format!("/v1/catalog/connect/{}", service);
+        async_requests::get_vec(&path, &self.config, HashMap::new(), q).await
+    }
+
+    /// https://www.consul.io/api-docs/catalog#list-services-for-gateway
+    async fn gateway_services(
+        &self,
+        gateway: &str,
+        q: Option<&QueryOptions>,
+    ) -> Result<(Vec<GatewayService>, QueryMeta)> {
+        let path = format!("/v1/catalog/gateway-services/{}", gateway);
+        async_requests::get_vec(&path, &self.config, HashMap::new(), q).await
+    }
 }
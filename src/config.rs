@@ -0,0 +1,406 @@
+//! Building a `Config` step by step, or from the Consul CLI's environment
+//! variables.
+
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use reqwest::Proxy;
+
+#[cfg(feature = "async")]
+use crate::build_async_http_client;
+use crate::errors::{Error, Result, ResultExt};
+use crate::retry::RetryPolicy;
+use crate::tls::{self, TlsConfig};
+use crate::{build_http_client, build_tls_transport, Config};
+
+/// Credentials sent in an `Authorization: Basic` header with every request,
+/// for agents behind an authenticating proxy.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct HttpBasicAuth {
+    pub username: String,
+    pub password: Option<String>,
+}
+
+impl HttpBasicAuth {
+    /// Parses `username` or `username:password`, the format of
+    /// `CONSUL_HTTP_AUTH`.
+    pub fn parse(value: &str) -> HttpBasicAuth {
+        match value.find(':') {
+            Some(colon) => HttpBasicAuth {
+                username: value[..colon].to_owned(),
+                password: Some(value[colon + 1..].to_owned()),
+            },
+            None => HttpBasicAuth {
+                username: value.to_owned(),
+                password: None,
+            },
+        }
+    }
+
+    pub(crate) fn header_value(&self) -> String {
+        let credentials = format!(
+            "{}:{}",
+            self.username,
+            self.password.as_deref().unwrap_or("")
+        );
+        format!("Basic {}", base64::encode(credentials))
+    }
+}
+
+/// The settings the HTTP clients in a `Config` are built with, set with the
+/// `ConfigBuilder` methods of the same names.
+#[derive(Clone, Debug, Default)]
+pub struct HttpOptions {
+    pub connect_timeout: Option<Duration>,
+    /// The deadline for a whole request, see `ConfigBuilder::request_timeout`.
+    pub request_timeout: Option<Duration>,
+    /// The longest wait for the agent to send more, see
+    /// `ConfigBuilder::read_timeout`.
+    pub read_timeout: Option<Duration>,
+    pub user_agent: Option<String>,
+    pub proxy: Option<Proxy>,
+    /// Ignores the proxy variables. Takes precedence over `proxy`.
+    pub no_proxy: bool,
+}
+
+/// Builds a `Config`, including the HTTP clients in it.
+///
+/// ```no_run
+/// use consul::config::ConfigBuilder;
+/// use std::time::Duration;
+///
+/// let config = ConfigBuilder::from_env()
+///     .unwrap()
+///     .datacenter("dc2")
+///     .connect_timeout(Duration::from_secs(2))
+///     .user_agent("billing/1.4")
+///     .build()
+///     .unwrap();
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct ConfigBuilder {
+    address: Option<String>,
+    datacenter: Option<String>,
+    namespace: Option<String>,
+    partition: Option<String>,
+    token: Option<String>,
+    token_file: Option<PathBuf>,
+    http_auth: Option<HttpBasicAuth>,
+    http_options: HttpOptions,
+    tls: TlsConfig,
+    retry_policy: RetryPolicy,
+    fallback_addresses: Vec<String>,
+}
+
+impl ConfigBuilder {
+    /// Starts from the defaults of `Config::new`.
+    pub fn new() -> ConfigBuilder {
+        ConfigBuilder::default()
+    }
+
+    /// Starts from the variables the Consul CLI reads, see `Config::new_from_env`.
+    pub fn from_env() -> Result<ConfigBuilder> {
+        ConfigBuilder::from_lookup(|name| env::var(name).ok())
+    }
+
+    pub(crate) fn from_lookup<F>(lookup: F) -> Result<ConfigBuilder>
+    where
+        F: Fn(&str) -> Option<String>,
+    {
+        let ssl = match lookup("CONSUL_HTTP_SSL") {
+            Some(val) => tls::parse_bool("CONSUL_HTTP_SSL", &val)?,
+            None => false,
+        };
+        let scheme = if ssl { "https" } else { "http" };
+        let address = match lookup("CONSUL_HTTP_ADDR") {
+            Some(val) => {
+                if val.starts_with("http") || val.starts_with("unix://") {
+                    val
+                } else {
+                    format!("{}://{}", scheme, val)
+                }
+            }
+            None => format!("{}://127.0.0.1:8500", scheme),
+        };
+        Ok(ConfigBuilder {
+            address: Some(address),
+            namespace: lookup("CONSUL_NAMESPACE"),
+            partition: lookup("CONSUL_PARTITION"),
+            token: lookup("CONSUL_HTTP_TOKEN"),
+            token_file: lookup("CONSUL_HTTP_TOKEN_FILE").map(PathBuf::from),
+            http_auth: lookup("CONSUL_HTTP_AUTH").map(|val| HttpBasicAuth::parse(&val)),
+            tls: TlsConfig::from_lookup(lookup)?,
+            ..Default::default()
+        })
+    }
+
+    /// The agent's address, e.g. `http://127.0.0.1:8500` or
+    /// `unix:///var/run/consul.sock`.
+    pub fn address<S: Into<String>>(mut self, address: S) -> Self {
+        self.address = Some(address.into());
+        self
+    }
+
+    pub fn datacenter<S: Into<String>>(mut self, datacenter: S) -> Self {
+        self.datacenter = Some(datacenter.into());
+        self
+    }
+
+    /// The namespace of every request that does not name one, Consul
+    /// Enterprise only.
+    pub fn namespace<S: Into<String>>(mut self, namespace: S) -> Self {
+        self.namespace = Some(namespace.into());
+        self
+    }
+
+    /// The admin partition of every request that does not name one, Consul
+    /// Enterprise only.
+    pub fn partition<S: Into<String>>(mut self, partition: S) -> Self {
+        self.partition = Some(partition.into());
+        self
+    }
+
+    pub fn token<S: Into<String>>(mut self, token: S) -> Self {
+        self.token = Some(token.into());
+        self
+    }
+
+    /// A file holding the ACL token, read by `build`. A token set with `token`
+    /// takes precedence.
+    pub fn token_file<P: Into<PathBuf>>(mut self, path: P) -> Self {
+        self.token_file = Some(path.into());
+        self
+    }
+
+    pub fn http_auth(mut self, auth: HttpBasicAuth) -> Self {
+        self.http_auth = Some(auth);
+        self
+    }
+
+    /// How long connecting to the agent may take.
+    pub fn connect_timeout(mut self, timeout: Duration) -> Self {
+        self.http_options.connect_timeout = Some(timeout);
+        self
+    }
+
+    /// The deadline for a whole request, from connecting until the last byte
+    /// of the response is read. It is not a read timeout: a response that
+    /// keeps trickling in is still cut off. 30 seconds by default, except for
+    /// `AsyncClient` requests over HTTP, which have none. Blocking queries are
+    /// given their own, longer deadline that covers the wait, see
+    /// `QueryOptions::wait_time`.
+    pub fn request_timeout(mut self, timeout: Duration) -> Self {
+        self.http_options.request_timeout = Some(timeout);
+        self
+    }
+
+    /// How long to wait for the agent to send anything, the start of the
+    /// response or more of it, so a stalled agent is given up on well before
+    /// the request deadline. Blocking queries wait for the start of their
+    /// response as long as their own deadline allows. None by default.
+    pub fn read_timeout(mut self, timeout: Duration) -> Self {
+        self.http_options.read_timeout = Some(timeout);
+        self
+    }
+
+    pub fn user_agent<S: Into<String>>(mut self, user_agent: S) -> Self {
+        self.http_options.user_agent = Some(user_agent.into());
+        self
+    }
+
+    /// Sends requests through `proxy` rather than the one in the `HTTP_PROXY`
+    /// and `HTTPS_PROXY` variables.
+    pub fn proxy(mut self, proxy: Proxy) -> Self {
+        self.http_options.proxy = Some(proxy);
+        self.http_options.no_proxy = false;
+        self
+    }
+
+    /// Connects to the agent directly, ignoring any proxy variables.
+    pub fn no_proxy(mut self) -> Self {
+        self.http_options.proxy = None;
+        self.http_options.no_proxy = true;
+        self
+    }
+
+    pub fn tls(mut self, tls: TlsConfig) -> Self {
+        self.tls = tls;
+        self
+    }
+
+    pub fn retry_policy(mut self, retry_policy: RetryPolicy) -> Self {
+        self.retry_policy = retry_policy;
+        self
+    }
+
+    pub fn fallback_addresses(mut self, addresses: Vec<String>) -> Self {
+        self.fallback_addresses = addresses;
+        self
+    }
+
+    pub fn build(self) -> Result<Config> {
+        let token = match (self.token, &self.token_file) {
+            (Some(token), _) => Some(token),
+            (None, Some(path)) => Some(read_token_file(path)?),
+            (None, None) => None,
+        };
+        let address = self
+            .address
+            .unwrap_or_else(|| String::from("http://localhost:8500"));
+        Ok(Config {
+            address,
+            datacenter: self.datacenter,
+            fallback_addresses: self.fallback_addresses,
+            http_auth: self.http_auth,
+            http_client: build_http_client(&self.tls, &self.http_options)?,
+            #[cfg(feature = "async")]
+            async_http_client: build_async_http_client(&self.tls, &self.http_options)?,
+            tls_transport: build_tls_transport(&self.tls, &self.http_options)?,
+            http_options: self.http_options,
+            namespace: self.namespace,
+            partition: self.partition,
+            retry_policy: self.retry_policy,
+            tls: self.tls,
+            token,
+            transport: None,
+            wait_time: None,
+        })
+    }
+}
+
+fn read_token_file(path: &Path) -> Result<String> {
+    let token = fs::read_to_string(path)
+        .chain_err(|| format!("Failed to read token file {}", path.display()))?;
+    let token = token.trim();
+    if token.is_empty() {
+        return Err(Error::from(format!(
+            "Token file {} is empty",
+            path.display()
+        )));
+    }
+    Ok(token.to_owned())
+}
+
+#[cfg(test)]
+mod config_tests {
+    use super::*;
+
+    use std::collections::HashMap;
+
+    fn lookup(vars: &[(&str, &str)]) -> impl Fn(&str) -> Option<String> {
+        let vars: HashMap<String, String> = vars
+            .iter()
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect();
+        move |name| vars.get(name).cloned()
+    }
+
+    #[test]
+    fn from_lookup_test() {
+        let config = ConfigBuilder::from_lookup(lookup(&[
+            ("CONSUL_HTTP_ADDR", "consul.service:8501"),
+            ("CONSUL_HTTP_SSL", "true"),
+            ("CONSUL_HTTP_TOKEN", "secret"),
+            ("CONSUL_HTTP_AUTH", "admin:hunter2"),
+            ("CONSUL_NAMESPACE", "team-a"),
+            ("CONSUL_PARTITION", "billing"),
+        ]))
+        .unwrap()
+        .build()
+        .unwrap();
+
+        assert_eq!(config.address, "https://consul.service:8501");
+        assert_eq!(config.token.as_deref(), Some("secret"));
+        assert_eq!(config.namespace.as_deref(), Some("team-a"));
+        assert_eq!(config.partition.as_deref(), Some("billing"));
+        assert_eq!(
+            config.http_auth,
+            Some(HttpBasicAuth {
+                username: String::from("admin"),
+                password: Some(String::from("hunter2")),
+            })
+        );
+    }
+
+    #[test]
+    fn token_file_test() {
+        let path = env::temp_dir().join(format!("consul-token-{}", std::process::id()));
+        fs::write(&path, "from-file\n").unwrap();
+
+        let from_file = ConfigBuilder::new().token_file(&path).build().unwrap();
+        let from_env = ConfigBuilder::from_lookup(lookup(&[(
+            "CONSUL_HTTP_TOKEN_FILE",
+            path.to_str().unwrap(),
+        )]))
+        .unwrap()
+        .build()
+        .unwrap();
+        let explicit = ConfigBuilder::new()
+            .token_file(&path)
+            .token("explicit")
+            .build()
+            .unwrap();
+        fs::remove_file(&path).unwrap();
+
+        assert_eq!(from_file.token.as_deref(), Some("from-file"));
+        assert_eq!(from_env.token.as_deref(), Some("from-file"));
+        assert_eq!(explicit.token.as_deref(), Some("explicit"));
+        assert!(ConfigBuilder::new().token_file(&path).build().is_err());
+    }
+
+    #[test]
+    fn http_basic_auth_test() {
+        let auth = HttpBasicAuth::parse("admin:pa:ss");
+        assert_eq!(auth.username, "admin");
+        assert_eq!(auth.password.as_deref(), Some("pa:ss"));
+        assert_eq!(auth.header_value(), "Basic YWRtaW46cGE6c3M=");
+
+        let auth = HttpBasicAuth::parse("admin");
+        assert_eq!(auth.password, None);
+        assert_eq!(auth.header_value(), "Basic YWRtaW46");
+    }
+
+    #[test]
+    fn build_defaults_test() {
+        let config = ConfigBuilder::new()
+            .request_timeout(Duration::from_secs(3))
+            .connect_timeout(Duration::from_secs(1))
+            .user_agent("tests/1.0")
+            .proxy(Proxy::all("http://proxy.internal:3128").unwrap())
+            .build()
+            .unwrap();
+
+        assert_eq!(config.address, "http://localhost:8500");
+        assert_eq!(config.token, None);
+        assert_eq!(
+            config.http_options().request_timeout,
+            Some(Duration::from_secs(3))
+        );
+        assert!(config.http_options().proxy.is_some());
+    }
+
+    #[test]
+    fn with_http_options_test() {
+        let config = ConfigBuilder::new()
+            .read_timeout(Duration::from_secs(5))
+            .build()
+            .unwrap();
+        let options = HttpOptions {
+            user_agent: Some(String::from("tests/2.0")),
+            ..config.http_options().clone()
+        };
+
+        let config = config.with_http_options(options).unwrap();
+
+        assert_eq!(
+            config.http_options().read_timeout,
+            Some(Duration::from_secs(5))
+        );
+        assert_eq!(
+            config.http_options().user_agent.as_deref(),
+            Some("tests/2.0")
+        );
+    }
+}
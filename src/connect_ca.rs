@@ -2,13 +2,20 @@ use std::collections::HashMap;
 
 use serde_json::Value;
 
+#[cfg(feature = "async")]
+use async_trait::async_trait;
+
 use crate::errors::Result;
+#[cfg(feature = "async")]
+use crate::request::async_requests;
 use crate::request::get_requests::get;
 use crate::request::put_requests::put;
+#[cfg(feature = "async")]
+use crate::AsyncClient;
 use crate::{Client, QueryMeta, QueryOptions, WriteMeta, WriteOptions};
 
-#[serde(default)]
 #[derive(Default, Serialize, Deserialize, Debug)]
+#[serde(default)]
 pub struct CAConfig {
     Provider: String,
     Config: Value,
@@ -16,16 +23,16 @@ pub struct CAConfig {
     ModifyIndex: u64,
 }
 
-#[serde(default)]
 #[derive(Default, Serialize, Deserialize, Debug)]
+#[serde(default)]
 pub struct CARootList {
     ActiveRootID: String,
     TrustDomain: String,
     Roots: Vec<CARoot>,
 }
 
-#[serde(default)]
 #[derive(Eq, Default, PartialEq, Serialize, Deserialize, Debug)]
+#[serde(default)]
 pub struct CARoot {
     ID: String,
     Name: String,
@@ -68,3 +75,51 @@ impl ConnectCA for Client {
         )
     }
 }
+
+#[cfg(feature = "async")]
+#[async_trait]
+pub trait AsyncConnectCA {
+    async fn ca_roots(&self, q: Option<&QueryOptions>) -> Result<(CARootList, QueryMeta)>;
+    async fn ca_get_config(&self, q: Option<&QueryOptions>) -> Result<(CAConfig, QueryMeta)>;
+    async fn ca_set_config(
+        &self,
+        conf: &CAConfig,
+        q: Option<&WriteOptions>,
+    ) -> Result<((), WriteMeta)>;
+}
+
+#[cfg(feature = "async")]
+#[async_trait]
+impl AsyncConnectCA for AsyncClient {
+    /// https://www.consul.io/api/connect/ca.html#list-ca-root-certificates
+    async fn ca_roots(&self, q: Option<&QueryOptions>) -> Result<(CARootList, QueryMeta)> {
+        async_requests::get("/v1/connect/ca/roots", &self.config, HashMap::new(), q).await
+    }
+
+    /// https://www.consul.io/api/connect/ca.html#get-ca-configuration
+    async fn ca_get_config(&self, q: Option<&QueryOptions>) -> Result<(CAConfig, QueryMeta)> {
+        async_requests::get(
+            "/v1/connect/ca/configuration",
+            &self.config,
+            HashMap::new(),
+            q,
+        )
+        .await
+    }
+
+    /// https://www.consul.io/api/connect/ca.html#update-ca-configuration
+    async fn ca_set_config(
+        &self,
+        conf: &CAConfig,
+        q: Option<&WriteOptions>,
+    ) -> Result<((), WriteMeta)> {
+        async_requests::put(
+            "/v1/connect/ca/configuration",
+            Some(conf),
+            &self.config,
+            HashMap::new(),
+            q,
+        )
+        .await
+    }
+}
@@ -0,0 +1,190 @@
+use std::collections::HashMap;
+use std::time::Duration;
+
+#[cfg(feature = "async")]
+use async_trait::async_trait;
+
+use crate::errors::Result;
+#[cfg(feature = "async")]
+use crate::request::async_requests;
+use crate::request::get_requests::get_vec;
+#[cfg(feature = "async")]
+use crate::AsyncClient;
+use crate::{Client, QueryMeta, QueryOptions};
+
+/// A node's position in Consul's network tomography, see
+/// https://www.consul.io/docs/architecture/coordinates.
+#[derive(Clone, Default, PartialEq, Serialize, Deserialize, Debug)]
+#[serde(default)]
+pub struct Coord {
+    pub Vec: Vec<f64>,
+    pub Error: f64,
+    pub Adjustment: f64,
+    pub Height: f64,
+}
+
+impl Coord {
+    /// The estimated round trip time between the two nodes, computed like
+    /// `ComputeDistance` in Consul.
+    pub fn distance_to(&self, other: &Coord) -> Duration {
+        let euclidean: f64 = self
+            .Vec
+            .iter()
+            .zip(&other.Vec)
+            .map(|(a, b)| (a - b) * (a - b))
+            .sum::<f64>()
+            .sqrt();
+        let distance = euclidean + self.Height + other.Height;
+        let adjusted = distance + self.Adjustment + other.Adjustment;
+        let seconds = if adjusted > 0.0 { adjusted } else { distance };
+        Duration::from_secs_f64(seconds.max(0.0))
+    }
+}
+
+#[derive(Clone, Default, PartialEq, Serialize, Deserialize, Debug)]
+#[serde(default)]
+pub struct CoordinateEntry {
+    pub Node: String,
+    pub Segment: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub Partition: Option<String>,
+    pub Coord: Option<Coord>,
+}
+
+#[derive(Clone, Default, PartialEq, Serialize, Deserialize, Debug)]
+#[serde(default)]
+pub struct CoordinateDatacenterMap {
+    pub Datacenter: String,
+    pub AreaID: String,
+    pub Coordinates: Vec<CoordinateEntry>,
+}
+
+pub trait Coordinate {
+    /// The WAN coordinates of the servers of every known datacenter.
+    fn datacenters(&self) -> Result<(Vec<CoordinateDatacenterMap>, QueryMeta)>;
+    /// The LAN coordinates of every node in the datacenter.
+    fn nodes(&self, q: Option<&QueryOptions>) -> Result<(Vec<CoordinateEntry>, QueryMeta)>;
+    /// The LAN coordinates of `node`, one per network segment.
+    fn node(
+        &self,
+        node: &str,
+        q: Option<&QueryOptions>,
+    ) -> Result<(Vec<CoordinateEntry>, QueryMeta)>;
+}
+
+impl Coordinate for Client {
+    /// https://www.consul.io/api-docs/coordinate#read-wan-coordinates
+    fn datacenters(&self) -> Result<(Vec<CoordinateDatacenterMap>, QueryMeta)> {
+        get_vec(
+            "/v1/coordinate/datacenters",
+            &self.config,
+            HashMap::new(),
+            None,
+        )
+    }
+
+    /// https://www.consul.io/api-docs/coordinate#read-lan-coordinates-for-all-nodes
+    fn nodes(&self, q: Option<&QueryOptions>) -> Result<(Vec<CoordinateEntry>, QueryMeta)> {
+        get_vec("/v1/coordinate/nodes", &self.config, HashMap::new(), q)
+    }
+
+    /// https://www.consul.io/api-docs/coordinate#read-lan-coordinates-for-a-node
+    fn node(
+        &self,
+        node: &str,
+        q: Option<&QueryOptions>,
+    ) -> Result<(Vec<CoordinateEntry>, QueryMeta)> {
+        let path = format!("/v1/coordinate/node/{}", node);
+        get_vec(&path, &self.config, HashMap::new(), q)
+    }
+}
+
+#[cfg(feature = "async")]
+#[async_trait]
+pub trait AsyncCoordinate {
+    async fn datacenters(&self) -> Result<(Vec<CoordinateDatacenterMap>, QueryMeta)>;
+    async fn nodes(&self, q: Option<&QueryOptions>) -> Result<(Vec<CoordinateEntry>, QueryMeta)>;
+    async fn node(
+        &self,
+        node: &str,
+        q: Option<&QueryOptions>,
+    ) -> Result<(Vec<CoordinateEntry>, QueryMeta)>;
+}
+
+#[cfg(feature = "async")]
+#[async_trait]
+impl AsyncCoordinate for AsyncClient {
+    async fn datacenters(&self) -> Result<(Vec<CoordinateDatacenterMap>, QueryMeta)> {
+        async_requests::get_vec(
+            "/v1/coordinate/datacenters",
+            &self.config,
+            HashMap::new(),
+            None,
+        )
+        .await
+    }
+
+    async fn nodes(&self, q: Option<&QueryOptions>) -> Result<(Vec<CoordinateEntry>, QueryMeta)> {
+        async_requests::get_vec("/v1/coordinate/nodes", &self.config, HashMap::new(), q).await
+    }
+
+    async fn node(
+        &self,
+        node: &str,
+        q: Option<&QueryOptions>,
+    ) -> Result<(Vec<CoordinateEntry>, QueryMeta)> {
+        let path = format!("/v1/coordinate/node/{}", node);
+        async_requests::get_vec(&path, &self.config, HashMap::new(), q).await
+    }
+}
+
+#[cfg(test)]
+mod coordinate_tests {
+    use super::*;
+
+    fn coord(vec: &[f64], height: f64, adjustment: f64) -> Coord {
+        Coord {
+            Vec: vec.to_vec(),
+            Height: height,
+            Adjustment: adjustment,
+            ..Default::default()
+        }
+    }
+
+    fn assert_millis(rtt: Duration, millis: f64) {
+        assert!(
+            (rtt.as_secs_f64() * 1000.0 - millis).abs() < 1e-6,
+            "{:?}",
+            rtt
+        );
+    }
+
+    #[test]
+    fn distance_to_test() {
+        let a = coord(&[0.0, 0.0], 0.001, 0.0);
+        let b = coord(&[0.003, 0.004], 0.001, 0.0);
+        assert_millis(a.distance_to(&b), 7.0);
+        assert_eq!(a.distance_to(&b), b.distance_to(&a));
+    }
+
+    #[test]
+    fn distance_to_negative_adjustment_test() {
+        let a = coord(&[0.0], 0.0, -0.002);
+        let b = coord(&[0.001], 0.0, 0.0);
+        // An adjustment that would make the distance negative is ignored.
+        assert_millis(a.distance_to(&b), 1.0);
+
+        let c = coord(&[0.005], 0.0, 0.0);
+        assert_millis(a.distance_to(&c), 3.0);
+    }
+
+    #[test]
+    fn coordinate_entry_serde_test() {
+        let entry: CoordinateEntry = serde_json::from_str(
+            r#"{"Node": "web-1", "Segment": "", "Coord": {"Vec": [0.5, -0.25], "Error": 1.5, "Adjustment": 0, "Height": 0.00001}}"#,
+        )
+        .unwrap();
+        assert_eq!(entry.Node, "web-1");
+        assert_eq!(entry.Coord.unwrap().Vec, vec![0.5, -0.25]);
+    }
+}
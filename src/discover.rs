@@ -0,0 +1,354 @@
+//! Service discovery for tower and hyper clients, behind the `discover`
+//! feature.
+//!
+//! `ConsulDiscover` is a `tower::discover::Discover` over the instances of a
+//! service, e.g. for `tonic::transport::Channel::balance`. `ConsulResolver`
+//! is a resolver for hyper's `HttpConnector` that looks host names up as
+//! service names.
+
+use std::collections::{BTreeMap, BTreeSet, VecDeque};
+use std::future::Future;
+use std::io;
+use std::net::{IpAddr, SocketAddr};
+use std::pin::Pin;
+use std::task::{Context, Poll};
+use std::vec;
+
+use hyper::client::connect::dns::Name;
+use hyper::service::Service;
+use rand::seq::SliceRandom;
+use rand::thread_rng;
+use tower_discover::{Change, Discover};
+
+use crate::errors::{Error, Result};
+use crate::health::{AsyncHealth, ServiceEntry};
+use crate::resolver::{entry_host, pick_weight, Instance};
+use crate::watch::{backoff, DEFAULT_MAX_BACKOFF, DEFAULT_MIN_BACKOFF};
+use crate::{AsyncClient, QueryMeta, QueryOptions};
+
+type Query = Pin<Box<dyn Future<Output = Result<(Vec<Instance>, QueryMeta)>> + Send>>;
+
+/// The instances of a service as a `Discover`, keyed by address.
+///
+/// A blocking query on `/v1/health/service` runs for as long as the
+/// `ConsulDiscover` is polled. Each time its index changes, the instances that
+/// are gone are removed and the new ones are inserted, with the service
+/// `make` builds for them. Instances are picked like for a
+/// `resolver::ServiceResolver`: passing or warning, with a weight above 0.
+///
+/// A failed query is yielded as an error and repeated after a backoff the
+/// next time the `ConsulDiscover` is polled.
+pub struct ConsulDiscover<F> {
+    client: AsyncClient,
+    service: String,
+    tag: Option<String>,
+    options: QueryOptions,
+    make: F,
+    last_index: Option<u64>,
+    failures: u32,
+    known: BTreeSet<SocketAddr>,
+    changes: VecDeque<Change<SocketAddr, Instance>>,
+    query: Option<Query>,
+}
+
+impl<F, S> ConsulDiscover<F>
+where
+    F: FnMut(&Instance) -> S,
+{
+    pub fn new(client: AsyncClient, service: &str, make: F) -> Self {
+        ConsulDiscover {
+            client,
+            service: service.to_owned(),
+            tag: None,
+            options: QueryOptions::default(),
+            make,
+            last_index: None,
+            failures: 0,
+            known: BTreeSet::new(),
+            changes: VecDeque::new(),
+            query: None,
+        }
+    }
+
+    /// Only discovers the instances tagged `tag`.
+    pub fn with_tag(mut self, tag: &str) -> Self {
+        self.tag = Some(tag.to_owned());
+        self
+    }
+
+    /// The options of the blocking queries, e.g. their datacenter and wait.
+    pub fn with_query_options(mut self, options: QueryOptions) -> Self {
+        self.options = options;
+        self
+    }
+
+    fn start_query(&self) -> Query {
+        let client = self.client.clone();
+        let service = self.service.clone();
+        let tag = self.tag.clone();
+        let mut options = self.options.clone();
+        options.wait_index = self.last_index;
+        let delay = if self.failures > 0 {
+            Some(backoff(
+                self.failures,
+                DEFAULT_MIN_BACKOFF,
+                DEFAULT_MAX_BACKOFF,
+            ))
+        } else {
+            None
+        };
+        Box::pin(async move {
+            if let Some(delay) = delay {
+                tokio::time::delay_for(delay).await;
+            }
+            let (entries, meta) = client
+                .service(&service, tag.as_deref(), false, Some(&options))
+                .await?;
+            Ok((instances_of(&entries).await, meta))
+        })
+    }
+
+    /// Queues the changes from the known instances to `instances`.
+    fn update(&mut self, instances: Vec<Instance>) {
+        let current: BTreeMap<SocketAddr, Instance> = instances
+            .into_iter()
+            .map(|instance| (instance.addr, instance))
+            .collect();
+        for addr in &self.known {
+            if !current.contains_key(addr) {
+                self.changes.push_back(Change::Remove(*addr));
+            }
+        }
+        for (addr, instance) in &current {
+            if !self.known.contains(addr) {
+                self.changes
+                    .push_back(Change::Insert(*addr, instance.clone()));
+            }
+        }
+        self.known = current.into_keys().collect();
+    }
+}
+
+impl<F, S> Discover for ConsulDiscover<F>
+where
+    F: FnMut(&Instance) -> S + Unpin,
+{
+    type Key = SocketAddr;
+    type Service = S;
+    type Error = Error;
+
+    fn poll_discover(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Result<Change<SocketAddr, S>>> {
+        let this = self.get_mut();
+        loop {
+            match this.changes.pop_front() {
+                Some(Change::Insert(addr, instance)) => {
+                    return Poll::Ready(Ok(Change::Insert(addr, (this.make)(&instance))));
+                }
+                Some(Change::Remove(addr)) => return Poll::Ready(Ok(Change::Remove(addr))),
+                None => {}
+            }
+
+            if this.query.is_none() {
+                this.query = Some(this.start_query());
+            }
+            let result = match this.query.as_mut().unwrap().as_mut().poll(cx) {
+                Poll::Ready(result) => result,
+                Poll::Pending => return Poll::Pending,
+            };
+            this.query = None;
+
+            let (instances, meta) = match result {
+                Ok(result) => result,
+                Err(e) => {
+                    this.failures += 1;
+                    return Poll::Ready(Err(e));
+                }
+            };
+            match meta.last_index {
+                // As in `Watch`, the index is never sent as 0.
+                Some(index) => this.last_index = Some(index.max(1)),
+                None => {
+                    this.failures += 1;
+                    return Poll::Ready(Err(Error::from(
+                        "Watched endpoint did not return an X-Consul-Index",
+                    )));
+                }
+            }
+            this.failures = 0;
+            this.update(instances);
+        }
+    }
+}
+
+/// The instances `entries` describe, like `Instance::from_entry` but with host
+/// names looked up without blocking the executor.
+async fn instances_of(entries: &[ServiceEntry]) -> Vec<Instance> {
+    let mut instances = Vec::new();
+    for entry in entries {
+        if pick_weight(entry).is_none() {
+            continue;
+        }
+        let host = entry_host(entry);
+        let port = entry.Service.Port;
+        let addr = match host.parse::<IpAddr>() {
+            Ok(ip) => SocketAddr::new(ip, port),
+            Err(_) => {
+                let addrs = tokio::net::lookup_host((host, port)).await;
+                match addrs.ok().and_then(|mut addrs| addrs.next()) {
+                    Some(addr) => addr,
+                    None => continue,
+                }
+            }
+        };
+        instances.extend(Instance::at(entry, addr));
+    }
+    instances
+}
+
+/// A hyper resolver that looks host names up as Consul services.
+///
+/// `http://billing:8080/` connects to one of the passing or warning instances
+/// of `billing`, tried in random order. hyper takes the port from the URI, so
+/// the instances' ports are not used.
+///
+/// ```no_run
+/// use consul::discover::ConsulResolver;
+/// use consul::{AsyncClient, Config};
+/// use hyper::client::HttpConnector;
+///
+/// let client = AsyncClient::new(Config::new().unwrap());
+/// let connector = HttpConnector::new_with_resolver(ConsulResolver::new(client));
+/// let http = hyper::Client::builder().build::<_, hyper::Body>(connector);
+/// ```
+#[derive(Clone, Debug)]
+pub struct ConsulResolver {
+    client: AsyncClient,
+    tag: Option<String>,
+    options: QueryOptions,
+}
+
+impl ConsulResolver {
+    pub fn new(client: AsyncClient) -> Self {
+        ConsulResolver {
+            client,
+            tag: None,
+            options: QueryOptions::default(),
+        }
+    }
+
+    /// Only resolves to the instances tagged `tag`.
+    pub fn with_tag(mut self, tag: &str) -> Self {
+        self.tag = Some(tag.to_owned());
+        self
+    }
+
+    /// The options of the queries, e.g. their datacenter.
+    pub fn with_query_options(mut self, options: QueryOptions) -> Self {
+        self.options = options;
+        self
+    }
+}
+
+impl Service<Name> for ConsulResolver {
+    type Response = vec::IntoIter<IpAddr>;
+    type Error = io::Error;
+    type Future = Pin<Box<dyn Future<Output = io::Result<Self::Response>> + Send>>;
+
+    fn poll_ready(&mut self, _: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn call(&mut self, name: Name) -> Self::Future {
+        let client = self.client.clone();
+        let tag = self.tag.clone();
+        let options = self.options.clone();
+        Box::pin(async move {
+            let (entries, _) = client
+                .service(name.as_str(), tag.as_deref(), false, Some(&options))
+                .await
+                .map_err(|e| io::Error::other(e.to_string()))?;
+            let mut ips: Vec<IpAddr> = instances_of(&entries)
+                .await
+                .iter()
+                .map(|instance| instance.addr.ip())
+                .collect();
+            if ips.is_empty() {
+                return Err(io::Error::new(
+                    io::ErrorKind::NotFound,
+                    format!("No healthy instances of {}", name),
+                ));
+            }
+            ips.shuffle(&mut thread_rng());
+            Ok(ips.into_iter())
+        })
+    }
+}
+
+#[cfg(test)]
+mod discover_tests {
+    use super::*;
+
+    use crate::agent::AgentService;
+    use crate::health::Node;
+    use crate::Config;
+
+    fn entry(id: &str, address: &str) -> ServiceEntry {
+        ServiceEntry {
+            Node: Node {
+                Node: String::from("node-1"),
+                Address: address.to_owned(),
+                ..Default::default()
+            },
+            Service: AgentService {
+                ID: id.to_owned(),
+                Port: 9000,
+                ..Default::default()
+            },
+            Checks: Vec::new(),
+        }
+    }
+
+    fn instances(entries: &[ServiceEntry]) -> Vec<Instance> {
+        entries.iter().filter_map(Instance::from_entry).collect()
+    }
+
+    fn key(change: &Change<SocketAddr, Instance>) -> (&'static str, String) {
+        match change {
+            Change::Insert(addr, _) => ("insert", addr.to_string()),
+            Change::Remove(addr) => ("remove", addr.to_string()),
+        }
+    }
+
+    #[test]
+    fn update_test() {
+        let client = AsyncClient::new(Config::new().unwrap());
+        let mut discover = ConsulDiscover::new(client, "web", |i: &Instance| i.id.clone());
+
+        discover.update(instances(&[entry("a", "10.0.0.1"), entry("b", "10.0.0.2")]));
+        discover.update(instances(&[entry("b", "10.0.0.2"), entry("c", "10.0.0.3")]));
+        discover.update(instances(&[entry("b", "10.0.0.2"), entry("c", "10.0.0.3")]));
+
+        let changes: Vec<_> = discover.changes.iter().map(key).collect();
+        assert_eq!(
+            changes,
+            vec![
+                ("insert", String::from("10.0.0.1:9000")),
+                ("insert", String::from("10.0.0.2:9000")),
+                ("remove", String::from("10.0.0.1:9000")),
+                ("insert", String::from("10.0.0.3:9000")),
+            ]
+        );
+    }
+    #[tokio::test]
+    async fn instances_of_host_name_test() {
+        let instances = instances_of(&[entry("a", "10.0.0.1"), entry("b", "localhost")]).await;
+
+        assert_eq!(instances.len(), 2);
+        assert_eq!(instances[0].addr.to_string(), "10.0.0.1:9000");
+        assert!(instances[1].addr.ip().is_loopback());
+        assert_eq!(instances[1].addr.port(), 9000);
+    }
+}
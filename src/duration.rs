@@ -0,0 +1,96 @@
+//! Durations in Go's format, which Consul uses for TTLs and intervals.
+
+use std::time::Duration;
+
+use crate::errors::{Error, Result};
+
+/// Parses a Go style duration such as `15s`, `1m30s` or `500ms`. Durations
+/// too long for a `Duration` are an error.
+pub(crate) fn parse_duration(s: &str) -> Result<Duration> {
+    let invalid = || Error::from(format!("Invalid duration {:?}", s));
+    let mut total = Duration::from_secs(0);
+    let mut rest = s.trim();
+    if rest.is_empty() {
+        return Err(invalid());
+    }
+    while !rest.is_empty() {
+        let number_len = rest
+            .find(|c: char| !(c.is_ascii_digit() || c == '.'))
+            .ok_or_else(invalid)?;
+        let value: f64 = rest[..number_len].parse().map_err(|_| invalid())?;
+        rest = &rest[number_len..];
+        let unit_len = rest
+            .find(|c: char| c.is_ascii_digit() || c == '.')
+            .unwrap_or(rest.len());
+        let unit_secs = match &rest[..unit_len] {
+            "ns" => 1e-9,
+            "us" | "µs" => 1e-6,
+            "ms" => 1e-3,
+            "s" => 1.0,
+            "m" => 60.0,
+            "h" => 3600.0,
+            _ => return Err(invalid()),
+        };
+        rest = &rest[unit_len..];
+        total = Duration::try_from_secs_f64(value * unit_secs)
+            .ok()
+            .and_then(|part| total.checked_add(part))
+            .ok_or_else(invalid)?;
+    }
+    Ok(total)
+}
+
+/// Formats `duration` in whole seconds when possible and without losing
+/// precision otherwise, e.g. `15s`, `10500ms` or `10000000001ns`.
+pub(crate) fn format_duration(duration: Duration) -> String {
+    if duration.subsec_nanos() == 0 {
+        format!("{}s", duration.as_secs())
+    } else if duration.subsec_millis() * 1_000_000 == duration.subsec_nanos() {
+        format!("{}ms", duration.as_millis())
+    } else {
+        format!("{}ns", duration.as_nanos())
+    }
+}
+
+#[cfg(test)]
+mod duration_tests {
+    use super::*;
+
+    #[test]
+    fn parse_duration_test() {
+        assert_eq!(parse_duration("15s").unwrap(), Duration::from_secs(15));
+        assert_eq!(parse_duration("1m30s").unwrap(), Duration::from_secs(90));
+        assert_eq!(parse_duration("500ms").unwrap(), Duration::from_millis(500));
+        assert_eq!(parse_duration("1.5h").unwrap(), Duration::from_secs(5400));
+        assert!(parse_duration("").is_err());
+        assert!(parse_duration("15").is_err());
+        assert!(parse_duration("15 seconds").is_err());
+    }
+
+    #[test]
+    fn parse_duration_overflow_test() {
+        assert!(parse_duration("1e300h").is_err());
+        assert!(parse_duration("NaNs").is_err());
+        assert!(parse_duration(&format!("{}h", "9".repeat(400))).is_err());
+        assert!(parse_duration("10000000000000000000s10000000000000000000s").is_err());
+    }
+
+    #[test]
+    fn format_duration_test() {
+        assert_eq!(format_duration(Duration::from_secs(15)), "15s");
+        assert_eq!(format_duration(Duration::from_millis(10_500)), "10500ms");
+        assert_eq!(format_duration(Duration::new(10, 1)), "10000000001ns");
+    }
+
+    #[test]
+    fn formatted_duration_parses_back_test() {
+        for duration in &[
+            Duration::from_secs(15),
+            Duration::from_millis(10_500),
+            Duration::new(10, 1),
+        ] {
+            let formatted = format_duration(*duration);
+            assert_eq!(parse_duration(&formatted).unwrap(), *duration);
+        }
+    }
+}
@@ -3,9 +3,47 @@ error_chain! {
         BadUrl{
             description("")
         }
-        ConsulError{
-            description("")
+        ConnectFailed(address: String) {
+            description("Failed to connect to consul")
+            display("Failed to connect to {}", address)
+        }
+        ConsulError(status: u16, body: String, path: String) {
+            description("Consul returned an error response")
+            display("Consul request to {} failed with status {}: {}", path, status, body)
+        }
+    }
+
+}
+
+impl ErrorKind {
+    /// The HTTP status code Consul responded with, if this is a `ConsulError`.
+    pub fn status(&self) -> Option<u16> {
+        match self {
+            ErrorKind::ConsulError(status, _, _) => Some(*status),
+            _ => None,
         }
     }
 
+    /// 403, e.g. a missing or insufficient ACL token.
+    pub fn is_permission_denied(&self) -> bool {
+        self.status() == Some(403)
+    }
+
+    /// 404, the requested entity does not exist.
+    pub fn is_not_found(&self) -> bool {
+        self.status() == Some(404)
+    }
+
+    /// 429, the agent is rate limiting requests.
+    pub fn is_rate_limited(&self) -> bool {
+        self.status() == Some(429)
+    }
+
+    /// Any 5xx, e.g. "No cluster leader".
+    pub fn is_server_error(&self) -> bool {
+        match self.status() {
+            Some(status) => (500..600).contains(&status),
+            None => false,
+        }
+    }
 }
@@ -0,0 +1,296 @@
+use std::collections::{HashMap, VecDeque};
+
+#[cfg(feature = "async")]
+use async_trait::async_trait;
+use serde::de::Error as _;
+use serde::{Deserialize, Deserializer, Serializer};
+
+use crate::errors::Result;
+#[cfg(feature = "async")]
+use crate::request::async_requests;
+use crate::request::get_requests::get_vec;
+use crate::request::put_requests::put_raw;
+use crate::watch::Watch;
+#[cfg(feature = "async")]
+use crate::AsyncClient;
+use crate::{Client, QueryMeta, QueryOptions, WriteMeta, WriteOptions};
+
+/// An event fired with `Event::fire` or `consul event`.
+#[derive(Clone, Default, Eq, PartialEq, Serialize, Deserialize, Debug)]
+#[serde(default)]
+pub struct UserEvent {
+    pub ID: String,
+    pub Name: String,
+    #[serde(
+        deserialize_with = "deserialize_payload",
+        serialize_with = "serialize_payload"
+    )]
+    pub Payload: Vec<u8>,
+    pub NodeFilter: String,
+    pub ServiceFilter: String,
+    pub TagFilter: String,
+    pub Version: u32,
+    pub LTime: u64,
+}
+
+/// Which agents an event is delivered to. Node and service are regular
+/// expressions, and a tag only applies together with a service.
+#[derive(Clone, Default, Eq, PartialEq, Debug)]
+pub struct EventFilter {
+    pub node: Option<String>,
+    pub service: Option<String>,
+    pub tag: Option<String>,
+}
+
+impl EventFilter {
+    fn params(&self) -> HashMap<String, String> {
+        let mut params = HashMap::new();
+        let filters = [
+            ("node", &self.node),
+            ("service", &self.service),
+            ("tag", &self.tag),
+        ];
+        for (name, value) in filters.iter() {
+            if let Some(value) = value {
+                params.insert(String::from(*name), value.clone());
+            }
+        }
+        params
+    }
+}
+
+/// The index Consul answers an event list with when `id` is the ID of its
+/// last event: the upper and lower halves of the UUID XORed, as `IDToIndex` in
+/// the Go client. `None` if `id` is not a UUID.
+pub fn id_to_index(id: &str) -> Option<u64> {
+    if id.len() != 36 {
+        return None;
+    }
+    let lower = format!("{}{}{}", id.get(0..8)?, id.get(9..13)?, id.get(14..18)?);
+    let upper = format!("{}{}", id.get(19..23)?, id.get(24..36)?);
+    let lower = u64::from_str_radix(&lower, 16).ok()?;
+    let upper = u64::from_str_radix(&upper, 16).ok()?;
+    Some(lower ^ upper)
+}
+
+fn list_params(name: Option<&str>) -> HashMap<String, String> {
+    let mut params = HashMap::new();
+    if let Some(name) = name {
+        params.insert(String::from("name"), name.to_owned());
+    }
+    params
+}
+
+pub trait Event {
+    /// Fires the event `name` with `payload` to the agents `filter` matches.
+    fn fire(
+        &self,
+        name: &str,
+        payload: &[u8],
+        filter: &EventFilter,
+        options: Option<&WriteOptions>,
+    ) -> Result<(UserEvent, WriteMeta)>;
+    /// The recent events the agent has seen, oldest first, only those named
+    /// `name` when given.
+    fn list(
+        &self,
+        name: Option<&str>,
+        options: Option<&QueryOptions>,
+    ) -> Result<(Vec<UserEvent>, QueryMeta)>;
+}
+
+impl Event for Client {
+    /// https://www.consul.io/api-docs/event#fire-event
+    fn fire(
+        &self,
+        name: &str,
+        payload: &[u8],
+        filter: &EventFilter,
+        options: Option<&WriteOptions>,
+    ) -> Result<(UserEvent, WriteMeta)> {
+        let path = format!("/v1/event/fire/{}", name);
+        put_raw(&path, payload, &self.config, filter.params(), options)
+    }
+
+    /// https://www.consul.io/api-docs/event#list-events
+    fn list(
+        &self,
+        name: Option<&str>,
+        options: Option<&QueryOptions>,
+    ) -> Result<(Vec<UserEvent>, QueryMeta)> {
+        get_vec("/v1/event/list", &self.config, list_params(name), options)
+    }
+}
+
+#[cfg(feature = "async")]
+#[async_trait]
+pub trait AsyncEvent {
+    async fn fire(
+        &self,
+        name: &str,
+        payload: &[u8],
+        filter: &EventFilter,
+        options: Option<&WriteOptions>,
+    ) -> Result<(UserEvent, WriteMeta)>;
+    async fn list(
+        &self,
+        name: Option<&str>,
+        options: Option<&QueryOptions>,
+    ) -> Result<(Vec<UserEvent>, QueryMeta)>;
+}
+
+#[cfg(feature = "async")]
+#[async_trait]
+impl AsyncEvent for AsyncClient {
+    async fn fire(
+        &self,
+        name: &str,
+        payload: &[u8],
+        filter: &EventFilter,
+        options: Option<&WriteOptions>,
+    ) -> Result<(UserEvent, WriteMeta)> {
+        let path = format!("/v1/event/fire/{}", name);
+        async_requests::put_raw(&path, payload, &self.config, filter.params(), options).await
+    }
+
+    async fn list(
+        &self,
+        name: Option<&str>,
+        options: Option<&QueryOptions>,
+    ) -> Result<(Vec<UserEvent>, QueryMeta)> {
+        async_requests::get_vec("/v1/event/list", &self.config, list_params(name), options).await
+    }
+}
+
+/// Yields the events fired after the watch started, one at a time.
+///
+/// The index of an event list is derived from the ID of its last event (see
+/// `id_to_index`), so it does not grow like other indexes. After each change
+/// the events up to the one the previous index stands for have been seen; if
+/// that event has dropped out of the agent's buffer, every listed event is
+/// yielded.
+pub struct EventWatch<'a> {
+    watch: Watch<'a, Vec<UserEvent>>,
+    pending: VecDeque<UserEvent>,
+}
+
+impl<'a> EventWatch<'a> {
+    /// Watches the events named `name`, or all events.
+    pub fn new(client: &'a Client, name: Option<&str>, options: Option<QueryOptions>) -> Self {
+        let name = name.map(str::to_owned);
+        EventWatch {
+            watch: Watch::new(move |o| client.list(name.as_deref(), o), options),
+            pending: VecDeque::new(),
+        }
+    }
+}
+
+impl<'a> Iterator for EventWatch<'a> {
+    type Item = Result<UserEvent>;
+
+    fn next(&mut self) -> Option<Result<UserEvent>> {
+        loop {
+            if let Some(event) = self.pending.pop_front() {
+                return Some(Ok(event));
+            }
+            let previous = self.watch.last_index();
+            let events = match self.watch.next()? {
+                Ok(events) => events,
+                Err(e) => return Some(Err(e)),
+            };
+            // The first list holds the events fired before the watch started.
+            if let Some(previous) = previous {
+                self.pending.extend(new_events(events, previous));
+            }
+        }
+    }
+}
+
+/// The events after the one whose index is `previous`.
+fn new_events(mut events: Vec<UserEvent>, previous: u64) -> Vec<UserEvent> {
+    let seen = events
+        .iter()
+        .rposition(|e| id_to_index(&e.ID) == Some(previous))
+        .map_or(0, |last_seen| last_seen + 1);
+    events.split_off(seen)
+}
+
+fn deserialize_payload<'de, D>(deserializer: D) -> std::result::Result<Vec<u8>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let raw_base64_string: Option<String> = Deserialize::deserialize(deserializer)?;
+    match raw_base64_string {
+        Some(s) => base64::decode(&s).map_err(D::Error::custom),
+        None => Ok(Vec::new()),
+    }
+}
+
+fn serialize_payload<S>(value: &[u8], serializer: S) -> std::result::Result<S::Ok, S::Error>
+where
+    S: Serializer,
+{
+    serializer.serialize_str(&base64::encode(value))
+}
+
+#[cfg(test)]
+mod event_tests {
+    use super::*;
+
+    const ID: &str = "b54fe110-7af5-cafc-d1fb-afc8ba432b1c";
+
+    fn event(id: &str) -> UserEvent {
+        UserEvent {
+            ID: id.to_owned(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn id_to_index_test() {
+        assert_eq!(id_to_index(ID), Some(7_256_511_592_453_824_992));
+        assert_eq!(id_to_index("b54fe110"), None);
+        assert_eq!(id_to_index("zzzzzzzz-7af5-cafc-d1fb-afc8ba432b1c"), None);
+    }
+
+    #[test]
+    fn deserialize_payload_test() {
+        let event: UserEvent = serde_json::from_str(
+            r#"{"ID": "b54fe110-7af5-cafc-d1fb-afc8ba432b1c", "Name": "deploy", "Payload": "Zmx1c2g=", "LTime": 3}"#,
+        )
+        .unwrap();
+        assert_eq!(event.Payload, b"flush");
+        assert_eq!(event.LTime, 3);
+
+        let event: UserEvent =
+            serde_json::from_str(r#"{"Name": "deploy", "Payload": null}"#).unwrap();
+        assert!(event.Payload.is_empty());
+    }
+
+    #[test]
+    fn filter_params_test() {
+        let filter = EventFilter {
+            service: Some(String::from("web")),
+            tag: Some(String::from("v2")),
+            ..Default::default()
+        };
+        let params = filter.params();
+        assert_eq!(params.len(), 2);
+        assert_eq!(params["service"], "web");
+        assert_eq!(params["tag"], "v2");
+    }
+
+    #[test]
+    fn new_events_test() {
+        let first = "00000000-0000-0000-0000-000000000001";
+        let second = "00000000-0000-0000-0000-000000000002";
+        let third = "00000000-0000-0000-0000-000000000003";
+        let events = vec![event(first), event(second), event(third)];
+
+        let after_first = new_events(events.clone(), id_to_index(first).unwrap());
+        assert_eq!(after_first, vec![event(second), event(third)]);
+        assert!(new_events(events.clone(), id_to_index(third).unwrap()).is_empty());
+        // The last seen event dropped out of the buffer.
+        assert_eq!(new_events(events.clone(), 1 << 40).len(), 3);
+    }
+}
@@ -1,17 +1,91 @@
 use std::collections::HashMap;
+use std::fmt;
+
+#[cfg(feature = "async")]
+use async_trait::async_trait;
 
 use crate::agent::AgentService;
 use crate::errors::Result;
+#[cfg(feature = "async")]
+use crate::request::async_requests;
 use crate::request::get_requests::get;
+#[cfg(feature = "async")]
+use crate::AsyncClient;
 use crate::{Client, QueryMeta, QueryOptions};
 
+/// The check ID of a node in maintenance mode.
+pub const NODE_MAINTENANCE_CHECK: &str = "_node_maintenance";
+/// The prefix of the check ID of a service in maintenance mode.
+pub const SERVICE_MAINTENANCE_CHECK_PREFIX: &str = "_service_maintenance:";
+
+/// The status of a health check, ordered from best to worst.
+#[derive(Clone, Copy, Eq, Hash, Ord, PartialEq, PartialOrd, Serialize, Deserialize, Debug)]
+#[serde(rename_all = "lowercase")]
+pub enum HealthStatus {
+    Passing,
+    Warning,
+    Critical,
+    Maintenance,
+}
+
+impl HealthStatus {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            HealthStatus::Passing => "passing",
+            HealthStatus::Warning => "warning",
+            HealthStatus::Critical => "critical",
+            HealthStatus::Maintenance => "maintenance",
+        }
+    }
+}
+
+impl Default for HealthStatus {
+    /// Consul starts new checks as critical.
+    fn default() -> Self {
+        HealthStatus::Critical
+    }
+}
+
+impl fmt::Display for HealthStatus {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// The checks `Health::state` asks for. Maintenance is left out, as Consul
+/// cannot be asked for it; maintenance checks are critical.
+#[derive(Clone, Copy, Eq, Hash, PartialEq, Debug)]
+pub enum CheckState {
+    Any,
+    Passing,
+    Warning,
+    Critical,
+}
+
+impl CheckState {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            CheckState::Any => "any",
+            CheckState::Passing => "passing",
+            CheckState::Warning => "warning",
+            CheckState::Critical => "critical",
+        }
+    }
+}
+
+impl fmt::Display for CheckState {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+#[derive(Clone, Eq, Default, PartialEq, Serialize, Deserialize, Debug)]
 #[serde(default)]
-#[derive(Eq, Default, PartialEq, Serialize, Deserialize, Debug)]
 pub struct HealthCheck {
     pub Node: String,
     pub CheckID: String,
     pub Name: String,
-    pub Status: String,
+    pub Status: HealthStatus,
     pub Notes: String,
     pub Output: String,
     pub ServiceID: String,
@@ -19,8 +93,34 @@ pub struct HealthCheck {
     pub ServiceTags: Option<Vec<String>>,
 }
 
+impl HealthCheck {
+    /// Whether this check puts its node or service in maintenance mode.
+    pub fn is_maintenance(&self) -> bool {
+        self.CheckID == NODE_MAINTENANCE_CHECK
+            || self.CheckID.starts_with(SERVICE_MAINTENANCE_CHECK_PREFIX)
+    }
+}
+
+/// The status of a group of checks the way Consul sums it up: maintenance if
+/// any check is a maintenance check, else the worst status of the checks, and
+/// passing when there are none. With `ignore_maintenance` maintenance checks
+/// are skipped instead.
+pub fn aggregated_status(checks: &[HealthCheck], ignore_maintenance: bool) -> HealthStatus {
+    let mut status = HealthStatus::Passing;
+    for check in checks {
+        if check.is_maintenance() {
+            if ignore_maintenance {
+                continue;
+            }
+            return HealthStatus::Maintenance;
+        }
+        status = status.max(check.Status);
+    }
+    status
+}
+
+#[derive(Clone, Eq, Default, PartialEq, Serialize, Deserialize, Debug)]
 #[serde(default)]
-#[derive(Eq, Default, PartialEq, Serialize, Deserialize, Debug)]
 pub struct Node {
     pub ID: String,
     pub Node: String,
@@ -32,14 +132,36 @@ pub struct Node {
     pub ModifyIndex: u64,
 }
 
+#[derive(Clone, Eq, Default, PartialEq, Serialize, Deserialize, Debug)]
 #[serde(default)]
-#[derive(Eq, Default, PartialEq, Serialize, Deserialize, Debug)]
 pub struct ServiceEntry {
     pub Node: Node,
     pub Service: AgentService,
     pub Checks: Vec<HealthCheck>,
 }
 
+impl ServiceEntry {
+    /// The status of the instance, see `aggregated_status`.
+    pub fn aggregated_status(&self, ignore_maintenance: bool) -> HealthStatus {
+        aggregated_status(&self.Checks, ignore_maintenance)
+    }
+}
+
+fn service_params(tag: Option<&str>, passing_only: bool) -> HashMap<String, String> {
+    let mut params = HashMap::new();
+    if passing_only {
+        params.insert(String::from("passing"), String::from("1"));
+    }
+    if let Some(tag) = tag {
+        params.insert(String::from("tag"), tag.to_owned());
+    }
+    params
+}
+
+fn state_path(state: CheckState) -> String {
+    format!("/v1/health/state/{}", state)
+}
+
 pub trait Health {
     fn service(
         &self,
@@ -48,6 +170,40 @@ pub trait Health {
         passing_only: bool,
         options: Option<&QueryOptions>,
     ) -> Result<(Vec<ServiceEntry>, QueryMeta)>;
+    /// The checks of the node `node`.
+    fn node(
+        &self,
+        node: &str,
+        options: Option<&QueryOptions>,
+    ) -> Result<(Vec<HealthCheck>, QueryMeta)>;
+    /// The checks of every instance of `service`.
+    fn checks(
+        &self,
+        service: &str,
+        options: Option<&QueryOptions>,
+    ) -> Result<(Vec<HealthCheck>, QueryMeta)>;
+    /// The checks in `state`, or all checks for `CheckState::Any`.
+    fn state(
+        &self,
+        state: CheckState,
+        options: Option<&QueryOptions>,
+    ) -> Result<(Vec<HealthCheck>, QueryMeta)>;
+    /// Like `service`, for the Connect capable instances of `service`.
+    fn connect(
+        &self,
+        service: &str,
+        tag: Option<&str>,
+        passing_only: bool,
+        options: Option<&QueryOptions>,
+    ) -> Result<(Vec<ServiceEntry>, QueryMeta)>;
+    /// Like `service`, for the ingress gateways routing to `service`.
+    fn ingress(
+        &self,
+        service: &str,
+        tag: Option<&str>,
+        passing_only: bool,
+        options: Option<&QueryOptions>,
+    ) -> Result<(Vec<ServiceEntry>, QueryMeta)>;
 }
 
 impl Health for Client {
@@ -58,14 +214,243 @@ impl Health for Client {
         passing_only: bool,
         options: Option<&QueryOptions>,
     ) -> Result<(Vec<ServiceEntry>, QueryMeta)> {
-        let mut params = HashMap::new();
         let path = format!("/v1/health/service/{}", service);
-        if passing_only {
-            params.insert(String::from("passing"), String::from("1"));
-        }
-        if let Some(tag) = tag {
-            params.insert(String::from("tag"), tag.to_owned());
+        get(
+            &path,
+            &self.config,
+            service_params(tag, passing_only),
+            options,
+        )
+    }
+
+    fn node(
+        &self,
+        node: &str,
+        options: Option<&QueryOptions>,
+    ) -> Result<(Vec<HealthCheck>, QueryMeta)> {
+        let path = format!("/v1/health/node/{}", node);
+        get(&path, &self.config, HashMap::new(), options)
+    }
+
+    fn checks(
+        &self,
+        service: &str,
+        options: Option<&QueryOptions>,
+    ) -> Result<(Vec<HealthCheck>, QueryMeta)> {
+        let path = format!("/v1/health/checks/{}", service);
+        get(&path, &self.config, HashMap::new(), options)
+    }
+
+    fn state(
+        &self,
+        state: CheckState,
+        options: Option<&QueryOptions>,
+    ) -> Result<(Vec<HealthCheck>, QueryMeta)> {
+        get(&state_path(state), &self.config, HashMap::new(), options)
+    }
+
+    fn connect(
+        &self,
+        service: &str,
+        tag: Option<&str>,
+        passing_only: bool,
+        options: Option<&QueryOptions>,
+    ) -> Result<(Vec<ServiceEntry>, QueryMeta)> {
+        let path = format!("/v1/health/connect/{}", service);
+        get(
+            &path,
+            &self.config,
+            service_params(tag, passing_only),
+            options,
+        )
+    }
+
+    fn ingress(
+        &self,
+        service: &str,
+        tag: Option<&str>,
+        passing_only: bool,
+        options: Option<&QueryOptions>,
+    ) -> Result<(Vec<ServiceEntry>, QueryMeta)> {
+        let path = format!("/v1/health/ingress/{}", service);
+        get(
+            &path,
+            &self.config,
+            service_params(tag, passing_only),
+            options,
+        )
+    }
+}
+
+#[cfg(feature = "async")]
+#[async_trait]
+pub trait AsyncHealth {
+    async fn service(
+        &self,
+        service: &str,
+        tag: Option<&str>,
+        passing_only: bool,
+        options: Option<&QueryOptions>,
+    ) -> Result<(Vec<ServiceEntry>, QueryMeta)>;
+    async fn node(
+        &self,
+        node: &str,
+        options: Option<&QueryOptions>,
+    ) -> Result<(Vec<HealthCheck>, QueryMeta)>;
+    async fn checks(
+        &self,
+        service: &str,
+        options: Option<&QueryOptions>,
+    ) -> Result<(Vec<HealthCheck>, QueryMeta)>;
+    async fn state(
+        &self,
+        state: CheckState,
+        options: Option<&QueryOptions>,
+    ) -> Result<(Vec<HealthCheck>, QueryMeta)>;
+    async fn connect(
+        &self,
+        service: &str,
+        tag: Option<&str>,
+        passing_only: bool,
+        options: Option<&QueryOptions>,
+    ) -> Result<(Vec<ServiceEntry>, QueryMeta)>;
+    async fn ingress(
+        &self,
+        service: &str,
+        tag: Option<&str>,
+        passing_only: bool,
+        options: Option<&QueryOptions>,
+    ) -> Result<(Vec<ServiceEntry>, QueryMeta)>;
+}
+
+#[cfg(feature = "async")]
+#[async_trait]
+impl AsyncHealth for AsyncClient {
+    async fn service(
+        &self,
+        service: &str,
+        tag: Option<&str>,
+        passing_only: bool,
+        options: Option<&QueryOptions>,
+    ) -> Result<(Vec<ServiceEntry>, QueryMeta)> {
+        let path = format!("/v1/health/service/{}", service);
+        let params = service_params(tag, passing_only);
+        async_requests::get(&path, &self.config, params, options).await
+    }
+
+    async fn node(
+        &self,
+        node: &str,
+        options: Option<&QueryOptions>,
+    ) -> Result<(Vec<HealthCheck>, QueryMeta)> {
+        let path = format!("/v1/health/node/{}", node);
+        async_requests::get(&path, &self.config, HashMap::new(), options).await
+    }
+
+    async fn checks(
+        &self,
+        service: &str,
+        options: Option<&QueryOptions>,
+    ) -> Result<(Vec<HealthCheck>, QueryMeta)> {
+        let path = format!("/v1/health/checks/{}", service);
+        async_requests::get(&path, &self.config, HashMap::new(), options).await
+    }
+
+    async fn state(
+        &self,
+        state: CheckState,
+        options: Option<&QueryOptions>,
+    ) -> Result<(Vec<HealthCheck>, QueryMeta)> {
+        async_requests::get(&state_path(state), &self.config, HashMap::new(), options).await
+    }
+
+    async fn connect(
+        &self,
+        service: &str,
+        tag: Option<&str>,
+        passing_only: bool,
+        options: Option<&QueryOptions>,
+    ) -> Result<(Vec<ServiceEntry>, QueryMeta)> {
+        let path = format!("/v1/health/connect/{}", service);
+        let params = service_params(tag, passing_only);
+        async_requests::get(&path, &self.config, params, options).await
+    }
+
+    async fn ingress(
+        &self,
+        service: &str,
+        tag: Option<&str>,
+        passing_only: bool,
+        options: Option<&QueryOptions>,
+    ) -> Result<(Vec<ServiceEntry>, QueryMeta)> {
+        let path = format!("/v1/health/ingress/{}", service);
+        let params = service_params(tag, passing_only);
+        async_requests::get(&path, &self.config, params, options).await
+    }
+}
+
+#[cfg(test)]
+mod health_tests {
+    use super::*;
+
+    fn check(id: &str, status: HealthStatus) -> HealthCheck {
+        HealthCheck {
+            CheckID: id.to_owned(),
+            Status: status,
+            ..Default::default()
         }
-        get(&path, &self.config, params, options)
+    }
+
+    #[test]
+    fn health_status_serde_test() {
+        let status: HealthStatus = serde_json::from_str("\"warning\"").unwrap();
+        assert_eq!(status, HealthStatus::Warning);
+        assert_eq!(
+            serde_json::to_string(&HealthStatus::Critical).unwrap(),
+            "\"critical\""
+        );
+        assert_eq!(HealthStatus::Maintenance.to_string(), "maintenance");
+    }
+
+    #[test]
+    fn aggregated_status_test() {
+        assert_eq!(aggregated_status(&[], false), HealthStatus::Passing);
+
+        let checks = vec![
+            check("serfHealth", HealthStatus::Passing),
+            check("service:web", HealthStatus::Warning),
+        ];
+        assert_eq!(aggregated_status(&checks, false), HealthStatus::Warning);
+
+        let checks = vec![
+            check("serfHealth", HealthStatus::Critical),
+            check("service:web", HealthStatus::Warning),
+        ];
+        assert_eq!(aggregated_status(&checks, false), HealthStatus::Critical);
+    }
+
+    #[test]
+    fn aggregated_status_maintenance_test() {
+        let entry = ServiceEntry {
+            Checks: vec![
+                check("serfHealth", HealthStatus::Passing),
+                check("_service_maintenance:web", HealthStatus::Critical),
+                check("service:web", HealthStatus::Warning),
+            ],
+            ..Default::default()
+        };
+
+        assert_eq!(entry.aggregated_status(false), HealthStatus::Maintenance);
+        assert_eq!(entry.aggregated_status(true), HealthStatus::Warning);
+        assert!(check(NODE_MAINTENANCE_CHECK, HealthStatus::Critical).is_maintenance());
+    }
+
+    #[test]
+    fn state_path_test() {
+        assert_eq!(state_path(CheckState::Any), "/v1/health/state/any");
+        assert_eq!(
+            state_path(CheckState::Critical),
+            "/v1/health/state/critical"
+        );
     }
 }
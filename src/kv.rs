@@ -2,31 +2,76 @@ extern crate base64;
 
 use std::collections::HashMap;
 
+#[cfg(feature = "async")]
+use async_trait::async_trait;
+
 use crate::errors::Error;
-use crate::errors::Result;
+use crate::errors::{Result, ResultExt};
+#[cfg(feature = "async")]
+use crate::request::async_requests;
 use crate::request::delete_requests::delete;
-use crate::request::get_requests::{get, get_vec};
-use crate::request::put_requests::put;
+use crate::request::get_requests::get_vec;
+use crate::request::put_requests::put_raw;
+#[cfg(feature = "async")]
+use crate::AsyncClient;
 use crate::{Client, QueryMeta, QueryOptions, WriteMeta, WriteOptions};
 
-use serde::{Deserialize, Deserializer};
+use serde::de::{DeserializeOwned, Error as DeError};
+use serde::{Deserialize, Deserializer, Serializer};
 
-#[serde(default)]
 #[derive(Clone, Default, Eq, PartialEq, Serialize, Deserialize, Debug)]
+#[serde(default)]
 pub struct KVPair {
     pub Key: String,
     pub CreateIndex: Option<u64>,
     pub ModifyIndex: Option<u64>,
     pub LockIndex: Option<u64>,
     pub Flags: Option<u64>,
-    #[serde(deserialize_with = "deserialize_kv_pair_value")]
-    pub Value: String,
+    #[serde(
+        deserialize_with = "deserialize_kv_pair_value",
+        serialize_with = "serialize_kv_pair_value"
+    )]
+    pub Value: Vec<u8>,
     pub Session: Option<String>,
 }
 
+impl KVPair {
+    /// The raw bytes stored under the key.
+    pub fn value_bytes(&self) -> &[u8] {
+        &self.Value
+    }
+
+    /// The value as UTF-8 text.
+    pub fn value_str(&self) -> Result<&str> {
+        std::str::from_utf8(&self.Value).chain_err(|| "KV value is not valid UTF-8")
+    }
+
+    /// The value deserialized from JSON.
+    pub fn value_json<T: DeserializeOwned>(&self) -> Result<T> {
+        serde_json::from_slice(&self.Value).chain_err(|| "Failed to parse KV value as JSON")
+    }
+}
+
 pub trait KV {
     fn acquire(&self, _: &KVPair, _: Option<&WriteOptions>) -> Result<(bool, WriteMeta)>;
+    /// Check-and-set put using `pair.ModifyIndex`. A missing index is sent as 0,
+    /// which only writes the key if it does not exist yet.
+    fn cas(&self, pair: &KVPair, _: Option<&WriteOptions>) -> Result<(bool, WriteMeta)>;
+    fn cas_with_index(
+        &self,
+        _: &KVPair,
+        index: u64,
+        _: Option<&WriteOptions>,
+    ) -> Result<(bool, WriteMeta)>;
     fn delete(&self, _: &str, _: Option<&WriteOptions>) -> Result<(bool, WriteMeta)>;
+    /// Check-and-set delete using `pair.ModifyIndex`, which must be set.
+    fn delete_cas(&self, pair: &KVPair, _: Option<&WriteOptions>) -> Result<(bool, WriteMeta)>;
+    fn delete_cas_with_index(
+        &self,
+        _: &str,
+        index: u64,
+        _: Option<&WriteOptions>,
+    ) -> Result<(bool, WriteMeta)>;
     fn get(&self, _: &str, _: Option<&QueryOptions>) -> Result<(Option<KVPair>, QueryMeta)>;
     fn list(&self, _: &str, _: Option<&QueryOptions>) -> Result<(Vec<KVPair>, QueryMeta)>;
     fn put(&self, _: &KVPair, _: Option<&WriteOptions>) -> Result<(bool, WriteMeta)>;
@@ -35,32 +80,65 @@ pub trait KV {
 
 impl KV for Client {
     fn acquire(&self, pair: &KVPair, o: Option<&WriteOptions>) -> Result<(bool, WriteMeta)> {
-        let mut params = HashMap::new();
-        if let Some(i) = pair.Flags {
-            if i != 0 {
-                params.insert(String::from("flags"), i.to_string());
-            }
-        }
+        let mut params = pair_params(pair);
         if let Some(ref session) = pair.Session {
             params.insert(String::from("acquire"), session.to_owned());
             let path = format!("/v1/kv/{}", pair.Key);
-            put(&path, Some(&pair.Value), &self.config, params, o)
+            put_raw(&path, &pair.Value, &self.config, params, o)
         } else {
             Err(Error::from("Session flag is required to acquire lock"))
         }
     }
 
+    fn cas(&self, pair: &KVPair, o: Option<&WriteOptions>) -> Result<(bool, WriteMeta)> {
+        self.cas_with_index(pair, pair.ModifyIndex.unwrap_or(0), o)
+    }
+
+    fn cas_with_index(
+        &self,
+        pair: &KVPair,
+        index: u64,
+        o: Option<&WriteOptions>,
+    ) -> Result<(bool, WriteMeta)> {
+        let mut params = pair_params(pair);
+        params.insert(String::from("cas"), index.to_string());
+        let path = format!("/v1/kv/{}", pair.Key);
+        put_raw(&path, &pair.Value, &self.config, params, o)
+    }
+
     fn delete(&self, key: &str, options: Option<&WriteOptions>) -> Result<(bool, WriteMeta)> {
         let path = format!("/v1/kv/{}", key);
         delete(&path, &self.config, HashMap::new(), options)
     }
+
+    fn delete_cas(&self, pair: &KVPair, o: Option<&WriteOptions>) -> Result<(bool, WriteMeta)> {
+        match pair.ModifyIndex {
+            Some(index) => self.delete_cas_with_index(&pair.Key, index, o),
+            None => Err(Error::from(
+                "ModifyIndex is required for a check-and-set delete",
+            )),
+        }
+    }
+
+    fn delete_cas_with_index(
+        &self,
+        key: &str,
+        index: u64,
+        options: Option<&WriteOptions>,
+    ) -> Result<(bool, WriteMeta)> {
+        let mut params = HashMap::new();
+        params.insert(String::from("cas"), index.to_string());
+        let path = format!("/v1/kv/{}", key);
+        delete(&path, &self.config, params, options)
+    }
     fn get(
         &self,
         key: &str,
         options: Option<&QueryOptions>,
     ) -> Result<(Option<KVPair>, QueryMeta)> {
         let path = format!("/v1/kv/{}", key);
-        let x: Result<(Vec<KVPair>, QueryMeta)> = get(&path, &self.config, HashMap::new(), options);
+        let x: Result<(Vec<KVPair>, QueryMeta)> =
+            get_vec(&path, &self.config, HashMap::new(), options);
         x.map(|r| (r.0.first().cloned(), r.1))
     }
 
@@ -72,40 +150,235 @@ impl KV for Client {
     }
 
     fn put(&self, pair: &KVPair, o: Option<&WriteOptions>) -> Result<(bool, WriteMeta)> {
-        let mut params = HashMap::new();
-        if let Some(i) = pair.Flags {
-            if i != 0 {
-                params.insert(String::from("flags"), i.to_string());
-            }
-        }
+        let params = pair_params(pair);
         let path = format!("/v1/kv/{}", pair.Key);
-        put(&path, Some(&pair.Value), &self.config, params, o)
+        put_raw(&path, &pair.Value, &self.config, params, o)
     }
 
     fn release(&self, pair: &KVPair, o: Option<&WriteOptions>) -> Result<(bool, WriteMeta)> {
-        let mut params = HashMap::new();
-        if let Some(i) = pair.Flags {
-            if i != 0 {
-                params.insert(String::from("flags"), i.to_string());
-            }
+        let mut params = pair_params(pair);
+        if let Some(ref session) = pair.Session {
+            params.insert(String::from("release"), session.to_owned());
+            let path = format!("/v1/kv/{}", pair.Key);
+            put_raw(&path, &pair.Value, &self.config, params, o)
+        } else {
+            Err(Error::from("Session flag is required to release a lock"))
         }
+    }
+}
+
+#[cfg(feature = "async")]
+#[async_trait]
+pub trait AsyncKV {
+    async fn acquire(&self, _: &KVPair, _: Option<&WriteOptions>) -> Result<(bool, WriteMeta)>;
+    async fn cas(&self, pair: &KVPair, _: Option<&WriteOptions>) -> Result<(bool, WriteMeta)>;
+    async fn cas_with_index(
+        &self,
+        _: &KVPair,
+        index: u64,
+        _: Option<&WriteOptions>,
+    ) -> Result<(bool, WriteMeta)>;
+    async fn delete(&self, _: &str, _: Option<&WriteOptions>) -> Result<(bool, WriteMeta)>;
+    async fn delete_cas(
+        &self,
+        pair: &KVPair,
+        _: Option<&WriteOptions>,
+    ) -> Result<(bool, WriteMeta)>;
+    async fn delete_cas_with_index(
+        &self,
+        _: &str,
+        index: u64,
+        _: Option<&WriteOptions>,
+    ) -> Result<(bool, WriteMeta)>;
+    async fn get(&self, _: &str, _: Option<&QueryOptions>) -> Result<(Option<KVPair>, QueryMeta)>;
+    async fn list(&self, _: &str, _: Option<&QueryOptions>) -> Result<(Vec<KVPair>, QueryMeta)>;
+    async fn put(&self, _: &KVPair, _: Option<&WriteOptions>) -> Result<(bool, WriteMeta)>;
+    async fn release(&self, _: &KVPair, _: Option<&WriteOptions>) -> Result<(bool, WriteMeta)>;
+}
+
+#[cfg(feature = "async")]
+#[async_trait]
+impl AsyncKV for AsyncClient {
+    async fn acquire(&self, pair: &KVPair, o: Option<&WriteOptions>) -> Result<(bool, WriteMeta)> {
+        let mut params = pair_params(pair);
+        if let Some(ref session) = pair.Session {
+            params.insert(String::from("acquire"), session.to_owned());
+            let path = format!("/v1/kv/{}", pair.Key);
+            async_requests::put_raw(&path, &pair.Value, &self.config, params, o).await
+        } else {
+            Err(Error::from("Session flag is required to acquire lock"))
+        }
+    }
+
+    async fn cas(&self, pair: &KVPair, o: Option<&WriteOptions>) -> Result<(bool, WriteMeta)> {
+        self.cas_with_index(pair, pair.ModifyIndex.unwrap_or(0), o)
+            .await
+    }
+
+    async fn cas_with_index(
+        &self,
+        pair: &KVPair,
+        index: u64,
+        o: Option<&WriteOptions>,
+    ) -> Result<(bool, WriteMeta)> {
+        let mut params = pair_params(pair);
+        params.insert(String::from("cas"), index.to_string());
+        let path = format!("/v1/kv/{}", pair.Key);
+        async_requests::put_raw(&path, &pair.Value, &self.config, params, o).await
+    }
+
+    async fn delete(&self, key: &str, options: Option<&WriteOptions>) -> Result<(bool, WriteMeta)> {
+        let path = format!("/v1/kv/{}", key);
+        async_requests::delete(&path, &self.config, HashMap::new(), options).await
+    }
+
+    async fn delete_cas(
+        &self,
+        pair: &KVPair,
+        o: Option<&WriteOptions>,
+    ) -> Result<(bool, WriteMeta)> {
+        match pair.ModifyIndex {
+            Some(index) => self.delete_cas_with_index(&pair.Key, index, o).await,
+            None => Err(Error::from(
+                "ModifyIndex is required for a check-and-set delete",
+            )),
+        }
+    }
+
+    async fn delete_cas_with_index(
+        &self,
+        key: &str,
+        index: u64,
+        options: Option<&WriteOptions>,
+    ) -> Result<(bool, WriteMeta)> {
+        let mut params = HashMap::new();
+        params.insert(String::from("cas"), index.to_string());
+        let path = format!("/v1/kv/{}", key);
+        async_requests::delete(&path, &self.config, params, options).await
+    }
+
+    async fn get(
+        &self,
+        key: &str,
+        options: Option<&QueryOptions>,
+    ) -> Result<(Option<KVPair>, QueryMeta)> {
+        let path = format!("/v1/kv/{}", key);
+        let x: Result<(Vec<KVPair>, QueryMeta)> =
+            async_requests::get_vec(&path, &self.config, HashMap::new(), options).await;
+        x.map(|r| (r.0.first().cloned(), r.1))
+    }
+
+    async fn list(
+        &self,
+        prefix: &str,
+        o: Option<&QueryOptions>,
+    ) -> Result<(Vec<KVPair>, QueryMeta)> {
+        let mut params = HashMap::new();
+        params.insert(String::from("recurse"), String::from(""));
+        let path = format!("/v1/kv/{}", prefix);
+        async_requests::get_vec(&path, &self.config, params, o).await
+    }
+
+    async fn put(&self, pair: &KVPair, o: Option<&WriteOptions>) -> Result<(bool, WriteMeta)> {
+        let params = pair_params(pair);
+        let path = format!("/v1/kv/{}", pair.Key);
+        async_requests::put_raw(&path, &pair.Value, &self.config, params, o).await
+    }
+
+    async fn release(&self, pair: &KVPair, o: Option<&WriteOptions>) -> Result<(bool, WriteMeta)> {
+        let mut params = pair_params(pair);
         if let Some(ref session) = pair.Session {
             params.insert(String::from("release"), session.to_owned());
             let path = format!("/v1/kv/{}", pair.Key);
-            put(&path, Some(&pair.Value), &self.config, params, o)
+            async_requests::put_raw(&path, &pair.Value, &self.config, params, o).await
         } else {
             Err(Error::from("Session flag is required to release a lock"))
         }
     }
 }
 
-fn deserialize_kv_pair_value<'de, D>(deserializer: D) -> std::result::Result<String, D::Error>
+fn pair_params(pair: &KVPair) -> HashMap<String, String> {
+    let mut params = HashMap::new();
+    if let Some(i) = pair.Flags {
+        if i != 0 {
+            params.insert(String::from("flags"), i.to_string());
+        }
+    }
+    params
+}
+
+fn deserialize_kv_pair_value<'de, D>(deserializer: D) -> std::result::Result<Vec<u8>, D::Error>
 where
     D: Deserializer<'de>,
 {
-    let raw_base64_string: &str = Deserialize::deserialize(deserializer).unwrap();
-    let decoded_byte_array = base64::decode(raw_base64_string).unwrap();
-    let decoded_string = std::str::from_utf8(&decoded_byte_array).unwrap();
+    let raw_base64_string: Option<String> = Deserialize::deserialize(deserializer)?;
+    match raw_base64_string {
+        Some(s) => base64::decode(&s).map_err(D::Error::custom),
+        None => Ok(Vec::new()),
+    }
+}
 
-    Ok(decoded_string.to_string())
+fn serialize_kv_pair_value<S>(value: &[u8], serializer: S) -> std::result::Result<S::Ok, S::Error>
+where
+    S: Serializer,
+{
+    serializer.serialize_str(&base64::encode(value))
+}
+
+#[cfg(test)]
+mod kv_tests {
+    use super::*;
+
+    #[test]
+    fn deserialize_binary_value_test() {
+        let json = r#"{"Key": "binary", "Value": "H4sA//4=", "ModifyIndex": 7}"#;
+
+        let pair: KVPair = serde_json::from_str(json).unwrap();
+
+        assert_eq!(pair.value_bytes(), &[0x1f, 0x8b, 0x00, 0xff, 0xfe]);
+        assert!(pair.value_str().is_err());
+        assert_eq!(pair.ModifyIndex, Some(7));
+    }
+
+    #[test]
+    fn deserialize_null_value_test() {
+        let json = r#"{"Key": "folder/", "Value": null}"#;
+
+        let pair: KVPair = serde_json::from_str(json).unwrap();
+
+        assert!(pair.Value.is_empty());
+    }
+
+    #[test]
+    fn deserialize_invalid_base64_value_test() {
+        let json = r#"{"Key": "broken", "Value": "not base64!"}"#;
+
+        assert!(serde_json::from_str::<KVPair>(json).is_err());
+    }
+
+    #[test]
+    fn value_accessors_test() {
+        let pair = KVPair {
+            Key: String::from("config"),
+            Value: br#"{"replicas": 3}"#.to_vec(),
+            ..Default::default()
+        };
+
+        assert_eq!(pair.value_str().unwrap(), r#"{"replicas": 3}"#);
+        let json: serde_json::Value = pair.value_json().unwrap();
+        assert_eq!(json["replicas"], 3);
+    }
+
+    #[test]
+    fn serialize_value_as_base64_test() {
+        let pair = KVPair {
+            Key: String::from("binary"),
+            Value: vec![0x1f, 0x8b, 0x00, 0xff, 0xfe],
+            ..Default::default()
+        };
+
+        let json = serde_json::to_value(&pair).unwrap();
+
+        assert_eq!(json["Value"], "H4sA//4=");
+    }
 }
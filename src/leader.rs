@@ -0,0 +1,170 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::sync::{Arc, Mutex};
+use std::thread::{self, JoinHandle};
+
+use crate::errors::{Result, ResultExt};
+use crate::kv::KV;
+use crate::lock::{Lock, LockOptions};
+use crate::watch::Watch;
+use crate::{Client, QueryMeta, QueryOptions};
+
+#[derive(Clone, Copy, Eq, PartialEq, Debug)]
+pub enum LeadershipEvent {
+    Elected,
+    /// Sent when leadership ends, whether it was lost or given up by `resign`.
+    Lost,
+}
+
+/// Campaigns for leadership of a key until it resigns or is dropped.
+///
+/// Leadership is a `Lock` on the key holding the candidate's value. A
+/// background thread keeps campaigning, re-entering the race after losing the
+/// lock, and reports each transition on `events`. Anyone can read the current
+/// leader with `LeaderElection::leader`, or follow it with
+/// `LeaderElection::watch_leader`.
+pub struct LeaderElection {
+    lock: Arc<Mutex<Lock>>,
+    is_leader: Arc<AtomicBool>,
+    stop: Arc<AtomicBool>,
+    cancel: Option<Sender<()>>,
+    events: Receiver<LeadershipEvent>,
+    campaign: Option<JoinHandle<()>>,
+}
+
+impl LeaderElection {
+    /// Starts campaigning for `options.key` with `options.value` as the
+    /// candidate's identity.
+    pub fn campaign(client: Client, options: LockOptions) -> Result<Self> {
+        let retry_time = options.lock_retry_time;
+        let lock = Arc::new(Mutex::new(Lock::new(client, options)));
+        let is_leader = Arc::new(AtomicBool::new(false));
+        let stop = Arc::new(AtomicBool::new(false));
+        let (cancel, cancelled) = mpsc::channel();
+        let (events, received) = mpsc::channel();
+
+        let campaign = {
+            let lock = lock.clone();
+            let is_leader = is_leader.clone();
+            let stop = stop.clone();
+            thread::Builder::new()
+                .name(String::from("consul-leader-election"))
+                .spawn(move || {
+                    while !stop.load(Ordering::SeqCst) {
+                        let lost = match lock.lock().unwrap().lock(Some(&cancelled)) {
+                            Ok(Some(lost)) => lost,
+                            Ok(None) => continue,
+                            Err(_) => {
+                                thread::sleep(retry_time);
+                                continue;
+                            }
+                        };
+                        is_leader.store(true, Ordering::SeqCst);
+                        let _ = events.send(LeadershipEvent::Elected);
+
+                        // Disconnected when `resign` unlocks.
+                        let _ = lost.recv();
+                        is_leader.store(false, Ordering::SeqCst);
+                        let _ = events.send(LeadershipEvent::Lost);
+
+                        let mut lock = lock.lock().unwrap();
+                        if lock.is_held() {
+                            let _ = lock.unlock();
+                        }
+                    }
+                })
+                .chain_err(|| "Failed to start leader election thread")?
+        };
+
+        Ok(LeaderElection {
+            lock,
+            is_leader,
+            stop,
+            cancel: Some(cancel),
+            events: received,
+            campaign: Some(campaign),
+        })
+    }
+
+    pub fn is_leader(&self) -> bool {
+        self.is_leader.load(Ordering::SeqCst)
+    }
+
+    /// Leadership transitions, starting with `Elected` once the key is won.
+    pub fn events(&self) -> &Receiver<LeadershipEvent> {
+        &self.events
+    }
+
+    /// Stops campaigning and gives up leadership if it is held.
+    ///
+    /// A campaign that is waiting for the key notices within the lock's
+    /// `lock_wait_time`.
+    pub fn resign(mut self) -> Result<()> {
+        self.shutdown()
+    }
+
+    /// The value stored by the current leader of `key`, or `None` when the key
+    /// is not held.
+    pub fn leader(
+        client: &Client,
+        key: &str,
+        options: Option<&QueryOptions>,
+    ) -> Result<(Option<Vec<u8>>, QueryMeta)> {
+        let (pair, meta) = client.get(key, options)?;
+        let leader = pair.filter(|p| p.Session.is_some()).map(|p| p.Value);
+        Ok((leader, meta))
+    }
+
+    /// Follows the leader of `key` with blocking queries, yielding the value
+    /// stored by the current leader, or `None` when the key is not held: once
+    /// for the leader when the watch starts, then each time the key changes
+    /// hands. Other changes to the key are skipped. A failed query is yielded
+    /// as an error and retried after a backoff, as with `Watch`.
+    pub fn watch_leader<'a>(
+        client: &'a Client,
+        key: &str,
+        options: Option<QueryOptions>,
+    ) -> impl Iterator<Item = Result<Option<Vec<u8>>>> + 'a {
+        let key = key.to_owned();
+        // The session and value of the holder last yielded.
+        let mut current = None;
+        Watch::new(move |o| client.get(&key, o), options).filter_map(move |result| {
+            let pair = match result {
+                Ok(pair) => pair,
+                Err(e) => return Some(Err(e)),
+            };
+            let holder = pair
+                .filter(|p| p.Session.is_some())
+                .map(|p| (p.Session, p.Value));
+            if current.as_ref() == Some(&holder) {
+                return None;
+            }
+            let leader = holder.as_ref().map(|(_, value)| value.clone());
+            current = Some(holder);
+            Some(Ok(leader))
+        })
+    }
+
+    fn shutdown(&mut self) -> Result<()> {
+        self.stop.store(true, Ordering::SeqCst);
+        self.cancel.take();
+        let result = {
+            let mut lock = self.lock.lock().unwrap();
+            if lock.is_held() {
+                lock.unlock()
+            } else {
+                Ok(())
+            }
+        };
+        if let Some(campaign) = self.campaign.take() {
+            let _ = campaign.join();
+        }
+        result
+    }
+}
+
+impl Drop for LeaderElection {
+    fn drop(&mut self) {
+        let _ = self.shutdown();
+    }
+}
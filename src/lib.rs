@@ -1,29 +1,60 @@
 #![allow(non_snake_case)]
 #![allow(unused_doc_comments)]
+#![allow(clippy::unnecessary_lazy_evaluations)]
 
 #[macro_use]
 extern crate error_chain;
 #[macro_use]
 extern crate serde_derive;
 
+pub mod acl;
 pub mod agent;
 pub mod catalog;
+pub mod config;
 pub mod connect_ca;
+pub mod coordinate;
+#[cfg(feature = "discover")]
+pub mod discover;
 pub mod errors;
+pub mod event;
 pub mod health;
 pub mod kv;
+pub mod leader;
+pub mod lock;
+pub mod registration;
+pub mod resolver;
+pub mod retry;
+pub mod semaphore;
 pub mod session;
+#[cfg(feature = "testing")]
+pub mod testing;
+pub mod tls;
+pub mod transport;
+pub mod txn;
+pub mod watch;
 
+mod duration;
 mod request;
 
-use std::env;
+use std::collections::HashMap;
+use std::path::Path;
+use std::sync::Arc;
 
 use std::time::Duration;
 
+use std::thread;
+
 use reqwest::blocking::Client as HttpClient;
 use reqwest::blocking::ClientBuilder;
+#[cfg(feature = "async")]
+use reqwest::Client as AsyncHttpClient;
 
+use config::{ConfigBuilder, HttpBasicAuth, HttpOptions};
 use errors::{Result, ResultExt};
+use request::unix_socket::DEFAULT_TIMEOUT;
+use retry::RetryPolicy;
+use tls::TlsConfig;
+use transport::{ReqwestTransport, TlsTransport, Transport, UnixSocketTransport};
 
 #[derive(Clone, Debug)]
 pub struct Client {
@@ -36,65 +67,260 @@ impl Client {
     }
 }
 
+#[cfg(feature = "async")]
+#[derive(Clone, Debug)]
+pub struct AsyncClient {
+    config: Config,
+}
+
+#[cfg(feature = "async")]
+impl AsyncClient {
+    pub fn new(config: Config) -> Self {
+        AsyncClient { config }
+    }
+}
+
 #[derive(Clone, Debug)]
 pub struct Config {
     pub address: String,
     pub datacenter: Option<String>,
+    /// Agents tried in order when `address` cannot be reached or fails, with
+    /// the same scheme and host form as `address`. Ignored for `unix://`
+    /// addresses.
+    pub fallback_addresses: Vec<String>,
+    /// Sent as an `Authorization: Basic` header with every request.
+    pub http_auth: Option<HttpBasicAuth>,
     pub http_client: HttpClient,
+    #[cfg(feature = "async")]
+    pub async_http_client: AsyncHttpClient,
+    // The settings the HTTP clients and transports are built with, only
+    // changed together with them by `with_http_options`.
+    http_options: HttpOptions,
+    /// The namespace of requests that do not name one.
+    pub namespace: Option<String>,
+    /// The admin partition of requests that do not name one.
+    pub partition: Option<String>,
+    pub tls: TlsConfig,
+    // Sends HTTPS requests when `tls` names a server, which reqwest cannot
+    // verify certificates against.
+    tls_transport: Option<TlsTransport>,
     pub token: Option<String>,
+    /// Sends the requests of a `Client` in place of `http_client`. `AsyncClient`
+    /// does not use it.
+    pub transport: Option<Arc<dyn Transport>>,
+    pub retry_policy: RetryPolicy,
     pub wait_time: Option<Duration>,
 }
 
 impl Config {
     pub fn new() -> Result<Config> {
-        ClientBuilder::new()
-            .build()
-            .chain_err(|| "Failed to build reqwest client")
-            .map(|client| Config {
-                address: String::from("http://localhost:8500"),
-                datacenter: None,
-                http_client: client,
-                token: None,
-                wait_time: None,
-            })
+        ConfigBuilder::new().build()
     }
 
+    /// Reads the variables the Consul CLI reads: `CONSUL_HTTP_ADDR`,
+    /// `CONSUL_HTTP_TOKEN` or `CONSUL_HTTP_TOKEN_FILE`, `CONSUL_HTTP_AUTH`,
+    /// `CONSUL_NAMESPACE`, `CONSUL_PARTITION`, `CONSUL_HTTP_SSL` to default the
+    /// address to HTTPS, and the TLS variables read by `TlsConfig`.
     pub fn new_from_env() -> Result<Config> {
-        let consul_addr = match env::var("CONSUL_HTTP_ADDR") {
-            Ok(val) => {
-                if val.starts_with("http") {
-                    val
-                } else {
-                    format!("http://{}", val)
-                }
-            }
-            Err(_e) => String::from("http://127.0.0.1:8500"),
+        ConfigBuilder::from_env()?.build()
+    }
+
+    pub fn builder() -> ConfigBuilder {
+        ConfigBuilder::new()
+    }
+
+    /// The socket path of a `unix://` address, for agents that only listen on a
+    /// unix socket.
+    pub(crate) fn unix_socket(&self) -> Option<&Path> {
+        self.address.strip_prefix("unix://").map(Path::new)
+    }
+
+    /// The transport for an `https://` address when the TLS settings name the
+    /// server its certificate is checked against.
+    pub(crate) fn tls_transport(&self) -> Option<&TlsTransport> {
+        self.tls_transport
+            .as_ref()
+            .filter(|_| self.address.starts_with("https://"))
+    }
+
+    /// The transport a `Client` sends its requests with: `transport` when set,
+    /// otherwise `http_client`, the agent's unix socket or a `TlsTransport`.
+    pub(crate) fn transport(&self) -> Arc<dyn Transport> {
+        match (&self.transport, self.unix_socket(), self.tls_transport()) {
+            (Some(transport), _, _) => Arc::clone(transport),
+            (None, Some(socket), _) => Arc::new(UnixSocketTransport::with_options(
+                socket,
+                self.http_options.clone(),
+            )),
+            (None, None, Some(transport)) => Arc::new(transport.clone()),
+            (None, None, None) => Arc::new(ReqwestTransport::with_options(
+                self.http_client.clone(),
+                self.http_options.clone(),
+            )),
+        }
+    }
+
+    pub fn with_transport<T: Transport + 'static>(mut self, transport: T) -> Config {
+        self.transport = Some(Arc::new(transport));
+        self
+    }
+
+    /// The timeouts, user agent and proxy settings requests are sent with.
+    pub fn http_options(&self) -> &HttpOptions {
+        &self.http_options
+    }
+
+    /// Rebuilds the HTTP clients with the given TLS settings.
+    pub fn with_tls(self, tls: TlsConfig) -> Result<Config> {
+        let options = self.http_options.clone();
+        self.with_clients(tls, options)
+    }
+
+    /// Rebuilds the HTTP clients with the given timeouts, user agent and proxy
+    /// settings.
+    pub fn with_http_options(self, options: HttpOptions) -> Result<Config> {
+        let tls = self.tls.clone();
+        self.with_clients(tls, options)
+    }
+
+    fn with_clients(mut self, tls: TlsConfig, options: HttpOptions) -> Result<Config> {
+        self.tls_transport = build_tls_transport(&tls, &options)?;
+        self.http_client = build_http_client(&tls, &options)?;
+        #[cfg(feature = "async")]
+        {
+            self.async_http_client = build_async_http_client(&tls, &options)?;
+        }
+        self.tls = tls;
+        self.http_options = options;
+        Ok(self)
+    }
+}
+
+// The blocking reqwest client starts its own runtime and panics when it is built
+// from inside another one. Building it on a plain thread lets a `Config` be
+// created from async code as well, e.g. for an `AsyncClient`.
+fn build_http_client(tls: &TlsConfig, options: &HttpOptions) -> Result<HttpClient> {
+    let connector = tls.connector()?;
+    let options = options.clone();
+    thread::spawn(move || {
+        let mut builder = ClientBuilder::new();
+        if let Some(connector) = connector {
+            builder = builder.use_preconfigured_tls(connector);
+        }
+        if let Some(timeout) = options.connect_timeout {
+            builder = builder.connect_timeout(timeout);
+        }
+        // The blocking client's timeout bounds both the wait for a response and
+        // every read of its body, so with a read timeout it is the shorter of
+        // the two. `ReqwestTransport` keeps the whole request in its deadline.
+        let timeout = match (options.request_timeout, options.read_timeout) {
+            (Some(request), Some(read)) => Some(request.min(read)),
+            (None, Some(read)) => Some(read.min(DEFAULT_TIMEOUT)),
+            (request, None) => request,
         };
-        let consul_token = env::var("CONSUL_HTTP_TOKEN").ok();
-        ClientBuilder::new()
-            .build()
-            .chain_err(|| "Failed to build reqwest client")
-            .map(|client| Config {
-                address: consul_addr,
-                datacenter: None,
-                http_client: client,
-                token: consul_token,
-                wait_time: None,
-            })
+        if let Some(timeout) = timeout {
+            builder = builder.timeout(timeout);
+        }
+        if let Some(user_agent) = options.user_agent {
+            builder = builder.user_agent(user_agent);
+        }
+        if let Some(proxy) = options.proxy {
+            builder = builder.proxy(proxy);
+        }
+        if options.no_proxy {
+            builder = builder.no_proxy();
+        }
+        builder.build()
+    })
+    .join()
+    .map_err(|_| errors::Error::from("Failed to build reqwest client"))?
+    .chain_err(|| "Failed to build reqwest client")
+}
+
+/// A `TlsTransport` when `tls` names the server, unless certificates are not
+/// verified at all.
+fn build_tls_transport(tls: &TlsConfig, options: &HttpOptions) -> Result<Option<TlsTransport>> {
+    match tls.server_name {
+        Some(_) if !tls.insecure_skip_verify => TlsTransport::new(tls, options.clone()).map(Some),
+        _ => Ok(None),
     }
 }
 
+#[cfg(feature = "async")]
+fn build_async_http_client(tls: &TlsConfig, options: &HttpOptions) -> Result<AsyncHttpClient> {
+    let mut builder = reqwest::ClientBuilder::new();
+    if let Some(connector) = tls.connector()? {
+        builder = builder.use_preconfigured_tls(connector);
+    }
+    if let Some(timeout) = options.connect_timeout {
+        builder = builder.connect_timeout(timeout);
+    }
+    if let Some(timeout) = options.request_timeout {
+        builder = builder.timeout(timeout);
+    }
+    if let Some(user_agent) = &options.user_agent {
+        builder = builder.user_agent(user_agent.as_str());
+    }
+    if let Some(proxy) = &options.proxy {
+        builder = builder.proxy(proxy.clone());
+    }
+    if options.no_proxy {
+        builder = builder.no_proxy();
+    }
+    builder
+        .build()
+        .chain_err(|| "Failed to build reqwest client")
+}
+
+/// https://www.consul.io/api-docs/features/consistency and
+/// https://www.consul.io/api-docs/features/caching
 #[derive(Clone, Debug, Default)]
 pub struct QueryOptions {
     pub datacenter: Option<String>,
     pub wait_index: Option<u64>,
+    /// How long a blocking query may wait, sent with millisecond precision.
+    /// The request's own timeout is extended to cover it.
     pub wait_time: Option<Duration>,
+    /// Lets any server answer, possibly with stale data.
+    pub stale: bool,
+    /// Makes the leader confirm it is still the leader before answering.
+    pub consistent: bool,
+    /// Answers from the agent's cache, on endpoints that support it.
+    pub cached: bool,
+    /// With `cached`, the oldest cached result that may be returned.
+    pub max_age: Option<Duration>,
+    /// With `cached`, how long a stale result may be served while the servers
+    /// are unreachable.
+    pub stale_if_error: Option<Duration>,
+    /// Sorts nodes by round trip time from this node, or `_agent`.
+    pub near: Option<String>,
+    /// Only returns nodes with all of these metadata pairs.
+    pub node_meta: HashMap<String, String>,
+    /// A filter expression, see https://www.consul.io/api-docs/features/filtering
+    pub filter: Option<String>,
+    pub namespace: Option<String>,
+    pub partition: Option<String>,
+    /// Overrides `Config::token` for this request.
+    pub token: Option<String>,
 }
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, Default)]
 pub struct QueryMeta {
     pub last_index: Option<u64>,
     pub request_time: Duration,
+    /// Whether the answering server knew of a leader.
+    pub known_leader: bool,
+    /// How long ago the answering server last heard from the leader, for stale
+    /// reads.
+    pub last_contact: Duration,
+    /// Whether the agent answered from its cache.
+    pub cache_hit: bool,
+    /// The age of a cached answer.
+    pub cache_age: Option<Duration>,
+    /// The consistency mode the answer was served with, e.g. `leader`.
+    pub effective_consistency: Option<String>,
+    /// Whether addresses in the answer were translated to WAN addresses.
+    pub address_translation: bool,
 }
 
 #[derive(Clone, Debug, Default)]
@@ -0,0 +1,298 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{self, Receiver, Sender, TryRecvError};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+
+use crate::errors::{Error, Result, ResultExt};
+use crate::kv::{KVPair, KV};
+use crate::session::{format_session_ttl, RenewedSession, SessionEntry};
+use crate::{Client, QueryOptions};
+
+/// Flags value that marks a key as being used by a `Lock`, as in the Go client.
+pub const LOCK_FLAG_VALUE: u64 = 0x2ddc_cbc0_58a5_0c18;
+
+#[derive(Clone, Debug)]
+pub struct LockOptions {
+    /// The key to lock.
+    pub key: String,
+    /// Stored under the key while the lock is held.
+    pub value: Vec<u8>,
+    pub session_name: String,
+    /// From 10 seconds to a day, the range Consul accepts.
+    pub session_ttl: Duration,
+    /// How long each blocking query for the key waits.
+    pub lock_wait_time: Duration,
+    /// How long to wait after a failed acquire, e.g. during the lock delay.
+    pub lock_retry_time: Duration,
+    /// Give up after one round instead of waiting for the lock indefinitely.
+    pub lock_try_once: bool,
+    /// How many times a failing monitor query is retried before the lock is
+    /// considered lost.
+    pub monitor_retries: u32,
+    pub monitor_retry_time: Duration,
+}
+
+impl LockOptions {
+    pub fn new(key: &str) -> Self {
+        LockOptions {
+            key: key.to_owned(),
+            value: Vec::new(),
+            session_name: String::from("Consul API Lock"),
+            session_ttl: Duration::from_secs(15),
+            lock_wait_time: Duration::from_secs(15),
+            lock_retry_time: Duration::from_secs(5),
+            lock_try_once: false,
+            monitor_retries: 0,
+            monitor_retry_time: Duration::from_secs(2),
+        }
+    }
+
+    /// The session holding the lock, with `session_ttl` checked.
+    fn session_entry(&self) -> Result<SessionEntry> {
+        Ok(SessionEntry {
+            Name: Some(self.session_name.clone()),
+            TTL: Some(format_session_ttl(self.session_ttl)?),
+            ..Default::default()
+        })
+    }
+}
+
+pub(crate) type LostSignal = Arc<Mutex<Option<Sender<()>>>>;
+
+/// A distributed lock on a single KV key, like `api.Lock` in the Go client.
+///
+/// The lock is held through a session that is renewed in the background. While
+/// it is held, the key is watched and the lost-lock receiver returned by
+/// `lock` gets a message if the session is invalidated or the key is taken
+/// away. `unlock`, or dropping the `Lock`, releases the key and destroys the
+/// session.
+pub struct Lock {
+    client: Client,
+    options: LockOptions,
+    session: Option<RenewedSession>,
+    lost: LostSignal,
+    stop_monitor: Arc<AtomicBool>,
+}
+
+impl Lock {
+    pub fn new(client: Client, options: LockOptions) -> Self {
+        Lock {
+            client,
+            options,
+            session: None,
+            lost: Arc::new(Mutex::new(None)),
+            stop_monitor: Arc::new(AtomicBool::new(false)),
+        }
+    }
+
+    pub fn is_held(&self) -> bool {
+        self.session.is_some()
+    }
+
+    /// Waits until the lock is acquired and returns a receiver that is sent
+    /// `()` if the lock is lost. It is disconnected once the lock is released.
+    ///
+    /// The wait ends with `Ok(None)` when `cancel` receives a message or its
+    /// sender is dropped, or after one round with `lock_try_once`. Cancellation
+    /// is checked between blocking queries, so it takes up to `lock_wait_time`.
+    pub fn lock(&mut self, cancel: Option<&Receiver<()>>) -> Result<Option<Receiver<()>>> {
+        if self.is_held() {
+            return Err(Error::from("Lock already held"));
+        }
+
+        let entry = self.options.session_entry()?;
+        let lost = self.lost.clone();
+        let session = RenewedSession::create(&self.client, &entry, move || fire(&lost))?;
+
+        if !self.acquire(session.id(), cancel)? {
+            return Ok(None);
+        }
+
+        let (sender, receiver) = mpsc::channel();
+        *self.lost.lock().unwrap() = Some(sender);
+        self.stop_monitor = Arc::new(AtomicBool::new(false));
+        self.start_monitor(session.id().to_owned())?;
+        self.session = Some(session);
+        Ok(Some(receiver))
+    }
+
+    /// Releases the key and destroys the session.
+    pub fn unlock(&mut self) -> Result<()> {
+        let session = self
+            .session
+            .take()
+            .ok_or_else(|| Error::from("Lock not held"))?;
+        self.stop_monitor.store(true, Ordering::SeqCst);
+        self.lost.lock().unwrap().take();
+
+        let pair = self.pair(session.id());
+        self.client.release(&pair, None)?;
+        Ok(())
+    }
+
+    fn pair(&self, session: &str) -> KVPair {
+        KVPair {
+            Key: self.options.key.clone(),
+            Value: self.options.value.clone(),
+            Flags: Some(LOCK_FLAG_VALUE),
+            Session: Some(session.to_owned()),
+            ..Default::default()
+        }
+    }
+
+    fn acquire(&self, session: &str, cancel: Option<&Receiver<()>>) -> Result<bool> {
+        let mut wait_index = None;
+        loop {
+            if is_cancelled(cancel) {
+                return Ok(false);
+            }
+
+            let options = QueryOptions {
+                wait_index,
+                wait_time: Some(self.options.lock_wait_time),
+                ..Default::default()
+            };
+            let (pair, meta) = self.client.get(&self.options.key, Some(&options))?;
+            wait_index = meta.last_index;
+
+            let holder = match pair {
+                Some(pair) => {
+                    if pair.Flags != Some(LOCK_FLAG_VALUE) {
+                        return Err(Error::from(format!(
+                            "Existing key {} does not match lock use",
+                            self.options.key
+                        )));
+                    }
+                    pair.Session
+                }
+                None => None,
+            };
+
+            match holder {
+                Some(ref holder) if holder == session => return Ok(true),
+                Some(_) => {}
+                None => {
+                    if self.client.acquire(&self.pair(session), None)?.0 {
+                        return Ok(true);
+                    }
+                    // Most likely the lock delay after a previous holder's
+                    // session was invalidated.
+                    if !self.options.lock_try_once {
+                        thread::sleep(self.options.lock_retry_time);
+                    }
+                }
+            }
+
+            if self.options.lock_try_once {
+                return Ok(false);
+            }
+        }
+    }
+
+    fn start_monitor(&self, session: String) -> Result<()> {
+        let client = self.client.clone();
+        let options = self.options.clone();
+        let lost = self.lost.clone();
+        let stop = self.stop_monitor.clone();
+        thread::Builder::new()
+            .name(format!("consul-lock-{}", options.key))
+            .spawn(move || {
+                let mut wait_index = None;
+                let mut retries = options.monitor_retries;
+                while !stop.load(Ordering::SeqCst) {
+                    let query = QueryOptions {
+                        wait_index,
+                        wait_time: Some(options.lock_wait_time),
+                        ..Default::default()
+                    };
+                    match client.get(&options.key, Some(&query)) {
+                        Ok((pair, meta)) => {
+                            if pair.and_then(|p| p.Session).as_ref() != Some(&session) {
+                                break;
+                            }
+                            wait_index = meta.last_index;
+                            retries = options.monitor_retries;
+                        }
+                        Err(_) if retries > 0 => {
+                            retries -= 1;
+                            thread::sleep(options.monitor_retry_time);
+                        }
+                        Err(_) => break,
+                    }
+                }
+                if !stop.load(Ordering::SeqCst) {
+                    fire(&lost);
+                }
+            })
+            .map(|_| ())
+            .chain_err(|| "Failed to start lock monitor thread")
+    }
+}
+
+impl Drop for Lock {
+    fn drop(&mut self) {
+        if self.is_held() {
+            let _ = self.unlock();
+        }
+    }
+}
+
+pub(crate) fn fire(lost: &LostSignal) {
+    if let Some(sender) = lost.lock().unwrap().take() {
+        let _ = sender.send(());
+    }
+}
+
+pub(crate) fn is_cancelled(cancel: Option<&Receiver<()>>) -> bool {
+    match cancel.map(Receiver::try_recv) {
+        Some(Ok(())) | Some(Err(TryRecvError::Disconnected)) => true,
+        Some(Err(TryRecvError::Empty)) | None => false,
+    }
+}
+
+#[cfg(test)]
+mod lock_tests {
+    use super::*;
+
+    #[test]
+    fn is_cancelled_test() {
+        assert!(!is_cancelled(None));
+
+        let (sender, receiver) = mpsc::channel();
+        assert!(!is_cancelled(Some(&receiver)));
+        sender.send(()).unwrap();
+        assert!(is_cancelled(Some(&receiver)));
+        drop(sender);
+        assert!(is_cancelled(Some(&receiver)));
+    }
+
+    #[test]
+    fn fire_sends_once_test() {
+        let (sender, receiver) = mpsc::channel();
+        let lost: LostSignal = Arc::new(Mutex::new(Some(sender)));
+
+        fire(&lost);
+        fire(&lost);
+
+        assert_eq!(receiver.recv(), Ok(()));
+        assert!(receiver.recv().is_err());
+    }
+
+    #[test]
+    fn session_entry_ttl_test() {
+        let mut options = LockOptions::new("service/web/leader");
+        assert_eq!(options.session_entry().unwrap().TTL.as_deref(), Some("15s"));
+
+        options.session_ttl = Duration::from_millis(10_500);
+        assert_eq!(
+            options.session_entry().unwrap().TTL.as_deref(),
+            Some("10500ms")
+        );
+
+        options.session_ttl = Duration::from_millis(500);
+        assert!(options.session_entry().is_err());
+        options.session_ttl = Duration::from_secs(86401);
+        assert!(options.session_entry().is_err());
+    }
+}
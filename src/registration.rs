@@ -0,0 +1,282 @@
+use std::sync::mpsc::{self, RecvTimeoutError, Sender};
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+
+use crate::agent::{Agent, AgentCheck, AgentServiceRegistration};
+use crate::duration::parse_duration;
+use crate::errors::{Error, Result, ResultExt};
+use crate::Client;
+
+// Consul never reaps critical services sooner than this.
+const DEFAULT_DEREGISTER_CRITICAL_AFTER: &str = "1m";
+// The shortest TTL a registration heartbeats for.
+const MIN_TTL: Duration = Duration::from_secs(1);
+
+type HealthCheck = Box<dyn FnMut() -> AgentCheck + Send>;
+
+/// Keeps a service registered with the local agent for as long as it is alive.
+///
+/// If the registration has a TTL check, a background thread updates it at half
+/// the TTL: with a plain `register` the check is kept passing, with
+/// `register_with_health` the closure's `Status` and `Output` are reported.
+/// The TTL has to be at least a second. A TTL check without
+/// `DeregisterCriticalServiceAfter` gets one minute, so a process that dies
+/// without deregistering is reaped by Consul.
+///
+/// The service is deregistered on `deregister` or when the guard is dropped.
+pub struct ServiceRegistration {
+    client: Client,
+    service_id: String,
+    stop: Option<Sender<()>>,
+    heartbeat: Option<JoinHandle<()>>,
+    registered: bool,
+}
+
+impl ServiceRegistration {
+    pub fn register(client: Client, service: AgentServiceRegistration) -> Result<Self> {
+        Self::start(client, service, None)
+    }
+
+    pub fn register_with_health<F>(
+        client: Client,
+        service: AgentServiceRegistration,
+        health: F,
+    ) -> Result<Self>
+    where
+        F: FnMut() -> AgentCheck + Send + 'static,
+    {
+        Self::start(client, service, Some(Box::new(health)))
+    }
+
+    pub fn service_id(&self) -> &str {
+        &self.service_id
+    }
+
+    /// Stops the heartbeat and deregisters the service, reporting any error.
+    pub fn deregister(mut self) -> Result<()> {
+        self.shutdown()
+    }
+
+    fn start(
+        client: Client,
+        mut service: AgentServiceRegistration,
+        health: Option<HealthCheck>,
+    ) -> Result<Self> {
+        let service_id = service.ID.clone().unwrap_or_else(|| service.Name.clone());
+        service.ID = Some(service_id.clone());
+        let ttl_check = prepare_ttl_check(&mut service, &service_id)?;
+        if health.is_some() && ttl_check.is_none() {
+            return Err(Error::from(
+                "A TTL check is required to report the health of a service",
+            ));
+        }
+
+        client.service_register(&service)?;
+
+        let mut registration = ServiceRegistration {
+            client,
+            service_id,
+            stop: None,
+            heartbeat: None,
+            registered: true,
+        };
+
+        if let Some((check_id, ttl)) = ttl_check {
+            let (stop, stopped) = mpsc::channel();
+            let mut heartbeat = Heartbeat {
+                client: registration.client.clone(),
+                service,
+                check_id,
+                health,
+            };
+            heartbeat.beat();
+            let handle = thread::Builder::new()
+                .name(format!("consul-heartbeat-{}", registration.service_id))
+                .spawn(move || {
+                    while let Err(RecvTimeoutError::Timeout) = stopped.recv_timeout(ttl / 2) {
+                        heartbeat.beat();
+                    }
+                })
+                .chain_err(|| "Failed to start heartbeat thread")?;
+            registration.stop = Some(stop);
+            registration.heartbeat = Some(handle);
+        }
+
+        Ok(registration)
+    }
+
+    fn shutdown(&mut self) -> Result<()> {
+        self.stop.take();
+        if let Some(handle) = self.heartbeat.take() {
+            let _ = handle.join();
+        }
+        if self.registered {
+            self.registered = false;
+            self.client.service_deregister(&self.service_id)?;
+        }
+        Ok(())
+    }
+}
+
+impl Drop for ServiceRegistration {
+    fn drop(&mut self) {
+        let _ = self.shutdown();
+    }
+}
+
+struct Heartbeat {
+    client: Client,
+    service: AgentServiceRegistration,
+    check_id: String,
+    health: Option<HealthCheck>,
+}
+
+impl Heartbeat {
+    fn beat(&mut self) {
+        let result = match self.health.as_mut() {
+            Some(health) => {
+                let check = health();
+                self.client
+                    .check_update(&self.check_id, &check.Status, Some(&check.Output))
+            }
+            None => self.client.check_pass(&self.check_id, None),
+        };
+        // The agent forgets the service when it restarts without persisted state.
+        if let Err(e) = result {
+            if e.kind().is_not_found() {
+                let _ = self.client.service_register(&self.service);
+            }
+        }
+    }
+}
+
+/// Finds the service's TTL check, giving it an ID and a deregistration timeout
+/// if it has none, and returns the check ID and TTL. A TTL below a second is
+/// an error.
+fn prepare_ttl_check(
+    service: &mut AgentServiceRegistration,
+    service_id: &str,
+) -> Result<Option<(String, Duration)>> {
+    let single = service.Check.iter_mut().map(|c| (c, None));
+    let multiple = service
+        .Checks
+        .iter_mut()
+        .flatten()
+        .enumerate()
+        .map(|(i, c)| (c, Some(i + 1)));
+
+    for (check, position) in single.chain(multiple) {
+        let ttl = match check.TTL.as_ref() {
+            Some(ttl) => parse_duration(ttl)?,
+            None => continue,
+        };
+        // The heartbeat runs every ttl / 2 and would spin on a tiny TTL.
+        if ttl < MIN_TTL {
+            return Err(Error::from(format!(
+                "TTL of check {:?} is below the minimum of {:?}",
+                check.TTL.as_deref().unwrap_or_default(),
+                MIN_TTL
+            )));
+        }
+        // The IDs the agent would have generated for these checks.
+        let check_id = check.CheckID.get_or_insert_with(|| match position {
+            Some(i) => format!("service:{}:{}", service_id, i),
+            None => format!("service:{}", service_id),
+        });
+        let check_id = check_id.clone();
+        check
+            .DeregisterCriticalServiceAfter
+            .get_or_insert_with(|| String::from(DEFAULT_DEREGISTER_CRITICAL_AFTER));
+        return Ok(Some((check_id, ttl)));
+    }
+    Ok(None)
+}
+
+#[cfg(test)]
+mod registration_tests {
+    use super::*;
+
+    use crate::agent::AgentServiceCheck;
+
+    #[test]
+    fn prepare_single_ttl_check_test() {
+        let mut service = AgentServiceRegistration {
+            Name: String::from("web"),
+            Check: Some(AgentServiceCheck {
+                TTL: Some(String::from("10s")),
+                ..Default::default()
+            }),
+            ..Default::default()
+        };
+
+        let ttl_check = prepare_ttl_check(&mut service, "web-1").unwrap();
+
+        assert_eq!(
+            ttl_check,
+            Some((String::from("service:web-1"), Duration::from_secs(10)))
+        );
+        let check = service.Check.unwrap();
+        assert_eq!(check.CheckID.unwrap(), "service:web-1");
+        assert_eq!(check.DeregisterCriticalServiceAfter.unwrap(), "1m");
+    }
+
+    #[test]
+    fn prepare_ttl_check_among_checks_test() {
+        let mut service = AgentServiceRegistration {
+            Name: String::from("web"),
+            Checks: Some(vec![
+                AgentServiceCheck {
+                    HTTP: Some(String::from("http://localhost/health")),
+                    Interval: Some(String::from("10s")),
+                    ..Default::default()
+                },
+                AgentServiceCheck {
+                    CheckID: Some(String::from("web-ttl")),
+                    TTL: Some(String::from("30s")),
+                    DeregisterCriticalServiceAfter: Some(String::from("5m")),
+                    ..Default::default()
+                },
+            ]),
+            ..Default::default()
+        };
+
+        let ttl_check = prepare_ttl_check(&mut service, "web").unwrap();
+
+        assert_eq!(
+            ttl_check,
+            Some((String::from("web-ttl"), Duration::from_secs(30)))
+        );
+        let checks = service.Checks.unwrap();
+        assert_eq!(checks[0].CheckID, None);
+        assert_eq!(
+            checks[1].DeregisterCriticalServiceAfter.as_deref(),
+            Some("5m")
+        );
+    }
+
+    #[test]
+    fn prepare_ttl_check_rejects_short_ttl_test() {
+        for ttl in &["0s", "500ms", "0.9s"] {
+            let mut service = AgentServiceRegistration {
+                Name: String::from("web"),
+                Check: Some(AgentServiceCheck {
+                    TTL: Some(String::from(*ttl)),
+                    ..Default::default()
+                }),
+                ..Default::default()
+            };
+
+            assert!(prepare_ttl_check(&mut service, "web").is_err(), "{}", ttl);
+        }
+    }
+
+    #[test]
+    fn prepare_without_ttl_check_test() {
+        let mut service = AgentServiceRegistration {
+            Name: String::from("web"),
+            ..Default::default()
+        };
+
+        assert_eq!(prepare_ttl_check(&mut service, "web").unwrap(), None);
+    }
+}
@@ -1,31 +1,192 @@
 use reqwest::blocking::Client as HttpClient;
 use reqwest::blocking::RequestBuilder;
+use reqwest::header::HeaderMap;
+use reqwest::StatusCode;
 
 use serde::de::DeserializeOwned;
 use serde::Serialize;
 
 use std::collections::HashMap;
 use std::str;
-use std::time::Instant;
+use std::str::FromStr;
+use std::thread;
+use std::time::{Duration, Instant};
 
 use url::Url;
 
-use crate::errors::{Result, ResultExt};
-use crate::{Config, QueryOptions, WriteMeta, WriteOptions};
+use crate::errors::{ErrorKind, Result, ResultExt};
+use crate::retry;
+use crate::transport::{HttpRequest, HttpResponse};
+use crate::{Config, QueryMeta, QueryOptions, WriteMeta, WriteOptions};
 
+#[cfg(feature = "async")]
+pub mod async_requests;
 pub mod delete_requests;
 pub mod get_requests;
 pub mod post_requests;
 pub mod put_requests;
+pub(crate) mod tls_socket;
+pub(crate) mod unix_socket;
+
+/// The parts of reqwest's request builders used to build consul requests. It is
+/// implemented for both the blocking and the async builder so `Client` and
+/// `AsyncClient` share the same request construction.
+pub trait ConsulRequestBuilder: Sized {
+    fn header(self, name: &str, value: &str) -> Self;
+    fn json<T: Serialize + ?Sized>(self, json: &T) -> Self;
+    fn timeout(self, timeout: Duration) -> Self;
+}
+
+impl ConsulRequestBuilder for RequestBuilder {
+    fn header(self, name: &str, value: &str) -> Self {
+        RequestBuilder::header(self, name, value)
+    }
+
+    fn json<T: Serialize + ?Sized>(self, json: &T) -> Self {
+        RequestBuilder::json(self, json)
+    }
+
+    fn timeout(self, timeout: Duration) -> Self {
+        RequestBuilder::timeout(self, timeout)
+    }
+}
+
+#[cfg(feature = "async")]
+impl ConsulRequestBuilder for reqwest::RequestBuilder {
+    fn header(self, name: &str, value: &str) -> Self {
+        reqwest::RequestBuilder::header(self, name, value)
+    }
+
+    fn json<T: Serialize + ?Sized>(self, json: &T) -> Self {
+        reqwest::RequestBuilder::json(self, json)
+    }
+
+    fn timeout(self, timeout: Duration) -> Self {
+        reqwest::RequestBuilder::timeout(self, timeout)
+    }
+}
 
-pub fn add_config_options(builder: RequestBuilder, config: &Config) -> RequestBuilder {
+pub fn add_config_options<B: ConsulRequestBuilder>(builder: B, config: &Config) -> B {
+    let builder = add_http_auth(builder, config);
     match &config.token {
         Some(val) => builder.header("X-Consul-Token", val),
         None => builder,
     }
 }
 
-fn construct_write_request_builder<T: Serialize, R: DeserializeOwned, F>(
+fn add_http_auth<B: ConsulRequestBuilder>(builder: B, config: &Config) -> B {
+    match &config.http_auth {
+        Some(auth) => builder.header("Authorization", &auth.header_value()),
+        None => builder,
+    }
+}
+
+// Consul waits this long when a blocking query gives no wait time, and never
+// longer than the maximum.
+const DEFAULT_WAIT: Duration = Duration::from_secs(5 * 60);
+const MAX_WAIT: Duration = Duration::from_secs(10 * 60);
+// Time left for the request itself on top of the wait, the same as reqwest's
+// default timeout for an ordinary request.
+const BLOCKING_QUERY_MARGIN: Duration = Duration::from_secs(30);
+
+/// How long the client waits for a response to a blocking query before giving
+/// up. Consul adds up to wait/16 of random jitter to the wait, so this covers
+/// the wait, the jitter and a margin. `None` when the query does not block.
+pub fn blocking_query_timeout(options: Option<&QueryOptions>) -> Option<Duration> {
+    let options = options.filter(|o| o.wait_index.is_some())?;
+    let wait = options.wait_time.unwrap_or(DEFAULT_WAIT).min(MAX_WAIT);
+    Some(wait + wait / 16 + BLOCKING_QUERY_MARGIN)
+}
+
+/// Formats a wait time for the `wait` parameter, in whole seconds when
+/// possible and milliseconds otherwise.
+fn format_wait(wait: Duration) -> String {
+    if wait.subsec_millis() == 0 {
+        format!("{}s", wait.as_secs())
+    } else {
+        format!("{}ms", wait.as_millis())
+    }
+}
+
+/// Adds the token, preferring the one in `options`, the cache directives and,
+/// for blocking queries, a timeout longer than the wait.
+pub fn add_query_options<B: ConsulRequestBuilder>(
+    builder: B,
+    config: &Config,
+    options: Option<&QueryOptions>,
+) -> B {
+    let token = options
+        .and_then(|o| o.token.as_ref())
+        .or(config.token.as_ref());
+    let builder = add_http_auth(builder, config);
+    let builder = match token {
+        Some(token) => builder.header("X-Consul-Token", token),
+        None => builder,
+    };
+    let mut directives = Vec::new();
+    if let Some(options) = options {
+        if let Some(max_age) = options.max_age {
+            directives.push(format!("max-age={}", max_age.as_secs()));
+        }
+        if let Some(stale_if_error) = options.stale_if_error {
+            directives.push(format!("stale-if-error={}", stale_if_error.as_secs()));
+        }
+    }
+    let builder = if directives.is_empty() {
+        builder
+    } else {
+        builder.header("Cache-Control", &directives.join(", "))
+    };
+    match blocking_query_timeout(options) {
+        Some(timeout) => builder.timeout(timeout),
+        None => builder,
+    }
+}
+
+pub fn request_url(path: &str, config: &Config, params: &HashMap<String, String>) -> Result<Url> {
+    // Requests over a unix socket still need a URL for the path and query.
+    let base = match config.unix_socket() {
+        Some(_) => "http://localhost",
+        None => &config.address,
+    };
+    let url_str = format!("{}{}", base, path);
+    Url::parse_with_params(&url_str, params.iter()).chain_err(|| "Failed to parse URL")
+}
+
+/// The URL of a read. Node metadata filters go in a repeated `node-meta`
+/// parameter, which `params` cannot hold.
+pub fn query_url(
+    path: &str,
+    config: &Config,
+    params: &HashMap<String, String>,
+    options: Option<&QueryOptions>,
+) -> Result<Url> {
+    let mut url = request_url(path, config, params)?;
+    if let Some(options) = options {
+        let mut node_meta: Vec<_> = options.node_meta.iter().collect();
+        node_meta.sort();
+        for (key, value) in node_meta {
+            url.query_pairs_mut()
+                .append_pair("node-meta", &format!("{}:{}", key, value));
+        }
+    }
+    Ok(url)
+}
+
+pub fn prepare_write_request<T: Serialize, B: ConsulRequestBuilder>(
+    builder: B,
+    body: Option<&T>,
+    config: &Config,
+) -> B {
+    let builder = if let Some(b) = body {
+        builder.json(b)
+    } else {
+        builder
+    };
+    add_config_options(builder, config)
+}
+
+fn construct_write_request_builder<T: Serialize, F>(
     path: &str,
     body: Option<&T>,
     config: &Config,
@@ -35,19 +196,9 @@ fn construct_write_request_builder<T: Serialize, R: DeserializeOwned, F>(
 where
     F: Fn(&HttpClient, Url) -> RequestBuilder,
 {
-    let url_str = format!("{}{}", config.address, path);
-    let url =
-        Url::parse_with_params(&url_str, params.iter()).chain_err(|| "Failed to parse URL")?;
-
+    let url = request_url(path, config, &params)?;
     let builder = request_builder_from_http_client(&config.http_client, url);
-    let builder = if let Some(b) = body {
-        builder.json(b)
-    } else {
-        builder
-    };
-    let builder = add_config_options(builder, &config);
-
-    Ok(builder)
+    Ok(prepare_write_request(builder, body, config))
 }
 
 fn update_params_with_query_options(
@@ -67,11 +218,125 @@ fn update_params_with_query_options(
             params.insert(String::from("index"), index.to_string());
         }
         if let Some(wait_time) = options.wait_time {
-            params.insert(String::from("wait"), format!("{}s", wait_time.as_secs()));
+            params.insert(String::from("wait"), format_wait(wait_time));
         }
+        let flags = [
+            ("stale", options.stale),
+            ("consistent", options.consistent),
+            ("cached", options.cached),
+        ];
+        for (name, set) in &flags {
+            if *set {
+                params.insert(String::from(*name), String::new());
+            }
+        }
+        let values = [("near", &options.near), ("filter", &options.filter)];
+        for (name, value) in &values {
+            if let Some(value) = value {
+                params.insert(String::from(*name), value.to_owned());
+            }
+        }
+    }
+    let namespace = options
+        .and_then(|o| o.namespace.as_ref())
+        .or(config.namespace.as_ref());
+    let partition = options
+        .and_then(|o| o.partition.as_ref())
+        .or(config.partition.as_ref());
+    update_params_with_tenancy(params, namespace, partition);
+}
+
+/// Adds the Enterprise namespace and admin partition.
+fn update_params_with_tenancy(
+    params: &mut HashMap<String, String>,
+    namespace: Option<&String>,
+    partition: Option<&String>,
+) {
+    if let Some(namespace) = namespace {
+        params.insert(String::from("ns"), namespace.to_owned());
+    }
+    if let Some(partition) = partition {
+        params.insert(String::from("partition"), partition.to_owned());
     }
 }
 
+fn update_params_with_write_options(
+    config: &Config,
+    params: &mut HashMap<String, String>,
+    options: Option<&WriteOptions>,
+) {
+    let datacenter: Option<&String> = options
+        .and_then(|o| o.datacenter.as_ref())
+        .or_else(|| config.datacenter.as_ref());
+
+    if let Some(dc) = datacenter {
+        params.insert(String::from("dc"), dc.to_owned());
+    }
+    update_params_with_tenancy(params, config.namespace.as_ref(), config.partition.as_ref());
+}
+
+pub fn parse_last_index(headers: &HeaderMap) -> Result<Option<u64>> {
+    match headers.get("X-Consul-Index") {
+        Some(value) => str::from_utf8(value.as_bytes())
+            .chain_err(|| "Failed to parse valid UT8 for last index")
+            .and_then(|s| {
+                u64::from_str(s).chain_err(|| "Failed to parse valid number for last index")
+            })
+            .map(Some),
+        None => Ok(None),
+    }
+}
+
+/// Reads the `QueryMeta` of a response from its headers.
+pub fn parse_query_meta(headers: &HeaderMap, request_time: Duration) -> Result<QueryMeta> {
+    let text = |name: &str| headers.get(name).and_then(|v| v.to_str().ok());
+    let number = |name: &str| -> Result<Option<u64>> {
+        text(name)
+            .map(|v| {
+                u64::from_str(v).chain_err(|| format!("Failed to parse valid number for {}", name))
+            })
+            .transpose()
+    };
+    Ok(QueryMeta {
+        last_index: parse_last_index(headers)?,
+        request_time,
+        known_leader: text("X-Consul-KnownLeader") == Some("true"),
+        last_contact: Duration::from_millis(number("X-Consul-LastContact")?.unwrap_or(0)),
+        cache_hit: text("X-Cache") == Some("HIT"),
+        cache_age: number("Age")?.map(Duration::from_secs),
+        effective_consistency: text("X-Consul-Effective-Consistency").map(str::to_owned),
+        address_translation: text("X-Consul-Translate-Addresses") == Some("true"),
+    })
+}
+
+pub fn parse_json<R: DeserializeOwned>(body: &[u8]) -> Result<R> {
+    // Several endpoints (e.g. agent registration) answer with an empty body.
+    let body: &[u8] = if body.iter().all(u8::is_ascii_whitespace) {
+        b"null"
+    } else {
+        body
+    };
+    serde_json::from_slice(body).chain_err(|| "Failed to parse JSON response")
+}
+
+pub fn check_status(status: StatusCode, body: &[u8], path: &str) -> Result<()> {
+    if status.is_success() {
+        Ok(())
+    } else {
+        let body = String::from_utf8_lossy(body).trim().to_owned();
+        Err(ErrorKind::ConsulError(status.as_u16(), body, path.to_owned()).into())
+    }
+}
+
+pub fn parse_response<R: DeserializeOwned>(
+    status: StatusCode,
+    body: &[u8],
+    path: &str,
+) -> Result<R> {
+    check_status(status, body, path)?;
+    parse_json(body)
+}
+
 pub fn write_with_body<T: Serialize, R: DeserializeOwned, F>(
     path: &str,
     body: Option<&T>,
@@ -85,43 +350,106 @@ where
 {
     let start = Instant::now();
 
-    let datacenter: Option<&String> = options
-        .and_then(|o| o.datacenter.as_ref())
-        .or_else(|| config.datacenter.as_ref());
-
-    if let Some(dc) = datacenter {
-        params.insert(String::from("dc"), dc.to_owned());
-    }
+    update_params_with_write_options(config, &mut params, options);
 
-    let builder = construct_write_request_builder::<T, R, F>(
+    let builder = construct_write_request_builder::<T, F>(
         path,
         body,
         config,
         params,
         request_builder_from_http_client,
-    );
-
-    builder?
-        .send()
-        .chain_err(|| "HTTP request to consul failed")
-        .and_then(|x| x.json().chain_err(|| "Failed to parse JSON"))
-        .map(|x| {
-            (
-                x,
-                WriteMeta {
-                    request_time: Instant::now() - start,
-                },
-            )
-        })
+    )?;
+
+    send_write_request(builder, config, path, start)
+}
+
+/// Like `write_with_body`, but sends `body` as-is instead of encoding it as JSON.
+pub fn write_with_raw_body<R: DeserializeOwned, F>(
+    path: &str,
+    body: &[u8],
+    config: &Config,
+    mut params: HashMap<String, String>,
+    options: Option<&WriteOptions>,
+    request_builder_from_http_client: F,
+) -> Result<(R, WriteMeta)>
+where
+    F: Fn(&HttpClient, Url) -> RequestBuilder,
+{
+    let start = Instant::now();
+
+    update_params_with_write_options(config, &mut params, options);
+
+    let url = request_url(path, config, &params)?;
+    let builder = request_builder_from_http_client(&config.http_client, url).body(body.to_vec());
+    let builder = add_config_options(builder, config);
+
+    send_write_request(builder, config, path, start)
+}
+
+fn send_write_request<R: DeserializeOwned>(
+    builder: RequestBuilder,
+    config: &Config,
+    path: &str,
+    start: Instant,
+) -> Result<(R, WriteMeta)> {
+    let response = send(builder, config)?;
+    parse_response(response.status, &response.body, path).map(|x| {
+        (
+            x,
+            WriteMeta {
+                request_time: Instant::now() - start,
+            },
+        )
+    })
+}
+
+/// Sends a request built with `config.http_client` through the config's
+/// transport, retrying it as `config.retry_policy` allows.
+pub fn send(builder: RequestBuilder, config: &Config) -> Result<HttpResponse> {
+    let request = builder
+        .build()
+        .chain_err(|| "Failed to build HTTP request")?;
+    let body = request
+        .body()
+        .and_then(|b| b.as_bytes())
+        .map(<[u8]>::to_vec)
+        .unwrap_or_default();
+    let policy = &config.retry_policy;
+    let retryable = policy.allows(request.method());
+    let targets = retry::targets(config, request.url())?;
+    let transport = config.transport();
+    let mut attempt = 1;
+    loop {
+        let mut last = None;
+        for url in &targets {
+            let result = transport.send(HttpRequest {
+                method: request.method().clone(),
+                url: url.clone(),
+                headers: request.headers().clone(),
+                body: body.clone(),
+                timeout: request.timeout().copied(),
+            });
+            if !retry::should_fail_over(retryable, &result) {
+                return result;
+            }
+            last = Some(result);
+        }
+        if !retryable || attempt >= policy.max_attempts {
+            // There is always at least the agent's own address.
+            return last.unwrap();
+        }
+        thread::sleep(policy.backoff(attempt));
+        attempt += 1;
+    }
 }
 
 #[cfg(test)]
 pub mod request_tests {
 
     use super::*;
+    use crate::config::HttpBasicAuth;
     use rand::distributions::Alphanumeric;
     use rand::{thread_rng, Rng};
-    use std::time::Duration;
 
     fn setup() -> (RequestBuilder, String) {
         let client = HttpClient::new();
@@ -178,7 +506,7 @@ pub mod request_tests {
         let request_builder_from_http_client =
             |http_client: &HttpClient, url: Url| -> RequestBuilder { http_client.put(url) };
 
-        let builder = construct_write_request_builder::<String, String, _>(
+        let builder = construct_write_request_builder::<String, _>(
             path,
             body,
             &config,
@@ -209,7 +537,7 @@ pub mod request_tests {
         let request_builder_from_http_client =
             |http_client: &HttpClient, url: Url| -> RequestBuilder { http_client.put(url) };
 
-        let builder = construct_write_request_builder::<String, String, _>(
+        let builder = construct_write_request_builder::<String, _>(
             path,
             body,
             &config,
@@ -240,6 +568,7 @@ pub mod request_tests {
             datacenter: Some(String::from("test_datacenter")),
             wait_index: Some(123),
             wait_time: Some(Duration::new(5, 0)),
+            ..Default::default()
         };
 
         update_params_with_query_options(&config, &mut params, Some(&query_options));
@@ -250,6 +579,262 @@ pub mod request_tests {
         assert_eq!(params.get("wait").unwrap(), "5s");
     }
 
+    #[test]
+    fn update_params_with_sub_second_wait_test() {
+        let config = Config::new().unwrap();
+        let mut params = HashMap::<String, String>::new();
+        let query_options = QueryOptions {
+            wait_index: Some(7),
+            wait_time: Some(Duration::from_millis(1500)),
+            ..Default::default()
+        };
+
+        update_params_with_query_options(&config, &mut params, Some(&query_options));
+
+        assert_eq!(params.get("wait").unwrap(), "1500ms");
+        assert_eq!(format_wait(Duration::from_millis(500)), "500ms");
+        assert_eq!(format_wait(Duration::from_secs(90)), "90s");
+    }
+
+    #[test]
+    fn blocking_query_timeout_test() {
+        let wait = |wait_index, wait_time| QueryOptions {
+            wait_index,
+            wait_time,
+            ..Default::default()
+        };
+
+        assert_eq!(blocking_query_timeout(None), None);
+        assert_eq!(
+            blocking_query_timeout(Some(&wait(None, Some(Duration::from_secs(60))))),
+            None
+        );
+        assert_eq!(
+            blocking_query_timeout(Some(&wait(Some(1), Some(Duration::from_secs(160))))),
+            Some(Duration::from_secs(160 + 10 + 30))
+        );
+        assert_eq!(
+            blocking_query_timeout(Some(&wait(Some(1), None))),
+            Some(Duration::from_secs(300 + 18) + Duration::from_millis(750 + 30_000))
+        );
+        assert_eq!(
+            blocking_query_timeout(Some(&wait(Some(1), Some(Duration::from_secs(3600))))),
+            Some(Duration::from_secs(600 + 37) + Duration::from_millis(500 + 30_000))
+        );
+    }
+
+    #[test]
+    fn add_query_options_blocking_timeout_test() {
+        let (builder, _) = setup();
+        let config = Config::new().unwrap();
+        let query_options = QueryOptions {
+            wait_index: Some(3),
+            wait_time: Some(Duration::from_secs(32)),
+            ..Default::default()
+        };
+
+        let request = add_query_options(builder, &config, Some(&query_options))
+            .build()
+            .unwrap();
+
+        assert_eq!(request.timeout(), Some(&Duration::from_secs(32 + 2 + 30)));
+    }
+
+    #[test]
+    fn update_params_with_query_options_consistency_test() {
+        let config = Config::new().unwrap();
+        let mut params = HashMap::<String, String>::new();
+        let query_options = QueryOptions {
+            stale: true,
+            cached: true,
+            near: Some(String::from("_agent")),
+            filter: Some(String::from("Service.Tags contains \"primary\"")),
+            namespace: Some(String::from("team-a")),
+            partition: Some(String::from("default")),
+            ..Default::default()
+        };
+
+        update_params_with_query_options(&config, &mut params, Some(&query_options));
+
+        assert_eq!(params.len(), 6);
+        assert_eq!(params.get("stale").unwrap(), "");
+        assert_eq!(params.get("cached").unwrap(), "");
+        assert!(!params.contains_key("consistent"));
+        assert_eq!(params.get("near").unwrap(), "_agent");
+        assert_eq!(
+            params.get("filter").unwrap(),
+            "Service.Tags contains \"primary\""
+        );
+        assert_eq!(params.get("ns").unwrap(), "team-a");
+        assert_eq!(params.get("partition").unwrap(), "default");
+    }
+
+    #[test]
+    fn update_params_with_config_tenancy_test() {
+        let mut config = Config::new().unwrap();
+        config.namespace = Some(String::from("team-a"));
+        config.partition = Some(String::from("billing"));
+        let query_options = QueryOptions {
+            namespace: Some(String::from("team-b")),
+            ..Default::default()
+        };
+
+        let mut query_params = HashMap::<String, String>::new();
+        update_params_with_query_options(&config, &mut query_params, Some(&query_options));
+        let mut write_params = HashMap::<String, String>::new();
+        update_params_with_write_options(&config, &mut write_params, None);
+
+        assert_eq!(query_params.get("ns").unwrap(), "team-b");
+        assert_eq!(query_params.get("partition").unwrap(), "billing");
+        assert_eq!(write_params.get("ns").unwrap(), "team-a");
+        assert_eq!(write_params.get("partition").unwrap(), "billing");
+    }
+
+    #[test]
+    fn add_config_options_http_auth_test() {
+        let (builder, _) = setup();
+        let mut config = Config::new().unwrap();
+        config.http_auth = Some(HttpBasicAuth::parse("admin:hunter2"));
+
+        let request = add_config_options(builder, &config).build().unwrap();
+
+        assert_eq!(
+            request.headers()["authorization"],
+            "Basic YWRtaW46aHVudGVyMg=="
+        );
+    }
+
+    #[test]
+    fn query_url_node_meta_test() {
+        let mut config = Config::new().unwrap();
+        config.address = String::from("http://127.0.0.1:8500");
+        let mut query_options = QueryOptions::default();
+        query_options
+            .node_meta
+            .insert(String::from("rack"), String::from("r1"));
+        query_options
+            .node_meta
+            .insert(String::from("env"), String::from("prod"));
+
+        let url = query_url(
+            "/v1/catalog/nodes",
+            &config,
+            &HashMap::new(),
+            Some(&query_options),
+        )
+        .unwrap();
+
+        assert_eq!(
+            url.as_str(),
+            "http://127.0.0.1:8500/v1/catalog/nodes?node-meta=env%3Aprod&node-meta=rack%3Ar1"
+        );
+    }
+
+    #[test]
+    fn add_query_options_test() {
+        let (builder, expected_token) = setup();
+        let mut config = Config::new().unwrap();
+        config.token = Some(String::from("config-token"));
+        let query_options = QueryOptions {
+            token: Some(expected_token.clone()),
+            max_age: Some(Duration::from_secs(30)),
+            stale_if_error: Some(Duration::from_secs(300)),
+            ..Default::default()
+        };
+
+        let request = add_query_options(builder, &config, Some(&query_options))
+            .build()
+            .unwrap();
+        let headers = request.headers();
+
+        assert_eq!(headers.get_all("x-consul-token").iter().count(), 1);
+        assert_eq!(headers["x-consul-token"], expected_token);
+        assert_eq!(headers["cache-control"], "max-age=30, stale-if-error=300");
+    }
+
+    #[test]
+    fn parse_query_meta_test() {
+        let mut headers = HeaderMap::new();
+        headers.insert("X-Consul-Index", "42".parse().unwrap());
+        headers.insert("X-Consul-KnownLeader", "true".parse().unwrap());
+        headers.insert("X-Consul-LastContact", "250".parse().unwrap());
+        headers.insert("X-Cache", "HIT".parse().unwrap());
+        headers.insert("Age", "14".parse().unwrap());
+        headers.insert("X-Consul-Effective-Consistency", "stale".parse().unwrap());
+        headers.insert("X-Consul-Translate-Addresses", "true".parse().unwrap());
+
+        let meta = parse_query_meta(&headers, Duration::from_millis(3)).unwrap();
+
+        assert_eq!(meta.last_index, Some(42));
+        assert_eq!(meta.request_time, Duration::from_millis(3));
+        assert!(meta.known_leader);
+        assert_eq!(meta.last_contact, Duration::from_millis(250));
+        assert!(meta.cache_hit);
+        assert_eq!(meta.cache_age, Some(Duration::from_secs(14)));
+        assert_eq!(meta.effective_consistency.as_deref(), Some("stale"));
+        assert!(meta.address_translation);
+
+        let empty = parse_query_meta(&HeaderMap::new(), Duration::from_millis(3)).unwrap();
+        assert!(!empty.known_leader && !empty.cache_hit && !empty.address_translation);
+        assert_eq!(empty.cache_age, None);
+        assert_eq!(empty.effective_consistency, None);
+    }
+
+    #[test]
+    fn parse_response_success_test() {
+        let body = b"[\"alpha\", \"beta\"]";
+
+        let datacenters: Vec<String> =
+            parse_response(StatusCode::OK, body, "/v1/catalog/datacenters").unwrap();
+
+        assert_eq!(datacenters, vec!["alpha", "beta"]);
+    }
+
+    #[test]
+    fn parse_response_empty_body_test() {
+        parse_response::<()>(StatusCode::OK, b"", "/v1/agent/reload").unwrap();
+        let missing: Option<bool> = parse_response(StatusCode::OK, b"\n", "/v1/agent").unwrap();
+
+        assert_eq!(missing, None);
+    }
+
+    #[test]
+    fn parse_response_error_status_test() {
+        let body = b"Permission denied\n";
+
+        let error = parse_response::<bool>(StatusCode::FORBIDDEN, body, "/v1/kv/key").unwrap_err();
+
+        match error.kind() {
+            ErrorKind::ConsulError(status, body, path) => {
+                assert_eq!(*status, 403);
+                assert_eq!(body, "Permission denied");
+                assert_eq!(path, "/v1/kv/key");
+            }
+            kind => panic!("Unexpected error kind {:?}", kind),
+        }
+        assert!(error.kind().is_permission_denied());
+        assert!(!error.kind().is_server_error());
+    }
+
+    #[test]
+    fn check_status_classification_test() {
+        let error =
+            |status: StatusCode| check_status(status, b"", "/v1/status/leader").unwrap_err();
+
+        assert!(check_status(StatusCode::OK, b"", "/v1/status/leader").is_ok());
+        assert!(error(StatusCode::NOT_FOUND).kind().is_not_found());
+        assert!(error(StatusCode::TOO_MANY_REQUESTS)
+            .kind()
+            .is_rate_limited());
+        assert!(error(StatusCode::INTERNAL_SERVER_ERROR)
+            .kind()
+            .is_server_error());
+        assert!(error(StatusCode::SERVICE_UNAVAILABLE)
+            .kind()
+            .is_server_error());
+        assert_eq!(error(StatusCode::BAD_REQUEST).kind().status(), Some(400));
+    }
+
     #[test]
     fn update_params_with_query_options_no_options_test() {
         let config = Config::new().unwrap();
@@ -258,6 +843,7 @@ pub mod request_tests {
             datacenter: None,
             wait_index: None,
             wait_time: None,
+            ..Default::default()
         };
 
         update_params_with_query_options(&config, &mut params, Some(&query_options));
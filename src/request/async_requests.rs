@@ -0,0 +1,270 @@
+use reqwest::Client as AsyncHttpClient;
+use reqwest::Request as AsyncRequest;
+use reqwest::RequestBuilder as AsyncRequestBuilder;
+use reqwest::StatusCode;
+
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+
+use std::collections::HashMap;
+use std::time::Instant;
+
+use tokio::time::{self, Elapsed};
+
+use url::Url;
+
+use crate::errors::{Error, Result, ResultExt};
+use crate::request::unix_socket;
+use crate::request::*;
+use crate::retry;
+use crate::transport::{reqwest_error, HttpRequest, HttpResponse};
+use crate::{Config, QueryMeta, QueryOptions, WriteMeta, WriteOptions};
+
+pub async fn get<R: DeserializeOwned>(
+    path: &str,
+    config: &Config,
+    mut params: HashMap<String, String>,
+    options: Option<&QueryOptions>,
+) -> Result<(R, QueryMeta)> {
+    update_params_with_query_options(config, &mut params, options);
+
+    let url = query_url(path, config, &params, options)?;
+    let start = Instant::now();
+    let request_builder = add_query_options(config.async_http_client.get(url), config, options);
+    let response = send(request_builder, config).await?;
+    let j = parse_response(response.status, &response.body, path)?;
+    Ok((
+        j,
+        parse_query_meta(&response.headers, Instant::now() - start)?,
+    ))
+}
+
+pub async fn get_vec<R: DeserializeOwned>(
+    path: &str,
+    config: &Config,
+    mut params: HashMap<String, String>,
+    options: Option<&QueryOptions>,
+) -> Result<(Vec<R>, QueryMeta)> {
+    update_params_with_query_options(config, &mut params, options);
+
+    let url = query_url(path, config, &params, options)?;
+    let start = Instant::now();
+    let request_builder = add_query_options(config.async_http_client.get(url), config, options);
+    let response = send(request_builder, config).await?;
+    let j = if response.status != StatusCode::NOT_FOUND {
+        parse_response(response.status, &response.body, path)?
+    } else {
+        Vec::new()
+    };
+    Ok((
+        j,
+        parse_query_meta(&response.headers, Instant::now() - start)?,
+    ))
+}
+
+async fn write_with_body<T: Serialize, R: DeserializeOwned, F>(
+    path: &str,
+    body: Option<&T>,
+    config: &Config,
+    mut params: HashMap<String, String>,
+    options: Option<&WriteOptions>,
+    request_builder_from_http_client: F,
+) -> Result<(R, WriteMeta)>
+where
+    F: Fn(&AsyncHttpClient, Url) -> AsyncRequestBuilder,
+{
+    let start = Instant::now();
+
+    update_params_with_write_options(config, &mut params, options);
+
+    let url = request_url(path, config, &params)?;
+    let builder = request_builder_from_http_client(&config.async_http_client, url);
+    let builder = prepare_write_request(builder, body, config);
+
+    send_write_request(builder, config, path, start).await
+}
+
+async fn write_with_raw_body<R: DeserializeOwned, F>(
+    path: &str,
+    body: &[u8],
+    config: &Config,
+    mut params: HashMap<String, String>,
+    options: Option<&WriteOptions>,
+    request_builder_from_http_client: F,
+) -> Result<(R, WriteMeta)>
+where
+    F: Fn(&AsyncHttpClient, Url) -> AsyncRequestBuilder,
+{
+    let start = Instant::now();
+
+    update_params_with_write_options(config, &mut params, options);
+
+    let url = request_url(path, config, &params)?;
+    let builder =
+        request_builder_from_http_client(&config.async_http_client, url).body(body.to_vec());
+    let builder = add_config_options(builder, config);
+
+    send_write_request(builder, config, path, start).await
+}
+
+async fn send_write_request<R: DeserializeOwned>(
+    builder: AsyncRequestBuilder,
+    config: &Config,
+    path: &str,
+    start: Instant,
+) -> Result<(R, WriteMeta)> {
+    let response = send(builder, config).await?;
+    parse_response(response.status, &response.body, path).map(|x| {
+        (
+            x,
+            WriteMeta {
+                request_time: Instant::now() - start,
+            },
+        )
+    })
+}
+
+/// The async counterpart of `request::send`.
+async fn send(builder: AsyncRequestBuilder, config: &Config) -> Result<HttpResponse> {
+    let request = builder
+        .build()
+        .chain_err(|| "Failed to build HTTP request")?;
+    let policy = &config.retry_policy;
+    let retryable = policy.allows(request.method());
+    let targets = retry::targets(config, request.url())?;
+    let mut attempt = 1;
+    loop {
+        let mut last = None;
+        for url in &targets {
+            let result = send_to(&request, url, config).await;
+            if !retry::should_fail_over(retryable, &result) {
+                return result;
+            }
+            last = Some(result);
+        }
+        if !retryable || attempt >= policy.max_attempts {
+            // There is always at least the agent's own address.
+            return last.unwrap();
+        }
+        time::delay_for(policy.backoff(attempt)).await;
+        attempt += 1;
+    }
+}
+
+fn timed_out(_: Elapsed) -> Error {
+    Error::from("HTTP request to consul timed out")
+}
+
+/// Sends one copy of `request` to `url`.
+async fn send_to(request: &AsyncRequest, url: &Url, config: &Config) -> Result<HttpResponse> {
+    if let Some(socket) = config.unix_socket() {
+        let body = request.body().and_then(|b| b.as_bytes()).unwrap_or(&[]);
+        return unix_socket::send_async(
+            socket,
+            request.method(),
+            url,
+            request.headers(),
+            body,
+            request.timeout().copied(),
+            config.http_options(),
+        )
+        .await;
+    }
+
+    if let Some(transport) = config.tls_transport() {
+        let body = request.body().and_then(|b| b.as_bytes()).unwrap_or(&[]);
+        return transport
+            .send_async(HttpRequest {
+                method: request.method().clone(),
+                url: url.clone(),
+                headers: request.headers().clone(),
+                body: body.to_vec(),
+                timeout: request.timeout().copied(),
+            })
+            .await;
+    }
+
+    let mut request = request
+        .try_clone()
+        .ok_or_else(|| Error::from("Failed to copy HTTP request"))?;
+    *request.url_mut() = url.clone();
+    // A blocking query waits for the start of its response without the read
+    // timeout, as it does over a socket.
+    let blocking = request.timeout().is_some();
+    let read_timeout = config.http_options().read_timeout;
+    let response = config.async_http_client.execute(request);
+    let mut response = match read_timeout.filter(|_| !blocking) {
+        Some(timeout) => time::timeout(timeout, response).await.map_err(timed_out)?,
+        None => response.await,
+    }
+    .map_err(|err| reqwest_error(err, url))?;
+    let status = response.status();
+    let headers = response.headers().clone();
+    let mut body = Vec::new();
+    loop {
+        let chunk = match read_timeout {
+            Some(timeout) => time::timeout(timeout, response.chunk())
+                .await
+                .map_err(timed_out)?,
+            None => response.chunk().await,
+        }
+        .chain_err(|| "Failed to read response body")?;
+        match chunk {
+            Some(chunk) => body.extend_from_slice(&chunk),
+            None => break,
+        }
+    }
+    Ok(HttpResponse {
+        status,
+        headers,
+        body,
+    })
+}
+
+pub async fn delete<R: DeserializeOwned>(
+    path: &str,
+    config: &Config,
+    params: HashMap<String, String>,
+    options: Option<&WriteOptions>,
+) -> Result<(R, WriteMeta)> {
+    let req = |http_client: &AsyncHttpClient, url: Url| -> AsyncRequestBuilder {
+        http_client.delete(url)
+    };
+    write_with_body(path, None as Option<&()>, config, params, options, req).await
+}
+
+pub async fn post<T: Serialize, R: DeserializeOwned>(
+    path: &str,
+    body: Option<&T>,
+    config: &Config,
+    params: HashMap<String, String>,
+    options: Option<&WriteOptions>,
+) -> Result<(R, WriteMeta)> {
+    let req =
+        |http_client: &AsyncHttpClient, url: Url| -> AsyncRequestBuilder { http_client.post(url) };
+    write_with_body(path, body, config, params, options, req).await
+}
+
+pub async fn put<T: Serialize, R: DeserializeOwned>(
+    path: &str,
+    body: Option<&T>,
+    config: &Config,
+    params: HashMap<String, String>,
+    options: Option<&WriteOptions>,
+) -> Result<(R, WriteMeta)> {
+    let req =
+        |http_client: &AsyncHttpClient, url: Url| -> AsyncRequestBuilder { http_client.put(url) };
+    write_with_body(path, body, config, params, options, req).await
+}
+
+pub async fn put_raw<R: DeserializeOwned>(
+    path: &str,
+    body: &[u8],
+    config: &Config,
+    params: HashMap<String, String>,
+    options: Option<&WriteOptions>,
+) -> Result<(R, WriteMeta)> {
+    let req =
+        |http_client: &AsyncHttpClient, url: Url| -> AsyncRequestBuilder { http_client.put(url) };
+    write_with_raw_body(path, body, config, params, options, req).await
+}
@@ -1,16 +1,11 @@
-use reqwest::header::HeaderValue;
 use reqwest::StatusCode;
 
 use serde::de::DeserializeOwned;
 
 use std::collections::HashMap;
-use std::str;
-use std::str::FromStr;
 use std::time::Instant;
 
-use url::Url;
-
-use crate::errors::{Result, ResultExt};
+use crate::errors::Result;
 use crate::request::*;
 use crate::{Config, QueryMeta, QueryOptions};
 
@@ -22,42 +17,15 @@ pub fn get<R: DeserializeOwned>(
 ) -> Result<(R, QueryMeta)> {
     update_params_with_query_options(config, &mut params, options);
 
-    let url_str = format!("{}{}", config.address, path);
-    let url =
-        Url::parse_with_params(&url_str, params.iter()).chain_err(|| "Failed to parse URL")?;
+    let url = query_url(path, config, &params, options)?;
     let start = Instant::now();
-    let request_builder = add_config_options(config.http_client.get(url), &config);
-    let response = request_builder.send();
-    response
-        .chain_err(|| "HTTP request to consul failed")
-        .and_then(|r| {
-            let x: Option<Result<u64>> =
-                r.headers()
-                    .get("X-Consul-Index")
-                    .map(|bytes: &HeaderValue| -> Result<u64> {
-                        bytes
-                            .to_str()
-                            .chain_err(|| "Failed to parse valid UT8 for last index")
-                            .and_then(|s: &str| -> Result<u64> {
-                                u64::from_str(s)
-                                    .chain_err(|| "Failed to parse valid number for last index")
-                            })
-                    });
-            let j = r.json().chain_err(|| "Failed to parse JSON response")?;
-            match x {
-                Some(r) => Ok((j, Some(r?))),
-                None => Ok((j, None)),
-            }
-        })
-        .map(|x: (R, Option<u64>)| {
-            (
-                x.0,
-                QueryMeta {
-                    last_index: x.1,
-                    request_time: Instant::now() - start,
-                },
-            )
-        })
+    let request_builder = add_query_options(config.http_client.get(url), config, options);
+    let response = send(request_builder, config)?;
+    let j = parse_response(response.status, &response.body, path)?;
+    Ok((
+        j,
+        parse_query_meta(&response.headers, Instant::now() - start)?,
+    ))
 }
 
 pub fn get_vec<R: DeserializeOwned>(
@@ -68,44 +36,17 @@ pub fn get_vec<R: DeserializeOwned>(
 ) -> Result<(Vec<R>, QueryMeta)> {
     update_params_with_query_options(config, &mut params, options);
 
-    let url_str = format!("{}{}", config.address, path);
-    let url =
-        Url::parse_with_params(&url_str, params.iter()).chain_err(|| "Failed to parse URL")?;
+    let url = query_url(path, config, &params, options)?;
     let start = Instant::now();
-    let request_builder = add_config_options(config.http_client.get(url), &config);
-    let response = request_builder.send();
-    response
-        .chain_err(|| "HTTP request to consul failed")
-        .and_then(|r| {
-            let x: Option<Result<u64>> = r
-                .headers()
-                .get("X-Consul-Index")
-                .map(|value: &HeaderValue| value.as_bytes())
-                .map(|bytes| {
-                    str::from_utf8(bytes)
-                        .chain_err(|| "Failed to parse valid UT8 for last index")
-                        .and_then(|s| {
-                            u64::from_str(s)
-                                .chain_err(|| "Failed to parse valid number for last index")
-                        })
-                });
-            let j = if r.status() != StatusCode::NOT_FOUND {
-                r.json().chain_err(|| "Failed to parse JSON response")?
-            } else {
-                Vec::new()
-            };
-            match x {
-                Some(r) => Ok((j, Some(r?))),
-                None => Ok((j, None)),
-            }
-        })
-        .map(|x: (Vec<R>, Option<u64>)| {
-            (
-                x.0,
-                QueryMeta {
-                    last_index: x.1,
-                    request_time: Instant::now() - start,
-                },
-            )
-        })
+    let request_builder = add_query_options(config.http_client.get(url), config, options);
+    let response = send(request_builder, config)?;
+    let j = if response.status != StatusCode::NOT_FOUND {
+        parse_response(response.status, &response.body, path)?
+    } else {
+        Vec::new()
+    };
+    Ok((
+        j,
+        parse_query_meta(&response.headers, Instant::now() - start)?,
+    ))
 }
@@ -1,6 +1,6 @@
 use crate::request::*;
 
-pub fn _post<T: Serialize, R: DeserializeOwned>(
+pub fn post<T: Serialize, R: DeserializeOwned>(
     path: &str,
     body: Option<&T>,
     config: &Config,
@@ -18,3 +18,22 @@ pub fn put<T: Serialize, R: DeserializeOwned>(
         request_builder_from_http_client,
     )
 }
+
+pub fn put_raw<R: DeserializeOwned>(
+    path: &str,
+    body: &[u8],
+    config: &Config,
+    params: HashMap<String, String>,
+    options: Option<&WriteOptions>,
+) -> Result<(R, WriteMeta)> {
+    let request_builder_from_http_client =
+        |http_client: &HttpClient, url: Url| -> RequestBuilder { http_client.put(url) };
+    write_with_raw_body(
+        path,
+        body,
+        config,
+        params,
+        options,
+        request_builder_from_http_client,
+    )
+}
@@ -0,0 +1,131 @@
+//! HTTPS to agents whose certificate is checked against a configured server
+//! name rather than the host in their address, e.g. `server.dc1.consul` for an
+//! agent reached by IP.
+//!
+//! reqwest always checks the certificate against the host it connects to, so
+//! these connections are made here and the TLS handshake is given the server
+//! name. The HTTP spoken over them is the unix socket client's.
+
+use std::io::{self, Write};
+use std::net::TcpStream;
+use std::time::Duration;
+
+use native_tls::TlsConnector;
+use url::Url;
+
+use crate::config::HttpOptions;
+use crate::errors::{Error, ErrorKind, Result, ResultExt};
+#[cfg(feature = "async")]
+use crate::request::unix_socket::read_response_async;
+use crate::request::unix_socket::{decode_response, encode_request, read_response, Deadline};
+use crate::transport::{HttpRequest, HttpResponse};
+
+pub fn send(
+    connector: &TlsConnector,
+    server_name: &str,
+    request: &HttpRequest,
+    options: &HttpOptions,
+) -> Result<HttpResponse> {
+    let deadline = Deadline::new(request.timeout, options);
+    let stream = connect(&request.url, deadline.connect_remaining()?)?;
+    let remaining = deadline.remaining()?;
+    stream
+        .set_write_timeout(Some(remaining))
+        .and_then(|_| stream.set_read_timeout(Some(remaining)))
+        .chain_err(|| "HTTP request to consul failed")?;
+    let mut stream = connector
+        .connect(server_name, stream)
+        .chain_err(|| format!("TLS handshake with {} failed", server_name))?;
+    stream
+        .write_all(&encode(request, options)?)
+        .chain_err(|| "HTTP request to consul failed")?;
+    let response = read_response(&mut stream, &deadline, |stream, timeout| {
+        stream.get_ref().set_read_timeout(Some(timeout))
+    })?;
+    decode_response(&response)
+}
+
+#[cfg(feature = "async")]
+pub async fn send_async(
+    connector: &TlsConnector,
+    server_name: &str,
+    request: &HttpRequest,
+    options: &HttpOptions,
+) -> Result<HttpResponse> {
+    use tokio::io::AsyncWriteExt;
+    use tokio::time;
+
+    let timed_out = |_| Error::from("HTTP request to consul timed out");
+    let deadline = Deadline::new(request.timeout, options);
+    let connect_failed = || ErrorKind::ConnectFailed(request.url.to_string());
+    let stream = time::timeout(
+        deadline.connect_remaining()?,
+        tokio::net::TcpStream::connect(host_and_port(&request.url)?),
+    )
+    .await
+    .map_err(|err| Error::with_chain(err, connect_failed()))?
+    .chain_err(connect_failed)?;
+    let connector = tokio_tls::TlsConnector::from(connector.clone());
+    let mut stream = time::timeout(
+        deadline.remaining()?,
+        connector.connect(server_name, stream),
+    )
+    .await
+    .map_err(timed_out)?
+    .chain_err(|| format!("TLS handshake with {} failed", server_name))?;
+    time::timeout(
+        deadline.remaining()?,
+        stream.write_all(&encode(request, options)?),
+    )
+    .await
+    .map_err(timed_out)?
+    .chain_err(|| "HTTP request to consul failed")?;
+    let response = read_response_async(&mut stream, &deadline).await?;
+    decode_response(&response)
+}
+
+fn connect(url: &Url, timeout: Duration) -> Result<TcpStream> {
+    let connect = || -> io::Result<TcpStream> {
+        let mut last = None;
+        for addr in url.socket_addrs(|| None)? {
+            match TcpStream::connect_timeout(&addr, timeout) {
+                Ok(stream) => return Ok(stream),
+                Err(err) => last = Some(err),
+            }
+        }
+        Err(last.unwrap_or_else(|| io::Error::new(io::ErrorKind::NotFound, "no address")))
+    };
+    connect().chain_err(|| ErrorKind::ConnectFailed(url.to_string()))
+}
+
+#[cfg(feature = "async")]
+fn host_and_port(url: &Url) -> Result<(String, u16)> {
+    use url::Host;
+
+    let host = match url.host() {
+        // Without the brackets it takes in a URL.
+        Some(Host::Ipv6(ip)) => ip.to_string(),
+        Some(host) => host.to_string(),
+        None => return Err(Error::from(format!("No host in {}", url))),
+    };
+    let port = url
+        .port_or_known_default()
+        .ok_or_else(|| Error::from(format!("No port in {}", url)))?;
+    Ok((host, port))
+}
+
+fn encode(request: &HttpRequest, options: &HttpOptions) -> Result<Vec<u8>> {
+    let host = match (request.url.host(), request.url.port()) {
+        (Some(host), Some(port)) => format!("{}:{}", host, port),
+        (Some(host), None) => host.to_string(),
+        (None, _) => return Err(Error::from(format!("No host in {}", request.url))),
+    };
+    Ok(encode_request(
+        &host,
+        &request.method,
+        &request.url,
+        &request.headers,
+        &request.body,
+        options,
+    ))
+}
@@ -0,0 +1,505 @@
+//! A minimal HTTP/1.1 client for agents that only listen on a unix socket.
+//!
+//! Each request opens a new connection with `Connection: close`, so a response
+//! ends at EOF and no connection state has to be kept. `tls_socket` speaks the
+//! same HTTP over its own TLS connections.
+
+#[cfg(unix)]
+use std::io::Write;
+use std::io::{self, Read};
+#[cfg(unix)]
+use std::os::unix::net::UnixStream;
+use std::path::Path;
+use std::time::{Duration, Instant};
+
+use reqwest::header::{
+    HeaderMap, HeaderName, HeaderValue, CONTENT_LENGTH, TRANSFER_ENCODING, USER_AGENT,
+};
+use reqwest::{Method, StatusCode};
+#[cfg(unix)]
+use socket2::{Domain, SockAddr, Socket, Type};
+use url::Url;
+
+use crate::config::HttpOptions;
+use crate::errors::{Error, ErrorKind, Result, ResultExt};
+use crate::transport::HttpResponse;
+
+// How long a request may take when neither it nor the config sets a timeout,
+// the same as reqwest's blocking client.
+pub(crate) const DEFAULT_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// When a request has to be done by. Connecting, writing the request and
+/// reading the response all count towards it, and connecting and every read
+/// may take no longer than the configured connect and read timeouts either.
+pub(super) struct Deadline {
+    at: Instant,
+    connect_timeout: Option<Duration>,
+    read_timeout: Option<Duration>,
+    blocking: bool,
+}
+
+impl Deadline {
+    /// `timeout` is the request's own, set for blocking queries; otherwise the
+    /// configured timeout applies.
+    pub(super) fn new(timeout: Option<Duration>, options: &HttpOptions) -> Deadline {
+        let timeout_is_own = timeout.is_some();
+        let timeout = timeout
+            .or(options.request_timeout)
+            .unwrap_or(DEFAULT_TIMEOUT);
+        Deadline {
+            at: Instant::now() + timeout,
+            connect_timeout: options.connect_timeout,
+            read_timeout: options.read_timeout,
+            blocking: timeout_is_own,
+        }
+    }
+
+    pub(super) fn remaining(&self) -> Result<Duration> {
+        self.at
+            .checked_duration_since(Instant::now())
+            .filter(|left| *left > Duration::from_secs(0))
+            .ok_or_else(|| Error::from("HTTP request to consul timed out"))
+    }
+
+    pub(super) fn connect_remaining(&self) -> Result<Duration> {
+        let left = self.remaining()?;
+        Ok(self
+            .connect_timeout
+            .map_or(left, |connect| connect.min(left)))
+    }
+
+    /// How long the next read may wait. A blocking query waits for the start
+    /// of its response without the read timeout; `started` is whether any of
+    /// it has arrived.
+    pub(super) fn read_remaining(&self, started: bool) -> Result<Duration> {
+        let left = self.remaining()?;
+        Ok(match self.read_timeout {
+            Some(read) if started || !self.blocking => read.min(left),
+            _ => left,
+        })
+    }
+}
+
+/// Reads the response until the agent closes the connection. `set_timeout`
+/// bounds the next read of `stream`.
+pub(super) fn read_response<S, F>(
+    stream: &mut S,
+    deadline: &Deadline,
+    set_timeout: F,
+) -> Result<Vec<u8>>
+where
+    S: Read,
+    F: Fn(&S, Duration) -> io::Result<()>,
+{
+    let mut response = Vec::new();
+    let mut buf = [0; 8192];
+    loop {
+        // A socket's read timeout only bounds a single read, so it is set
+        // before each one.
+        set_timeout(stream, deadline.read_remaining(!response.is_empty())?)
+            .chain_err(|| "Failed to read response body")?;
+        let n = stream
+            .read(&mut buf)
+            .chain_err(|| "Failed to read response body")?;
+        if n == 0 {
+            return Ok(response);
+        }
+        response.extend_from_slice(&buf[..n]);
+    }
+}
+
+#[cfg(feature = "async")]
+pub(super) async fn read_response_async<S>(stream: &mut S, deadline: &Deadline) -> Result<Vec<u8>>
+where
+    S: tokio::io::AsyncRead + Unpin,
+{
+    use tokio::io::AsyncReadExt;
+
+    let mut response = Vec::new();
+    let mut buf = [0; 8192];
+    loop {
+        let n = tokio::time::timeout(
+            deadline.read_remaining(!response.is_empty())?,
+            stream.read(&mut buf),
+        )
+        .await
+        .map_err(|_| Error::from("HTTP request to consul timed out"))?
+        .chain_err(|| "Failed to read response body")?;
+        if n == 0 {
+            return Ok(response);
+        }
+        response.extend_from_slice(&buf[..n]);
+    }
+}
+
+#[cfg(unix)]
+pub fn send(
+    socket: &Path,
+    method: &Method,
+    url: &Url,
+    headers: &HeaderMap,
+    body: &[u8],
+    timeout: Option<Duration>,
+    options: &HttpOptions,
+) -> Result<HttpResponse> {
+    let deadline = Deadline::new(timeout, options);
+    let mut stream = connect(socket, deadline.connect_remaining()?)?;
+    stream
+        .set_write_timeout(Some(deadline.remaining()?))
+        .chain_err(|| "HTTP request to consul failed")?;
+    stream
+        .write_all(&encode_request(
+            "localhost",
+            method,
+            url,
+            headers,
+            body,
+            options,
+        ))
+        .chain_err(|| "HTTP request to consul failed")?;
+    let response = read_response(&mut stream, &deadline, |stream, timeout| {
+        stream.set_read_timeout(Some(timeout))
+    })?;
+    decode_response(&response)
+}
+
+#[cfg(unix)]
+fn connect(socket: &Path, timeout: Duration) -> Result<UnixStream> {
+    let connect = || -> std::io::Result<UnixStream> {
+        let stream = Socket::new(Domain::unix(), Type::stream(), None)?;
+        stream.connect_timeout(&SockAddr::unix(socket)?, timeout)?;
+        Ok(stream.into_unix_stream())
+    };
+    connect().chain_err(|| ErrorKind::ConnectFailed(socket.display().to_string()))
+}
+
+#[cfg(not(unix))]
+pub fn send(
+    _: &Path,
+    _: &Method,
+    _: &Url,
+    _: &HeaderMap,
+    _: &[u8],
+    _: Option<Duration>,
+    _: &HttpOptions,
+) -> Result<HttpResponse> {
+    Err(Error::from(
+        "Unix sockets are not supported on this platform",
+    ))
+}
+
+#[cfg(all(unix, feature = "async"))]
+pub async fn send_async(
+    socket: &Path,
+    method: &Method,
+    url: &Url,
+    headers: &HeaderMap,
+    body: &[u8],
+    timeout: Option<Duration>,
+    options: &HttpOptions,
+) -> Result<HttpResponse> {
+    use tokio::io::AsyncWriteExt;
+    use tokio::time;
+
+    let timed_out = |_| Error::from("HTTP request to consul timed out");
+    let deadline = Deadline::new(timeout, options);
+    let connect_failed = || ErrorKind::ConnectFailed(socket.display().to_string());
+    let mut stream = time::timeout(
+        deadline.connect_remaining()?,
+        tokio::net::UnixStream::connect(socket),
+    )
+    .await
+    .map_err(|err| Error::with_chain(err, connect_failed()))?
+    .chain_err(connect_failed)?;
+    time::timeout(
+        deadline.remaining()?,
+        stream.write_all(&encode_request(
+            "localhost",
+            method,
+            url,
+            headers,
+            body,
+            options,
+        )),
+    )
+    .await
+    .map_err(timed_out)?
+    .chain_err(|| "HTTP request to consul failed")?;
+    let response = read_response_async(&mut stream, &deadline).await?;
+    decode_response(&response)
+}
+
+#[cfg(all(not(unix), feature = "async"))]
+pub async fn send_async(
+    socket: &Path,
+    method: &Method,
+    url: &Url,
+    headers: &HeaderMap,
+    body: &[u8],
+    timeout: Option<Duration>,
+    options: &HttpOptions,
+) -> Result<HttpResponse> {
+    send(socket, method, url, headers, body, timeout, options)
+}
+
+/// The request as sent over the socket, with the configured `User-Agent`
+/// unless the request has its own.
+pub(super) fn encode_request(
+    host: &str,
+    method: &Method,
+    url: &Url,
+    headers: &HeaderMap,
+    body: &[u8],
+    options: &HttpOptions,
+) -> Vec<u8> {
+    let target = match url.query() {
+        Some(query) if !query.is_empty() => format!("{}?{}", url.path(), query),
+        _ => url.path().to_owned(),
+    };
+    let mut request = format!(
+        "{} {} HTTP/1.1\r\nHost: {}\r\nConnection: close\r\n",
+        method, target, host
+    )
+    .into_bytes();
+    if let Some(ref user_agent) = options.user_agent {
+        if !headers.contains_key(USER_AGENT) {
+            request.extend_from_slice(format!("User-Agent: {}\r\n", user_agent).as_bytes());
+        }
+    }
+    for (name, value) in headers {
+        request.extend_from_slice(name.as_str().as_bytes());
+        request.extend_from_slice(b": ");
+        request.extend_from_slice(value.as_bytes());
+        request.extend_from_slice(b"\r\n");
+    }
+    if !body.is_empty() || (method != Method::GET && method != Method::HEAD) {
+        request.extend_from_slice(format!("Content-Length: {}\r\n", body.len()).as_bytes());
+    }
+    request.extend_from_slice(b"\r\n");
+    request.extend_from_slice(body);
+    request
+}
+
+pub(super) fn decode_response(response: &[u8]) -> Result<HttpResponse> {
+    let malformed = || Error::from("Malformed HTTP response from consul");
+    let header_end = find(response, b"\r\n\r\n").ok_or_else(malformed)?;
+    let head = std::str::from_utf8(&response[..header_end]).map_err(|_| malformed())?;
+    let mut lines = head.split("\r\n");
+
+    let status = lines
+        .next()
+        .and_then(|line| line.split(' ').nth(1))
+        .and_then(|code| StatusCode::from_bytes(code.as_bytes()).ok())
+        .ok_or_else(malformed)?;
+
+    let mut headers = HeaderMap::new();
+    for line in lines {
+        let colon = line.find(':').ok_or_else(malformed)?;
+        let name =
+            HeaderName::from_bytes(line[..colon].trim().as_bytes()).map_err(|_| malformed())?;
+        let value = HeaderValue::from_str(line[colon + 1..].trim()).map_err(|_| malformed())?;
+        headers.append(name, value);
+    }
+
+    let rest = &response[header_end + 4..];
+    let chunked = headers
+        .get(TRANSFER_ENCODING)
+        .and_then(|v| v.to_str().ok())
+        .is_some_and(|v| v.eq_ignore_ascii_case("chunked"));
+    let body = if chunked {
+        decode_chunked(rest)?
+    } else if let Some(length) = headers.get(CONTENT_LENGTH) {
+        let length: usize = length
+            .to_str()
+            .ok()
+            .and_then(|l| l.parse().ok())
+            .ok_or_else(malformed)?;
+        rest.get(..length).ok_or_else(malformed)?.to_vec()
+    } else {
+        rest.to_vec()
+    };
+
+    Ok(HttpResponse {
+        status,
+        headers,
+        body,
+    })
+}
+
+fn decode_chunked(mut rest: &[u8]) -> Result<Vec<u8>> {
+    let malformed = || Error::from("Malformed chunked response from consul");
+    let mut body = Vec::new();
+    loop {
+        let line_end = find(rest, b"\r\n").ok_or_else(malformed)?;
+        let size_line = std::str::from_utf8(&rest[..line_end]).map_err(|_| malformed())?;
+        // Chunk extensions follow a `;` and carry nothing we need.
+        let size_hex = size_line.split(';').next().unwrap_or("").trim();
+        let size = usize::from_str_radix(size_hex, 16).map_err(|_| malformed())?;
+        rest = &rest[line_end + 2..];
+        if size == 0 {
+            return Ok(body);
+        }
+        body.extend_from_slice(rest.get(..size).ok_or_else(malformed)?);
+        rest = rest.get(size + 2..).ok_or_else(malformed)?;
+    }
+}
+
+fn find(haystack: &[u8], needle: &[u8]) -> Option<usize> {
+    haystack
+        .windows(needle.len())
+        .position(|window| window == needle)
+}
+
+#[cfg(test)]
+mod unix_socket_tests {
+    use super::*;
+
+    #[test]
+    fn encode_request_test() {
+        let url = Url::parse("http://localhost/v1/kv/foo?dc=dc1&index=5").unwrap();
+        let mut headers = HeaderMap::new();
+        headers.insert("X-Consul-Token", HeaderValue::from_static("secret"));
+
+        let request = encode_request(
+            "localhost",
+            &Method::PUT,
+            &url,
+            &headers,
+            b"bar",
+            &HttpOptions::default(),
+        );
+
+        assert_eq!(
+            String::from_utf8(request).unwrap(),
+            "PUT /v1/kv/foo?dc=dc1&index=5 HTTP/1.1\r\nHost: localhost\r\nConnection: close\r\n\
+             x-consul-token: secret\r\nContent-Length: 3\r\n\r\nbar"
+        );
+    }
+
+    #[test]
+    fn encode_request_user_agent_test() {
+        let url = Url::parse("http://localhost/v1/status/leader").unwrap();
+        let options = HttpOptions {
+            user_agent: Some(String::from("billing/1.4")),
+            ..Default::default()
+        };
+
+        let request = encode_request(
+            "localhost",
+            &Method::GET,
+            &url,
+            &HeaderMap::new(),
+            b"",
+            &options,
+        );
+        assert_eq!(
+            String::from_utf8(request).unwrap(),
+            "GET /v1/status/leader HTTP/1.1\r\nHost: localhost\r\nConnection: close\r\n\
+             User-Agent: billing/1.4\r\n\r\n"
+        );
+
+        // A User-Agent set on the request itself wins.
+        let mut headers = HeaderMap::new();
+        headers.insert(USER_AGENT, HeaderValue::from_static("custom"));
+        let request = encode_request("localhost", &Method::GET, &url, &headers, b"", &options);
+        let request = String::from_utf8(request).unwrap();
+        assert!(request.contains("user-agent: custom\r\n"));
+        assert!(!request.contains("billing"));
+    }
+
+    #[test]
+    fn deadline_test() {
+        let options = HttpOptions {
+            connect_timeout: Some(Duration::from_secs(1)),
+            request_timeout: Some(Duration::from_secs(10)),
+            ..Default::default()
+        };
+
+        let deadline = Deadline::new(None, &options);
+        assert!(deadline.remaining().unwrap() > Duration::from_secs(9));
+        assert_eq!(
+            deadline.connect_remaining().unwrap(),
+            Duration::from_secs(1)
+        );
+
+        // Blocking queries bring their own, longer timeout.
+        let deadline = Deadline::new(Some(Duration::from_secs(60)), &options);
+        assert!(deadline.remaining().unwrap() > Duration::from_secs(59));
+
+        let deadline = Deadline::new(None, &HttpOptions::default());
+        assert!(deadline.remaining().unwrap() > Duration::from_secs(29));
+        assert!(deadline.connect_remaining().unwrap() > Duration::from_secs(29));
+
+        let expired = Deadline {
+            at: Instant::now(),
+            connect_timeout: None,
+            read_timeout: None,
+            blocking: false,
+        };
+        assert!(expired.remaining().is_err());
+    }
+
+    #[test]
+    fn deadline_read_timeout_test() {
+        let options = HttpOptions {
+            read_timeout: Some(Duration::from_secs(2)),
+            ..Default::default()
+        };
+
+        let deadline = Deadline::new(None, &options);
+        assert_eq!(
+            deadline.read_remaining(false).unwrap(),
+            Duration::from_secs(2)
+        );
+        assert_eq!(
+            deadline.read_remaining(true).unwrap(),
+            Duration::from_secs(2)
+        );
+
+        // A blocking query waits for its answer as long as its deadline allows.
+        let deadline = Deadline::new(Some(Duration::from_secs(60)), &options);
+        assert!(deadline.read_remaining(false).unwrap() > Duration::from_secs(59));
+        assert_eq!(
+            deadline.read_remaining(true).unwrap(),
+            Duration::from_secs(2)
+        );
+
+        let deadline = Deadline::new(None, &HttpOptions::default());
+        assert!(deadline.read_remaining(false).unwrap() > Duration::from_secs(29));
+    }
+
+    #[test]
+    fn decode_response_with_content_length_test() {
+        let response = decode_response(
+            b"HTTP/1.1 200 OK\r\nX-Consul-Index: 42\r\nContent-Length: 4\r\n\r\ntrue",
+        )
+        .unwrap();
+
+        assert_eq!(response.status, StatusCode::OK);
+        assert_eq!(response.headers["x-consul-index"], "42");
+        assert_eq!(response.body, b"true");
+    }
+
+    #[test]
+    fn decode_chunked_response_test() {
+        let response = decode_response(
+            b"HTTP/1.1 404 Not Found\r\nTransfer-Encoding: chunked\r\n\r\n\
+              4\r\nnot \r\n5;ext=1\r\nfound\r\n0\r\n\r\n",
+        )
+        .unwrap();
+
+        assert_eq!(response.status, StatusCode::NOT_FOUND);
+        assert_eq!(response.body, b"not found");
+    }
+
+    #[test]
+    fn decode_malformed_response_test() {
+        assert!(decode_response(b"HTTP/1.1 200 OK\r\n").is_err());
+        assert!(decode_response(b"garbage\r\n\r\n").is_err());
+        assert!(decode_response(b"HTTP/1.1 200 OK\r\nContent-Length: 9\r\n\r\nshort").is_err());
+        assert!(
+            decode_response(b"HTTP/1.1 200 OK\r\nTransfer-Encoding: chunked\r\n\r\nzz\r\n")
+                .is_err()
+        );
+    }
+}
@@ -0,0 +1,553 @@
+//! Client side load balancing over the healthy instances of a service.
+
+use std::collections::HashMap;
+use std::net::{IpAddr, SocketAddr, ToSocketAddrs};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc;
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+
+use rand::Rng;
+
+use crate::catalog::Weights;
+use crate::coordinate::{Coord, Coordinate};
+use crate::errors::{Error, Result, ResultExt};
+use crate::health::{Health, HealthStatus, ServiceEntry};
+use crate::watch::{Watch, DEFAULT_MIN_BACKOFF};
+use crate::{Client, QueryOptions};
+
+/// The longest the resolver thread's blocking queries wait, and its retries
+/// after a failure, so that it stops soon after the resolver is dropped.
+const MAX_WAIT: Duration = Duration::from_secs(5);
+
+/// How `ServiceResolver::resolve` picks an instance.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Balance {
+    /// Every instance in turn.
+    RoundRobin,
+    /// Any instance, with the same probability.
+    Random,
+    /// Any instance, with a probability proportional to its weight.
+    Weighted,
+    /// The instance with the lowest estimated round trip time from
+    /// `ResolverOptions::near`, going by the nodes' network coordinates.
+    LeastRtt,
+}
+
+#[derive(Clone, Debug)]
+pub struct ResolverOptions {
+    pub balance: Balance,
+    /// Only instances with this tag.
+    pub tag: Option<String>,
+    /// The node round trip times are estimated from. Required for
+    /// `Balance::LeastRtt` and ignored otherwise.
+    pub near: Option<String>,
+    /// The options of the blocking queries, e.g. their datacenter and wait.
+    /// The wait is at most 5 seconds.
+    pub query_options: QueryOptions,
+}
+
+impl Default for ResolverOptions {
+    fn default() -> Self {
+        ResolverOptions {
+            balance: Balance::RoundRobin,
+            tag: None,
+            near: None,
+            query_options: QueryOptions::default(),
+        }
+    }
+}
+
+/// An instance `ServiceResolver` may pick.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Instance {
+    pub id: String,
+    pub node: String,
+    pub addr: SocketAddr,
+    pub status: HealthStatus,
+    /// The service's `Passing` or `Warning` weight, going by `status`.
+    pub weight: u32,
+    /// The estimated round trip time to `node`, for `Balance::LeastRtt`.
+    pub rtt: Option<Duration>,
+}
+
+impl Instance {
+    /// The instance `entry` describes, if it can be picked: its checks are
+    /// passing or warning, its weight for that status is not 0 and its address
+    /// is an IP address. The service's address is used when it has one and the
+    /// node's otherwise.
+    ///
+    /// Consul also allows host names, which are left out here because looking
+    /// them up blocks. `ServiceResolver` looks them up on its own thread and
+    /// the `discover` module without blocking the executor.
+    pub fn from_entry(entry: &ServiceEntry) -> Option<Instance> {
+        let ip = entry_host(entry).parse::<IpAddr>().ok()?;
+        Instance::at(entry, SocketAddr::new(ip, entry.Service.Port))
+    }
+
+    /// The instance `entry` describes, reached at `addr`, if it can be picked.
+    pub(crate) fn at(entry: &ServiceEntry, addr: SocketAddr) -> Option<Instance> {
+        let (status, weight) = pick_weight(entry)?;
+        Some(Instance {
+            id: entry.Service.ID.clone(),
+            node: entry.Node.Node.clone(),
+            addr,
+            status,
+            weight,
+            rtt: None,
+        })
+    }
+}
+
+/// The status and weight of the instance `entry` describes, if it can be
+/// picked: its checks are passing or warning and its weight for that status
+/// is not 0.
+pub(crate) fn pick_weight(entry: &ServiceEntry) -> Option<(HealthStatus, u32)> {
+    let status = entry.aggregated_status(false);
+    let weights = entry.Service.Weights.clone().unwrap_or(Weights {
+        Passing: 1,
+        Warning: 1,
+    });
+    let weight = match status {
+        HealthStatus::Passing => weights.Passing,
+        HealthStatus::Warning => weights.Warning,
+        _ => return None,
+    };
+    if weight == 0 {
+        return None;
+    }
+    Some((status, weight))
+}
+
+/// The address of the instance `entry` describes: the service's when it has
+/// one and the node's otherwise. Either may be an IP address or a host name.
+pub(crate) fn entry_host(entry: &ServiceEntry) -> &str {
+    if entry.Service.Address.is_empty() {
+        &entry.Node.Address
+    } else {
+        &entry.Service.Address
+    }
+}
+
+/// `Instance::from_entry`, with host names looked up. This blocks, so it is
+/// only done on the resolver's thread.
+fn lookup_instance(entry: &ServiceEntry) -> Option<Instance> {
+    pick_weight(entry)?;
+    let addr = (entry_host(entry), entry.Service.Port)
+        .to_socket_addrs()
+        .ok()?
+        .next()?;
+    Instance::at(entry, addr)
+}
+
+/// Picks one of `instances`, which must not be empty. `turn` counts the
+/// picks so far, for `Balance::RoundRobin`.
+fn pick(balance: Balance, instances: &[Instance], turn: usize) -> &Instance {
+    match balance {
+        Balance::RoundRobin => &instances[turn % instances.len()],
+        Balance::Random => &instances[rand::thread_rng().gen_range(0, instances.len())],
+        Balance::Weighted => {
+            let total: u64 = instances.iter().map(|i| u64::from(i.weight)).sum();
+            let mut point = rand::thread_rng().gen_range(0, total);
+            for instance in instances {
+                if point < u64::from(instance.weight) {
+                    return instance;
+                }
+                point -= u64::from(instance.weight);
+            }
+            unreachable!("the point is below the total weight")
+        }
+        // Instances on nodes without a coordinate come last.
+        Balance::LeastRtt => instances
+            .iter()
+            .min_by_key(|i| i.rtt.unwrap_or(Duration::from_secs(u64::MAX)))
+            .unwrap(),
+    }
+}
+
+#[derive(Default)]
+struct Instances {
+    instances: Vec<Instance>,
+    turn: usize,
+}
+
+/// Keeps the healthy instances of a service up to date with a blocking query
+/// on `/v1/health/service` and picks one of them for each request.
+///
+/// Instances whose checks are critical or in maintenance are left out, as are
+/// those whose weight for their status is 0. When the query fails, the last
+/// instances are kept until it succeeds again.
+///
+/// ```no_run
+/// use consul::resolver::{Balance, ResolverOptions, ServiceResolver};
+/// use consul::{Client, Config};
+///
+/// let client = Client::new(Config::new().unwrap());
+/// let options = ResolverOptions {
+///     balance: Balance::Weighted,
+///     ..Default::default()
+/// };
+/// let resolver = ServiceResolver::new(&client, "billing", options).unwrap();
+/// let addr = resolver.resolve();
+/// ```
+pub struct ServiceResolver {
+    balance: Balance,
+    instances: Arc<Mutex<Instances>>,
+    stop: Arc<AtomicBool>,
+}
+
+impl ServiceResolver {
+    /// Starts watching `service` and returns once its instances are known, or
+    /// with the error of the first query.
+    ///
+    /// The instances are updated from a background thread. It stops within
+    /// about 5 seconds of the resolver being dropped, when its current
+    /// blocking query returns.
+    pub fn new(client: &Client, service: &str, options: ResolverOptions) -> Result<Self> {
+        if options.balance == Balance::LeastRtt && options.near.is_none() {
+            return Err(Error::from(
+                "Balance::LeastRtt needs the node to measure from in ResolverOptions::near",
+            ));
+        }
+
+        let instances = Arc::new(Mutex::new(Instances::default()));
+        let stop = Arc::new(AtomicBool::new(false));
+        let (ready, started) = mpsc::channel();
+
+        let client = client.clone();
+        let service = service.to_owned();
+        let balance = options.balance;
+        let shared = Arc::clone(&instances);
+        let stopped = Arc::clone(&stop);
+        thread::Builder::new()
+            .name(format!("consul-resolver-{}", service))
+            .spawn(move || {
+                let mut query_options = options.query_options.clone();
+                query_options.wait_time = Some(
+                    query_options
+                        .wait_time
+                        .map_or(MAX_WAIT, |wait| wait.min(MAX_WAIT)),
+                );
+                let watch = Watch::new(
+                    |o| {
+                        // The watch repeats queries that return no change
+                        // without yielding, so the flag is checked here.
+                        if stopped.load(Ordering::SeqCst) {
+                            return Err(Error::from("The resolver was dropped"));
+                        }
+                        Health::service(&client, &service, options.tag.as_deref(), false, o)
+                    },
+                    Some(query_options),
+                )
+                .with_backoff(DEFAULT_MIN_BACKOFF, MAX_WAIT);
+                let mut ready = Some(ready);
+                for entries in watch {
+                    if stopped.load(Ordering::SeqCst) {
+                        return;
+                    }
+                    let update =
+                        entries.and_then(|entries| instances_of(&client, &entries, &options));
+                    match (update, ready.take()) {
+                        (Ok(update), ready) => {
+                            shared.lock().unwrap().instances = update;
+                            if let Some(ready) = ready {
+                                let _ = ready.send(Ok(()));
+                            }
+                        }
+                        (Err(e), Some(ready)) => {
+                            let _ = ready.send(Err(e));
+                            return;
+                        }
+                        (Err(_), None) => continue,
+                    }
+                }
+            })
+            .chain_err(|| "Failed to start resolver thread")?;
+
+        match started.recv() {
+            Ok(Ok(())) => Ok(ServiceResolver {
+                balance,
+                instances,
+                stop,
+            }),
+            Ok(Err(e)) => Err(e),
+            Err(_) => Err(Error::from("The resolver thread stopped")),
+        }
+    }
+
+    /// The instances to pick from.
+    pub fn instances(&self) -> Vec<Instance> {
+        self.instances.lock().unwrap().instances.clone()
+    }
+
+    /// The address of an instance, or `None` if the service has no healthy
+    /// instance.
+    pub fn resolve(&self) -> Option<SocketAddr> {
+        let mut instances = self.instances.lock().unwrap();
+        if instances.instances.is_empty() {
+            return None;
+        }
+        let addr = pick(self.balance, &instances.instances, instances.turn).addr;
+        instances.turn = instances.turn.wrapping_add(1);
+        Some(addr)
+    }
+}
+
+impl Drop for ServiceResolver {
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::SeqCst);
+    }
+}
+
+/// The instances `entries` describe, sorted by ID, with their round trip
+/// time from `options.near` for `Balance::LeastRtt`.
+fn instances_of(
+    client: &Client,
+    entries: &[ServiceEntry],
+    options: &ResolverOptions,
+) -> Result<Vec<Instance>> {
+    let mut instances: Vec<Instance> = entries.iter().filter_map(lookup_instance).collect();
+    instances.sort_by(|a, b| a.id.cmp(&b.id));
+    if let (Balance::LeastRtt, Some(near)) = (options.balance, options.near.as_deref()) {
+        let coordinate_options = coordinate_options(&options.query_options);
+        let (entries, _) = Coordinate::nodes(client, Some(&coordinate_options))?;
+        let mut coords: HashMap<String, Coord> = HashMap::new();
+        for entry in entries {
+            if let Some(coord) = entry.Coord {
+                // Nodes in several network segments have one entry per segment.
+                coords.entry(entry.Node).or_insert(coord);
+            }
+        }
+        if let Some(origin) = coords.get(near) {
+            for instance in &mut instances {
+                instance.rtt = coords.get(&instance.node).map(|c| origin.distance_to(c));
+            }
+        }
+    }
+    Ok(instances)
+}
+
+/// The options of the coordinate query made with each update: those of the
+/// service query that say where to look and as whom, without blocking.
+fn coordinate_options(options: &QueryOptions) -> QueryOptions {
+    QueryOptions {
+        datacenter: options.datacenter.clone(),
+        partition: options.partition.clone(),
+        token: options.token.clone(),
+        ..Default::default()
+    }
+}
+
+#[cfg(test)]
+mod resolver_tests {
+    use super::*;
+
+    use crate::agent::AgentService;
+    use crate::health::{HealthCheck, Node};
+    use crate::transport::{HttpRequest, HttpResponse, Transport};
+    use crate::Config;
+
+    use reqwest::header::HeaderMap;
+    use reqwest::StatusCode;
+
+    /// Answers every request with an empty list at index 1 and records it.
+    #[derive(Debug, Default)]
+    struct Recorder {
+        requests: Arc<Mutex<Vec<HttpRequest>>>,
+    }
+
+    impl Transport for Recorder {
+        fn send(&self, request: HttpRequest) -> Result<HttpResponse> {
+            self.requests.lock().unwrap().push(request);
+            let mut headers = HeaderMap::new();
+            headers.insert("X-Consul-Index", "1".parse().unwrap());
+            Ok(HttpResponse {
+                status: StatusCode::OK,
+                headers,
+                body: b"[]".to_vec(),
+            })
+        }
+    }
+
+    fn entry(id: &str, service_address: &str, status: HealthStatus) -> ServiceEntry {
+        ServiceEntry {
+            Node: Node {
+                Node: format!("{}-node", id),
+                Address: String::from("10.0.0.1"),
+                ..Default::default()
+            },
+            Service: AgentService {
+                ID: id.to_owned(),
+                Address: service_address.to_owned(),
+                Port: 8080,
+                Weights: Some(Weights {
+                    Passing: 10,
+                    Warning: 1,
+                }),
+                ..Default::default()
+            },
+            Checks: vec![HealthCheck {
+                CheckID: format!("service:{}", id),
+                Status: status,
+                ..Default::default()
+            }],
+        }
+    }
+
+    fn instance(id: &str, weight: u32, rtt: Option<u64>) -> Instance {
+        Instance {
+            id: id.to_owned(),
+            node: format!("{}-node", id),
+            addr: SocketAddr::from(([10, 0, 0, 1], 8080)),
+            status: HealthStatus::Passing,
+            weight,
+            rtt: rtt.map(Duration::from_millis),
+        }
+    }
+
+    #[test]
+    fn from_entry_address_test() {
+        let with_address = Instance::from_entry(&entry("a", "10.0.0.9", HealthStatus::Passing));
+        let without = Instance::from_entry(&entry("b", "", HealthStatus::Passing));
+        let ipv6 = Instance::from_entry(&entry("c", "::1", HealthStatus::Passing));
+
+        assert_eq!(
+            with_address.unwrap().addr,
+            SocketAddr::from(([10, 0, 0, 9], 8080))
+        );
+        assert_eq!(
+            without.unwrap().addr,
+            SocketAddr::from(([10, 0, 0, 1], 8080))
+        );
+        assert_eq!(ipv6.unwrap().addr.to_string(), "[::1]:8080");
+    }
+
+    #[test]
+    fn from_entry_host_name_test() {
+        let named = entry("a", "localhost", HealthStatus::Passing);
+        assert!(Instance::from_entry(&named).is_none());
+
+        let looked_up = lookup_instance(&named).unwrap();
+        assert!(looked_up.addr.ip().is_loopback());
+        assert_eq!(looked_up.addr.port(), 8080);
+        assert!(lookup_instance(&entry("b", "localhost", HealthStatus::Critical)).is_none());
+    }
+
+    #[test]
+    fn from_entry_health_test() {
+        let passing = Instance::from_entry(&entry("a", "", HealthStatus::Passing)).unwrap();
+        let warning = Instance::from_entry(&entry("b", "", HealthStatus::Warning)).unwrap();
+        assert_eq!(passing.weight, 10);
+        assert_eq!(warning.weight, 1);
+        assert!(Instance::from_entry(&entry("c", "", HealthStatus::Critical)).is_none());
+
+        let mut excluded = entry("d", "", HealthStatus::Warning);
+        excluded.Service.Weights = Some(Weights {
+            Passing: 1,
+            Warning: 0,
+        });
+        assert!(Instance::from_entry(&excluded).is_none());
+
+        let mut unweighted = entry("e", "", HealthStatus::Passing);
+        unweighted.Service.Weights = None;
+        assert_eq!(Instance::from_entry(&unweighted).unwrap().weight, 1);
+    }
+
+    #[test]
+    fn pick_round_robin_test() {
+        let instances = vec![instance("a", 1, None), instance("b", 1, None)];
+        let picked: Vec<&str> = (0..4)
+            .map(|turn| pick(Balance::RoundRobin, &instances, turn).id.as_str())
+            .collect();
+        assert_eq!(picked, vec!["a", "b", "a", "b"]);
+    }
+
+    #[test]
+    fn pick_weighted_test() {
+        let instances = vec![instance("heavy", 99, None), instance("light", 1, None)];
+        let heavy = (0..1000)
+            .filter(|_| pick(Balance::Weighted, &instances, 0).id == "heavy")
+            .count();
+        assert!(heavy > 900, "picked heavy {} times", heavy);
+
+        let single = vec![instance("only", 3, None)];
+        assert_eq!(pick(Balance::Random, &single, 7).id, "only");
+    }
+
+    #[test]
+    fn pick_least_rtt_test() {
+        let instances = vec![
+            instance("unknown", 1, None),
+            instance("far", 1, Some(40)),
+            instance("near", 1, Some(2)),
+        ];
+        assert_eq!(pick(Balance::LeastRtt, &instances, 0).id, "near");
+    }
+
+    #[test]
+    fn instances_of_coordinates_test() {
+        let recorder = Recorder::default();
+        let requests = Arc::clone(&recorder.requests);
+        let client = Client::new(Config::new().unwrap().with_transport(recorder));
+        let entries = vec![entry("a", "", HealthStatus::Passing)];
+        let mut options = ResolverOptions {
+            balance: Balance::RoundRobin,
+            near: Some(String::from("a-node")),
+            query_options: QueryOptions {
+                datacenter: Some(String::from("dc2")),
+                partition: Some(String::from("billing")),
+                token: Some(String::from("secret")),
+                wait_index: Some(42),
+                wait_time: Some(Duration::from_secs(60)),
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+
+        // Only least RTT balancing needs coordinates.
+        instances_of(&client, &entries, &options).unwrap();
+        assert!(requests.lock().unwrap().is_empty());
+
+        options.balance = Balance::LeastRtt;
+        instances_of(&client, &entries, &options).unwrap();
+        let requests = requests.lock().unwrap();
+        assert_eq!(requests.len(), 1);
+        let url = &requests[0].url;
+        assert_eq!(url.path(), "/v1/coordinate/nodes");
+        let params: HashMap<_, _> = url.query_pairs().into_owned().collect();
+        assert_eq!(params.get("dc").map(String::as_str), Some("dc2"));
+        assert_eq!(params.get("partition").map(String::as_str), Some("billing"));
+        assert!(!params.contains_key("index"));
+        assert!(!params.contains_key("wait"));
+        assert_eq!(requests[0].headers["X-Consul-Token"], "secret");
+    }
+    #[test]
+    fn resolver_stops_after_drop_test() {
+        let recorder = Recorder::default();
+        let requests = Arc::clone(&recorder.requests);
+        let client = Client::new(Config::new().unwrap().with_transport(recorder));
+        let options = ResolverOptions {
+            query_options: QueryOptions {
+                wait_time: Some(Duration::from_secs(600)),
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+
+        let resolver = ServiceResolver::new(&client, "web", options).unwrap();
+        // The recorder answers at once, so the watch keeps querying.
+        while requests.lock().unwrap().len() < 2 {
+            thread::sleep(Duration::from_millis(10));
+        }
+        drop(resolver);
+        thread::sleep(Duration::from_millis(100));
+        let sent = requests.lock().unwrap().len();
+        thread::sleep(Duration::from_millis(100));
+
+        let requests = requests.lock().unwrap();
+        assert_eq!(requests.len(), sent);
+        let params: HashMap<_, _> = requests[1].url.query_pairs().into_owned().collect();
+        assert_eq!(params.get("wait").map(String::as_str), Some("5s"));
+    }
+}
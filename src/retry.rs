@@ -0,0 +1,190 @@
+//! Retrying failed requests, against the agent's address and its fallbacks.
+
+use std::time::Duration;
+
+use rand::Rng;
+use reqwest::{Method, StatusCode};
+use url::{Position, Url};
+
+use crate::errors::{ErrorKind, Result, ResultExt};
+use crate::transport::HttpResponse;
+use crate::Config;
+
+/// When and how often a `Client` retries a request.
+///
+/// A request is retried when it fails to reach the agent, e.g. while the agent
+/// restarts, or when the agent answers 429 or 5xx. Each attempt tries
+/// `Config::address` and then every `Config::fallback_addresses` in order, so
+/// an attempt may send the request several times. Attempts after the first
+/// wait an exponentially growing delay with random jitter first.
+///
+/// Writes (`PUT`, `POST` and `DELETE`) may have taken effect even when they
+/// failed, e.g. creating a session or a check-and-set, so they are only retried
+/// when `retry_writes` is set. A write that could not connect to an agent
+/// cannot have taken effect, so it is still sent on to the next address.
+#[derive(Clone, Debug)]
+pub struct RetryPolicy {
+    /// The number of attempts, including the first. 1 never retries.
+    pub max_attempts: u32,
+    /// The delay before the second attempt. It doubles with every attempt.
+    pub base_delay: Duration,
+    /// The longest delay between two attempts.
+    pub max_delay: Duration,
+    /// Retries writes as well as reads.
+    pub retry_writes: bool,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        RetryPolicy {
+            max_attempts: 1,
+            base_delay: Duration::from_millis(100),
+            max_delay: Duration::from_secs(5),
+            retry_writes: false,
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// Whether a request with `method` may be sent again.
+    pub fn allows(&self, method: &Method) -> bool {
+        self.retry_writes || method == Method::GET || method == Method::HEAD
+    }
+
+    /// Whether Consul answering with `status` is worth another try.
+    pub fn is_retryable_status(status: StatusCode) -> bool {
+        status == StatusCode::TOO_MANY_REQUESTS || status.is_server_error()
+    }
+
+    /// The delay before the attempt after `attempt`, counting from 1: between
+    /// half of and the full exponential delay.
+    pub fn backoff(&self, attempt: u32) -> Duration {
+        let factor = 2u32.saturating_pow(attempt.saturating_sub(1));
+        let delay = self
+            .base_delay
+            .checked_mul(factor)
+            .unwrap_or(self.max_delay)
+            .min(self.max_delay);
+        let jitter = rand::thread_rng().gen_range(0.0, 0.5);
+        delay.mul_f64(1.0 - jitter)
+    }
+}
+
+/// Whether `result` should be retried.
+pub(crate) fn should_retry(result: &Result<HttpResponse>) -> bool {
+    match result {
+        Ok(response) => RetryPolicy::is_retryable_status(response.status),
+        Err(_) => true,
+    }
+}
+
+/// Whether the request that ended in `result` should be sent to the next
+/// address: when it may be retried and `result` is worth retrying, or when it
+/// never reached the agent.
+pub(crate) fn should_fail_over(retryable: bool, result: &Result<HttpResponse>) -> bool {
+    match result {
+        Err(err) if matches!(err.kind(), ErrorKind::ConnectFailed(_)) => true,
+        _ => retryable && should_retry(result),
+    }
+}
+
+/// `url` sent to the agent's address followed by the same path and query sent
+/// to each fallback address.
+pub(crate) fn targets(config: &Config, url: &Url) -> Result<Vec<Url>> {
+    let mut targets = vec![url.clone()];
+    if config.unix_socket().is_some() {
+        return Ok(targets);
+    }
+    for address in &config.fallback_addresses {
+        let target = format!("{}{}", address, &url[Position::BeforePath..]);
+        targets.push(Url::parse(&target).chain_err(|| "Failed to parse URL")?);
+    }
+    Ok(targets)
+}
+
+#[cfg(test)]
+mod retry_tests {
+    use super::*;
+
+    #[test]
+    fn allows_test() {
+        let policy = RetryPolicy::default();
+        assert!(policy.allows(&Method::GET));
+        assert!(!policy.allows(&Method::PUT));
+        assert!(!policy.allows(&Method::DELETE));
+
+        let policy = RetryPolicy {
+            retry_writes: true,
+            ..Default::default()
+        };
+        assert!(policy.allows(&Method::PUT));
+    }
+
+    #[test]
+    fn is_retryable_status_test() {
+        assert!(RetryPolicy::is_retryable_status(
+            StatusCode::TOO_MANY_REQUESTS
+        ));
+        assert!(RetryPolicy::is_retryable_status(StatusCode::BAD_GATEWAY));
+        assert!(!RetryPolicy::is_retryable_status(StatusCode::NOT_FOUND));
+        assert!(!RetryPolicy::is_retryable_status(StatusCode::OK));
+    }
+
+    #[test]
+    fn backoff_test() {
+        let policy = RetryPolicy {
+            base_delay: Duration::from_millis(100),
+            max_delay: Duration::from_secs(1),
+            ..Default::default()
+        };
+        for _ in 0..20 {
+            let first = policy.backoff(1);
+            assert!(first >= Duration::from_millis(50) && first <= Duration::from_millis(100));
+            let third = policy.backoff(3);
+            assert!(third >= Duration::from_millis(200) && third <= Duration::from_millis(400));
+            let capped = policy.backoff(40);
+            assert!(capped >= Duration::from_millis(500) && capped <= Duration::from_secs(1));
+        }
+    }
+
+    #[test]
+    fn should_fail_over_test() {
+        let refused: Result<HttpResponse> =
+            Err(ErrorKind::ConnectFailed(String::from("http://10.0.0.1:8500")).into());
+        let failed: Result<HttpResponse> = Err("HTTP request to consul failed".into());
+        let unavailable: Result<HttpResponse> = Ok(HttpResponse {
+            status: StatusCode::SERVICE_UNAVAILABLE,
+            headers: Default::default(),
+            body: Vec::new(),
+        });
+
+        assert!(should_fail_over(false, &refused));
+        assert!(!should_fail_over(false, &failed));
+        assert!(!should_fail_over(false, &unavailable));
+        assert!(should_fail_over(true, &failed));
+        assert!(should_fail_over(true, &unavailable));
+    }
+
+    #[test]
+    fn targets_test() {
+        let mut config = Config::new().unwrap();
+        config.address = String::from("http://127.0.0.1:8500");
+        config.fallback_addresses = vec![
+            String::from("http://10.0.0.2:8500"),
+            String::from("https://consul.example.com"),
+        ];
+        let url = Url::parse("http://127.0.0.1:8500/v1/kv/app?index=3").unwrap();
+
+        let targets = targets(&config, &url).unwrap();
+
+        let targets: Vec<_> = targets.iter().map(Url::as_str).collect();
+        assert_eq!(
+            targets,
+            vec![
+                "http://127.0.0.1:8500/v1/kv/app?index=3",
+                "http://10.0.0.2:8500/v1/kv/app?index=3",
+                "https://consul.example.com/v1/kv/app?index=3",
+            ]
+        );
+    }
+}
@@ -0,0 +1,427 @@
+use std::collections::{HashMap, HashSet};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{self, Receiver};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+
+use crate::errors::{Error, Result, ResultExt};
+use crate::kv::{KVPair, KV};
+use crate::lock::{fire, is_cancelled, LostSignal};
+use crate::session::{format_session_ttl, RenewedSession, SessionEntry};
+use crate::{Client, QueryOptions};
+
+/// Flags value that marks keys as being used by a `Semaphore`, as in the Go
+/// client.
+pub const SEMAPHORE_FLAG_VALUE: u64 = 0xe0f6_9a2b_aa41_4de0;
+
+const LOCK_KEY: &str = ".lock";
+
+#[derive(Clone, Debug)]
+pub struct SemaphoreOptions {
+    /// The KV prefix holding the contender keys and the `.lock` key.
+    pub prefix: String,
+    /// The number of holders allowed at once.
+    pub limit: u32,
+    /// Stored under this contender's key.
+    pub value: Vec<u8>,
+    pub session_name: String,
+    /// From 10 seconds to a day, the range Consul accepts.
+    pub session_ttl: Duration,
+    /// How long each blocking query for the prefix waits.
+    pub wait_time: Duration,
+    /// Give up after one round instead of waiting for a slot indefinitely.
+    pub try_once: bool,
+    /// How many times a failing monitor query is retried before the slot is
+    /// considered lost.
+    pub monitor_retries: u32,
+    pub monitor_retry_time: Duration,
+}
+
+impl SemaphoreOptions {
+    pub fn new(prefix: &str, limit: u32) -> Self {
+        SemaphoreOptions {
+            prefix: prefix.trim_end_matches('/').to_owned(),
+            limit,
+            value: Vec::new(),
+            session_name: String::from("Consul API Semaphore"),
+            session_ttl: Duration::from_secs(15),
+            wait_time: Duration::from_secs(15),
+            try_once: false,
+            monitor_retries: 0,
+            monitor_retry_time: Duration::from_secs(2),
+        }
+    }
+
+    /// The session holding a slot, with `session_ttl` checked. It is deleted
+    /// with its contender key when it is invalidated.
+    fn session_entry(&self) -> Result<SessionEntry> {
+        Ok(SessionEntry {
+            Name: Some(self.session_name.clone()),
+            TTL: Some(format_session_ttl(self.session_ttl)?),
+            Behavior: Some(String::from("delete")),
+            ..Default::default()
+        })
+    }
+}
+
+/// The contents of the `.lock` key: the limit and the sessions holding a slot.
+#[derive(Clone, Default, Eq, PartialEq, Serialize, Deserialize, Debug)]
+#[serde(default)]
+pub struct SemaphoreLock {
+    pub Limit: u32,
+    pub Holders: HashMap<String, bool>,
+}
+
+/// A counting semaphore over a KV prefix, like `api.Semaphore` in the Go
+/// client.
+///
+/// Each contender holds `<prefix>/<session>` with its own session, and the
+/// `<prefix>/.lock` key lists the sessions holding a slot. Slots are taken and
+/// given back by check-and-set on `.lock`, and holders whose contender key has
+/// lost its session are pruned, so a host that died frees its slot once its
+/// session expires.
+pub struct Semaphore {
+    client: Client,
+    options: SemaphoreOptions,
+    session: Option<RenewedSession>,
+    lost: LostSignal,
+    stop_monitor: Arc<AtomicBool>,
+}
+
+impl Semaphore {
+    pub fn new(client: Client, options: SemaphoreOptions) -> Result<Self> {
+        if options.limit == 0 {
+            return Err(Error::from("Semaphore limit must be positive"));
+        }
+        options.session_entry()?;
+        Ok(Semaphore {
+            client,
+            options,
+            session: None,
+            lost: Arc::new(Mutex::new(None)),
+            stop_monitor: Arc::new(AtomicBool::new(false)),
+        })
+    }
+
+    pub fn is_held(&self) -> bool {
+        self.session.is_some()
+    }
+
+    /// Waits for a slot and returns a receiver that is sent `()` if the slot is
+    /// lost. It is disconnected once the slot is released.
+    ///
+    /// Cancellation works as for `Lock::lock`.
+    pub fn acquire(&mut self, cancel: Option<&Receiver<()>>) -> Result<Option<Receiver<()>>> {
+        if self.is_held() {
+            return Err(Error::from("Semaphore already held"));
+        }
+
+        let entry = self.options.session_entry()?;
+        let lost = self.lost.clone();
+        let session = RenewedSession::create(&self.client, &entry, move || fire(&lost))?;
+
+        let contender = KVPair {
+            Key: self.contender_key(session.id()),
+            Value: self.options.value.clone(),
+            Flags: Some(SEMAPHORE_FLAG_VALUE),
+            Session: Some(session.id().to_owned()),
+            ..Default::default()
+        };
+        if !self.client.acquire(&contender, None)?.0 {
+            return Err(Error::from("Failed to create semaphore contender entry"));
+        }
+
+        if !self.wait_for_slot(session.id(), cancel)? {
+            let _ = self.client.delete(&contender.Key, None);
+            return Ok(None);
+        }
+
+        let (sender, receiver) = mpsc::channel();
+        *self.lost.lock().unwrap() = Some(sender);
+        self.stop_monitor = Arc::new(AtomicBool::new(false));
+        self.start_monitor(session.id().to_owned())?;
+        self.session = Some(session);
+        Ok(Some(receiver))
+    }
+
+    /// Gives the slot back, removes the contender key and destroys the session.
+    pub fn release(&mut self) -> Result<()> {
+        let session = self
+            .session
+            .take()
+            .ok_or_else(|| Error::from("Semaphore not held"))?;
+        self.stop_monitor.store(true, Ordering::SeqCst);
+        self.lost.lock().unwrap().take();
+
+        let lock_key = self.lock_key();
+        loop {
+            let (pair, _) = self.client.get(&lock_key, None)?;
+            let pair = match pair {
+                Some(pair) => pair,
+                None => break,
+            };
+            let mut lock = decode_lock(&pair, self.options.limit)?;
+            if lock.Holders.remove(session.id()).is_none() {
+                break;
+            }
+            let update = KVPair {
+                Value: encode_lock(&lock)?,
+                ..pair
+            };
+            if self.client.cas(&update, None)?.0 {
+                break;
+            }
+        }
+
+        self.client
+            .delete(&self.contender_key(session.id()), None)?;
+        Ok(())
+    }
+
+    fn contender_key(&self, session: &str) -> String {
+        format!("{}/{}", self.options.prefix, session)
+    }
+
+    fn lock_key(&self) -> String {
+        format!("{}/{}", self.options.prefix, LOCK_KEY)
+    }
+
+    fn wait_for_slot(&self, session: &str, cancel: Option<&Receiver<()>>) -> Result<bool> {
+        let lock_key = self.lock_key();
+        let mut wait_index = None;
+        loop {
+            if is_cancelled(cancel) {
+                return Ok(false);
+            }
+
+            let options = QueryOptions {
+                wait_index,
+                wait_time: Some(self.options.wait_time),
+                ..Default::default()
+            };
+            let (pairs, meta) = self
+                .client
+                .list(&format!("{}/", self.options.prefix), Some(&options))?;
+            wait_index = meta.last_index;
+
+            let (mut lock, lock_pair) = find_lock(&pairs, &lock_key, self.options.limit)?;
+            prune_dead_holders(&mut lock, &pairs, &lock_key);
+
+            if lock.Holders.len() < self.options.limit as usize {
+                lock.Holders.insert(session.to_owned(), true);
+                let update = KVPair {
+                    Key: lock_key.clone(),
+                    Value: encode_lock(&lock)?,
+                    Flags: Some(SEMAPHORE_FLAG_VALUE),
+                    ModifyIndex: lock_pair.and_then(|p| p.ModifyIndex),
+                    ..Default::default()
+                };
+                if self.client.cas(&update, None)?.0 {
+                    return Ok(true);
+                }
+                // Another contender updated `.lock` first; look again at once.
+                wait_index = None;
+                continue;
+            }
+
+            if self.options.try_once {
+                return Ok(false);
+            }
+        }
+    }
+
+    fn start_monitor(&self, session: String) -> Result<()> {
+        let client = self.client.clone();
+        let options = self.options.clone();
+        let lock_key = self.lock_key();
+        let lost = self.lost.clone();
+        let stop = self.stop_monitor.clone();
+        thread::Builder::new()
+            .name(format!("consul-semaphore-{}", options.prefix))
+            .spawn(move || {
+                let mut wait_index = None;
+                let mut retries = options.monitor_retries;
+                while !stop.load(Ordering::SeqCst) {
+                    let query = QueryOptions {
+                        wait_index,
+                        wait_time: Some(options.wait_time),
+                        ..Default::default()
+                    };
+                    match client.get(&lock_key, Some(&query)) {
+                        Ok((pair, meta)) => {
+                            let held = pair
+                                .and_then(|p| decode_lock(&p, options.limit).ok())
+                                .is_some_and(|lock| lock.Holders.contains_key(&session));
+                            if !held {
+                                break;
+                            }
+                            wait_index = meta.last_index;
+                            retries = options.monitor_retries;
+                        }
+                        Err(_) if retries > 0 => {
+                            retries -= 1;
+                            thread::sleep(options.monitor_retry_time);
+                        }
+                        Err(_) => break,
+                    }
+                }
+                if !stop.load(Ordering::SeqCst) {
+                    fire(&lost);
+                }
+            })
+            .map(|_| ())
+            .chain_err(|| "Failed to start semaphore monitor thread")
+    }
+}
+
+impl Drop for Semaphore {
+    fn drop(&mut self) {
+        if self.is_held() {
+            let _ = self.release();
+        }
+    }
+}
+
+/// Finds and decodes the `.lock` key among `pairs`, checking that every key
+/// under the prefix belongs to a semaphore with the same limit.
+fn find_lock<'a>(
+    pairs: &'a [KVPair],
+    lock_key: &str,
+    limit: u32,
+) -> Result<(SemaphoreLock, Option<&'a KVPair>)> {
+    if let Some(pair) = pairs.iter().find(|p| p.Flags != Some(SEMAPHORE_FLAG_VALUE)) {
+        return Err(Error::from(format!(
+            "Existing key {} does not match semaphore use",
+            pair.Key
+        )));
+    }
+    match pairs.iter().find(|p| p.Key == lock_key) {
+        Some(pair) => Ok((decode_lock(pair, limit)?, Some(pair))),
+        None => Ok((
+            SemaphoreLock {
+                Limit: limit,
+                Holders: HashMap::new(),
+            },
+            None,
+        )),
+    }
+}
+
+/// Drops holders whose contender key no longer has a live session.
+fn prune_dead_holders(lock: &mut SemaphoreLock, pairs: &[KVPair], lock_key: &str) {
+    let alive: HashSet<&str> = pairs
+        .iter()
+        .filter(|p| p.Key != lock_key)
+        .filter_map(|p| p.Session.as_deref())
+        .collect();
+    lock.Holders
+        .retain(|session, _| alive.contains(session.as_str()));
+}
+
+fn decode_lock(pair: &KVPair, limit: u32) -> Result<SemaphoreLock> {
+    let lock: SemaphoreLock = pair.value_json()?;
+    if lock.Limit != limit {
+        return Err(Error::from(format!(
+            "Semaphore limit conflict (lock: {}, local: {})",
+            lock.Limit, limit
+        )));
+    }
+    Ok(lock)
+}
+
+fn encode_lock(lock: &SemaphoreLock) -> Result<Vec<u8>> {
+    serde_json::to_vec(lock).chain_err(|| "Failed to encode semaphore lock")
+}
+
+#[cfg(test)]
+mod semaphore_tests {
+    use super::*;
+
+    fn contender(session: Option<&str>) -> KVPair {
+        KVPair {
+            Key: format!("jobs/{}", session.unwrap_or("dead")),
+            Flags: Some(SEMAPHORE_FLAG_VALUE),
+            Session: session.map(String::from),
+            ..Default::default()
+        }
+    }
+
+    fn lock_pair(lock: &SemaphoreLock) -> KVPair {
+        KVPair {
+            Key: String::from("jobs/.lock"),
+            Flags: Some(SEMAPHORE_FLAG_VALUE),
+            ModifyIndex: Some(7),
+            Value: encode_lock(lock).unwrap(),
+            ..Default::default()
+        }
+    }
+
+    fn holders(sessions: &[&str]) -> HashMap<String, bool> {
+        sessions.iter().map(|s| (s.to_string(), true)).collect()
+    }
+
+    #[test]
+    fn find_missing_lock_test() {
+        let pairs = vec![contender(Some("a"))];
+
+        let (lock, pair) = find_lock(&pairs, "jobs/.lock", 3).unwrap();
+
+        assert_eq!(lock.Limit, 3);
+        assert!(lock.Holders.is_empty());
+        assert!(pair.is_none());
+    }
+
+    #[test]
+    fn find_lock_checks_limit_and_flags_test() {
+        let lock = SemaphoreLock {
+            Limit: 2,
+            Holders: holders(&["a"]),
+        };
+        let pairs = vec![contender(Some("a")), lock_pair(&lock)];
+
+        let (found, pair) = find_lock(&pairs, "jobs/.lock", 2).unwrap();
+        assert_eq!(found, lock);
+        assert_eq!(pair.unwrap().ModifyIndex, Some(7));
+
+        assert!(find_lock(&pairs, "jobs/.lock", 3).is_err());
+
+        let foreign = KVPair {
+            Key: String::from("jobs/other"),
+            ..Default::default()
+        };
+        assert!(find_lock(&[foreign], "jobs/.lock", 2).is_err());
+    }
+
+    #[test]
+    fn prune_dead_holders_test() {
+        let mut lock = SemaphoreLock {
+            Limit: 3,
+            Holders: holders(&["a", "b", "c"]),
+        };
+        let pairs = vec![contender(Some("a")), contender(None), contender(Some("c"))];
+
+        prune_dead_holders(&mut lock, &pairs, "jobs/.lock");
+
+        assert_eq!(lock.Holders, holders(&["a", "c"]));
+    }
+
+    #[test]
+    fn session_entry_ttl_test() {
+        let mut options = SemaphoreOptions::new("jobs", 2);
+        let entry = options.session_entry().unwrap();
+        assert_eq!(entry.TTL.as_deref(), Some("15s"));
+        assert_eq!(entry.Behavior.as_deref(), Some("delete"));
+
+        options.session_ttl = Duration::from_millis(10_500);
+        assert_eq!(
+            options.session_entry().unwrap().TTL.as_deref(),
+            Some("10500ms")
+        );
+
+        options.session_ttl = Duration::from_secs(0);
+        assert!(options.session_entry().is_err());
+        options.session_ttl = Duration::from_secs(86401);
+        assert!(options.session_entry().is_err());
+    }
+}
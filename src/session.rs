@@ -1,18 +1,29 @@
 use std::collections::HashMap;
+use std::sync::mpsc::{self, RecvTimeoutError, Sender};
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
 
-use crate::errors::Result;
+#[cfg(feature = "async")]
+use async_trait::async_trait;
+
+use crate::duration::{format_duration, parse_duration};
+use crate::errors::{Error, Result, ResultExt};
+#[cfg(feature = "async")]
+use crate::request::async_requests;
 use crate::request::get_requests::get;
 use crate::request::put_requests::put;
+#[cfg(feature = "async")]
+use crate::AsyncClient;
 use crate::{Client, QueryMeta, QueryOptions, WriteMeta, WriteOptions};
 
-#[serde(default)]
 #[derive(Clone, Default, Eq, PartialEq, Serialize, Deserialize, Debug)]
+#[serde(default)]
 pub struct SessionID {
     pub ID: String,
 }
 
-#[serde(default)]
 #[derive(Clone, Default, Eq, PartialEq, Serialize, Deserialize, Debug)]
+#[serde(default)]
 pub struct SessionEntry {
     pub CreateIndex: Option<u64>,
     pub ID: Option<String>,
@@ -108,3 +119,209 @@ impl Session for Client {
         )
     }
 }
+
+#[cfg(feature = "async")]
+#[async_trait]
+pub trait AsyncSession {
+    async fn create(
+        &self,
+        session: &SessionEntry,
+        options: Option<&WriteOptions>,
+    ) -> Result<(SessionEntry, WriteMeta)>;
+    async fn destroy(&self, id: &str, options: Option<&WriteOptions>) -> Result<(bool, WriteMeta)>;
+    async fn info(
+        &self,
+        id: &str,
+        options: Option<&QueryOptions>,
+    ) -> Result<(Vec<SessionEntry>, QueryMeta)>;
+    async fn list(&self, options: Option<&QueryOptions>) -> Result<(Vec<SessionEntry>, QueryMeta)>;
+    async fn node(
+        &self,
+        node: &str,
+        options: Option<&QueryOptions>,
+    ) -> Result<(Vec<SessionEntry>, QueryMeta)>;
+    async fn renew(
+        &self,
+        id: &str,
+        options: Option<&WriteOptions>,
+    ) -> Result<(Vec<SessionEntry>, WriteMeta)>;
+}
+
+#[cfg(feature = "async")]
+#[async_trait]
+impl AsyncSession for AsyncClient {
+    async fn create(
+        &self,
+        session: &SessionEntry,
+        options: Option<&WriteOptions>,
+    ) -> Result<(SessionEntry, WriteMeta)> {
+        async_requests::put(
+            "/v1/session/create",
+            Some(session),
+            &self.config,
+            HashMap::new(),
+            options,
+        )
+        .await
+    }
+    async fn destroy(&self, id: &str, options: Option<&WriteOptions>) -> Result<(bool, WriteMeta)> {
+        let path = format!("/v1/session/destroy/{}", id);
+        async_requests::put(
+            &path,
+            None as Option<&()>,
+            &self.config,
+            HashMap::new(),
+            options,
+        )
+        .await
+    }
+    async fn info(
+        &self,
+        id: &str,
+        options: Option<&QueryOptions>,
+    ) -> Result<(Vec<SessionEntry>, QueryMeta)> {
+        let path = format!("/v1/session/info/{}", id);
+        async_requests::get(&path, &self.config, HashMap::new(), options).await
+    }
+    async fn list(&self, options: Option<&QueryOptions>) -> Result<(Vec<SessionEntry>, QueryMeta)> {
+        async_requests::get("/v1/session/list", &self.config, HashMap::new(), options).await
+    }
+    async fn node(
+        &self,
+        node: &str,
+        options: Option<&QueryOptions>,
+    ) -> Result<(Vec<SessionEntry>, QueryMeta)> {
+        let path = format!("/v1/session/node/{}", node);
+        async_requests::get(&path, &self.config, HashMap::new(), options).await
+    }
+
+    async fn renew(
+        &self,
+        id: &str,
+        options: Option<&WriteOptions>,
+    ) -> Result<(Vec<SessionEntry>, WriteMeta)> {
+        let path = format!("/v1/session/renew/{}", id);
+        async_requests::put(
+            &path,
+            None as Option<&()>,
+            &self.config,
+            HashMap::new(),
+            options,
+        )
+        .await
+    }
+}
+
+// The range of session TTLs Consul accepts.
+const MIN_SESSION_TTL: Duration = Duration::from_secs(10);
+const MAX_SESSION_TTL: Duration = Duration::from_secs(86400);
+
+/// Formats `ttl` for `SessionEntry::TTL`, in whole seconds when possible and
+/// without losing precision otherwise. Consul only accepts TTLs from 10
+/// seconds to a day, so anything else is an error.
+pub(crate) fn format_session_ttl(ttl: Duration) -> Result<String> {
+    if ttl < MIN_SESSION_TTL || ttl > MAX_SESSION_TTL {
+        return Err(Error::from(format!(
+            "Session TTL {:?} is outside of {:?} to {:?}",
+            ttl, MIN_SESSION_TTL, MAX_SESSION_TTL
+        )));
+    }
+    Ok(format_duration(ttl))
+}
+
+/// A session that is renewed at half its TTL until it is dropped, at which
+/// point it is destroyed. Backs the `lock` and `semaphore` primitives.
+pub(crate) struct RenewedSession {
+    client: Client,
+    id: String,
+    stop: Option<Sender<()>>,
+    renewal: Option<JoinHandle<()>>,
+}
+
+impl RenewedSession {
+    /// Creates a session with `entry` and starts renewing it. `on_lost` is
+    /// called from the renewal thread if the session is invalidated.
+    pub(crate) fn create<F>(client: &Client, entry: &SessionEntry, on_lost: F) -> Result<Self>
+    where
+        F: FnOnce() + Send + 'static,
+    {
+        let ttl = entry
+            .TTL
+            .as_deref()
+            .ok_or("A session TTL is required for renewal")
+            .map(parse_duration)??;
+        let id = client
+            .create(entry, None)?
+            .0
+            .ID
+            .ok_or("Consul did not return a session ID")?;
+
+        let (stop, stopped) = mpsc::channel();
+        let renew_client = client.clone();
+        let renew_id = id.clone();
+        let renewal = thread::Builder::new()
+            .name(format!("consul-session-{}", id))
+            .spawn(move || {
+                while let Err(RecvTimeoutError::Timeout) = stopped.recv_timeout(ttl / 2) {
+                    match renew_client.renew(&renew_id, None) {
+                        Err(ref e) if e.kind().is_not_found() => return on_lost(),
+                        // Anything else is retried until the TTL runs out.
+                        _ => continue,
+                    }
+                }
+            });
+        let renewal = match renewal {
+            Ok(renewal) => renewal,
+            Err(e) => {
+                let _ = client.destroy(&id, None);
+                return Err(e).chain_err(|| "Failed to start session renewal thread");
+            }
+        };
+
+        Ok(RenewedSession {
+            client: client.clone(),
+            id,
+            stop: Some(stop),
+            renewal: Some(renewal),
+        })
+    }
+
+    pub(crate) fn id(&self) -> &str {
+        &self.id
+    }
+}
+
+impl Drop for RenewedSession {
+    fn drop(&mut self) {
+        self.stop.take();
+        if let Some(renewal) = self.renewal.take() {
+            let _ = renewal.join();
+        }
+        let _ = self.client.destroy(&self.id, None);
+    }
+}
+
+#[cfg(test)]
+mod session_tests {
+    use super::*;
+
+    #[test]
+    fn format_session_ttl_test() {
+        assert_eq!(format_session_ttl(Duration::from_secs(10)).unwrap(), "10s");
+        assert_eq!(
+            format_session_ttl(Duration::from_secs(86400)).unwrap(),
+            "86400s"
+        );
+        assert_eq!(
+            format_session_ttl(Duration::from_millis(10_500)).unwrap(),
+            "10500ms"
+        );
+        assert_eq!(
+            format_session_ttl(Duration::new(10, 1)).unwrap(),
+            "10000000001ns"
+        );
+        assert!(format_session_ttl(Duration::from_secs(0)).is_err());
+        assert!(format_session_ttl(Duration::from_millis(9_999)).is_err());
+        assert!(format_session_ttl(Duration::from_secs(86401)).is_err());
+    }
+}
@@ -0,0 +1,363 @@
+//! An in-memory Consul agent for tests.
+//!
+//! `TestServer` listens on a random localhost port and answers the HTTP API the
+//! client uses: KV (with indexes, check-and-set and session locks),
+//! transactions, sessions, catalog registration, health and the agent
+//! endpoints. Reads support blocking queries. Everything lives in memory, so
+//! tests need neither network access nor a Consul binary.
+//!
+//! It is not a full Consul: ACLs, tokens, lock delays and multiple
+//! datacenters are not enforced, and every request is answered by the same
+//! agent.
+//!
+//! ```no_run
+//! use consul::kv::{KVPair, KV};
+//! use consul::testing::TestServer;
+//! use consul::Client;
+//!
+//! let server = TestServer::start().unwrap();
+//! let client = Client::new(server.config().unwrap());
+//! let pair = KVPair {
+//!     Key: String::from("my/key"),
+//!     Value: b"value".to_vec(),
+//!     ..Default::default()
+//! };
+//! client.put(&pair, None).unwrap();
+//! ```
+
+use std::collections::HashMap;
+use std::io::{self, BufRead, BufReader, ErrorKind as IoErrorKind, Write};
+use std::net::{SocketAddr, TcpListener, TcpStream};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Condvar, Mutex};
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+
+use url::form_urlencoded;
+
+use crate::errors::{Result, ResultExt};
+use crate::Config;
+
+mod api;
+mod state;
+
+use api::{Reply, Request};
+use state::State;
+
+// Idle connections check this often whether the server was stopped.
+const IDLE_POLL: Duration = Duration::from_millis(100);
+
+/// What the cluster behind a `TestServer` looks like.
+#[derive(Clone, Debug)]
+pub struct TestServerOptions {
+    /// The datacenter the agent is in.
+    pub datacenter: String,
+    /// The datacenters listed by the catalog.
+    pub datacenters: Vec<String>,
+    /// The agent's node name. Other servers are named after it.
+    pub node: String,
+    /// The number of server nodes, each a member running the `consul` service.
+    pub servers: usize,
+}
+
+impl Default for TestServerOptions {
+    fn default() -> Self {
+        TestServerOptions {
+            datacenter: String::from("dc1"),
+            datacenters: vec![String::from("dc1")],
+            node: String::from("test-node"),
+            servers: 1,
+        }
+    }
+}
+
+struct Shared {
+    state: Mutex<State>,
+    // Signalled after every write, waking blocked reads.
+    changed: Condvar,
+    stopped: AtomicBool,
+}
+
+/// A running in-memory Consul agent. It stops when dropped.
+pub struct TestServer {
+    address: SocketAddr,
+    shared: Arc<Shared>,
+    listener: Option<JoinHandle<()>>,
+}
+
+impl TestServer {
+    /// Starts a single node cluster in datacenter `dc1`.
+    pub fn start() -> Result<Self> {
+        Self::start_with(TestServerOptions::default())
+    }
+
+    pub fn start_with(options: TestServerOptions) -> Result<Self> {
+        let listener =
+            TcpListener::bind("127.0.0.1:0").chain_err(|| "Failed to bind test server")?;
+        let address = listener
+            .local_addr()
+            .chain_err(|| "Failed to bind test server")?;
+        let state = State::new(
+            &options.datacenter,
+            &options.datacenters,
+            &options.node,
+            options.servers,
+        );
+        let shared = Arc::new(Shared {
+            state: Mutex::new(state),
+            changed: Condvar::new(),
+            stopped: AtomicBool::new(false),
+        });
+
+        let accepting = shared.clone();
+        let listener = thread::Builder::new()
+            .name(format!("consul-test-server-{}", address.port()))
+            .spawn(move || accept(listener, accepting))
+            .chain_err(|| "Failed to start test server thread")?;
+        Ok(TestServer {
+            address,
+            shared,
+            listener: Some(listener),
+        })
+    }
+
+    /// The server's address, e.g. `http://127.0.0.1:52113`.
+    pub fn address(&self) -> String {
+        format!("http://{}", self.address)
+    }
+
+    /// A `Config` pointing at this server.
+    pub fn config(&self) -> Result<Config> {
+        let mut config = Config::new()?;
+        config.address = self.address();
+        Ok(config)
+    }
+}
+
+impl Drop for TestServer {
+    fn drop(&mut self) {
+        self.shared.stopped.store(true, Ordering::SeqCst);
+        self.shared.changed.notify_all();
+        // Wakes the listener, which is blocked in accept.
+        let _ = TcpStream::connect(self.address);
+        if let Some(listener) = self.listener.take() {
+            let _ = listener.join();
+        }
+    }
+}
+
+fn accept(listener: TcpListener, shared: Arc<Shared>) {
+    for stream in listener.incoming() {
+        if shared.stopped.load(Ordering::SeqCst) {
+            return;
+        }
+        if let Ok(stream) = stream {
+            let shared = shared.clone();
+            let _ = thread::Builder::new()
+                .name(String::from("consul-test-connection"))
+                .spawn(move || {
+                    let _ = serve(stream, &shared);
+                });
+        }
+    }
+}
+
+/// Answers the requests of one keep-alive connection.
+fn serve(stream: TcpStream, shared: &Shared) -> io::Result<()> {
+    let mut writer = stream.try_clone()?;
+    let mut reader = BufReader::new(stream);
+    loop {
+        if !wait_for_request(&mut reader, shared)? {
+            return Ok(());
+        }
+        reader.get_ref().set_read_timeout(None)?;
+        let (request, close) = read_request(&mut reader)?;
+        let reply = api::handle(shared, &request);
+        write_reply(&mut writer, &reply, close)?;
+        if close {
+            return Ok(());
+        }
+    }
+}
+
+/// Waits until the next request starts arriving. Returns false when the
+/// connection was closed or the server stopped.
+fn wait_for_request(reader: &mut BufReader<TcpStream>, shared: &Shared) -> io::Result<bool> {
+    reader.get_ref().set_read_timeout(Some(IDLE_POLL))?;
+    loop {
+        if shared.stopped.load(Ordering::SeqCst) {
+            return Ok(false);
+        }
+        match reader.fill_buf() {
+            Ok(buf) => return Ok(!buf.is_empty()),
+            Err(e) if e.kind() == IoErrorKind::WouldBlock || e.kind() == IoErrorKind::TimedOut => {}
+            Err(e) => return Err(e),
+        }
+    }
+}
+
+fn invalid<E: ToString>(e: E) -> io::Error {
+    io::Error::new(IoErrorKind::InvalidData, e.to_string())
+}
+
+/// Reads a request and whether the connection should be closed after it.
+fn read_request<R: BufRead>(reader: &mut R) -> io::Result<(Request, bool)> {
+    let mut line = String::new();
+    reader.read_line(&mut line)?;
+    let mut parts = line.split_whitespace();
+    let (method, target, version) = match (parts.next(), parts.next(), parts.next()) {
+        (Some(method), Some(target), Some(version)) => (method, target, version),
+        _ => return Err(invalid("malformed request line")),
+    };
+
+    let mut headers = HashMap::new();
+    loop {
+        let mut header = String::new();
+        reader.read_line(&mut header)?;
+        let header = header.trim_end();
+        if header.is_empty() {
+            break;
+        }
+        let colon = header
+            .find(':')
+            .ok_or_else(|| invalid("malformed header"))?;
+        headers.insert(
+            header[..colon].trim().to_ascii_lowercase(),
+            header[colon + 1..].trim().to_owned(),
+        );
+    }
+
+    let chunked = headers
+        .get("transfer-encoding")
+        .is_some_and(|v| v.eq_ignore_ascii_case("chunked"));
+    let body = if chunked {
+        read_chunked(reader)?
+    } else {
+        let length = match headers.get("content-length") {
+            Some(length) => length.parse().map_err(invalid)?,
+            None => 0,
+        };
+        let mut body = vec![0; length];
+        reader.read_exact(&mut body)?;
+        body
+    };
+
+    let close = match headers.get("connection") {
+        Some(connection) => connection.eq_ignore_ascii_case("close"),
+        None => version == "HTTP/1.0",
+    };
+    let (path, query) = match target.find('?') {
+        Some(at) => (&target[..at], &target[at + 1..]),
+        None => (target, ""),
+    };
+    let request = Request {
+        method: method.to_owned(),
+        path: percent_decode(path)?,
+        query: form_urlencoded::parse(query.as_bytes())
+            .into_owned()
+            .collect(),
+        body,
+    };
+    Ok((request, close))
+}
+
+fn read_chunked<R: BufRead>(reader: &mut R) -> io::Result<Vec<u8>> {
+    let mut body = Vec::new();
+    loop {
+        let mut line = String::new();
+        reader.read_line(&mut line)?;
+        let size = line.trim_end().split(';').next().unwrap_or("");
+        let size = usize::from_str_radix(size.trim(), 16).map_err(invalid)?;
+        if size == 0 {
+            // Skips trailers up to the final empty line.
+            loop {
+                line.clear();
+                reader.read_line(&mut line)?;
+                if line.trim_end().is_empty() {
+                    return Ok(body);
+                }
+            }
+        }
+        let start = body.len();
+        body.resize(start + size, 0);
+        reader.read_exact(&mut body[start..])?;
+        let mut crlf = [0; 2];
+        reader.read_exact(&mut crlf)?;
+    }
+}
+
+fn percent_decode(path: &str) -> io::Result<String> {
+    let bytes = path.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i] == b'%' {
+            let hex = path
+                .get(i + 1..i + 3)
+                .ok_or_else(|| invalid("malformed escape"))?;
+            decoded.push(u8::from_str_radix(hex, 16).map_err(invalid)?);
+            i += 3;
+        } else {
+            decoded.push(bytes[i]);
+            i += 1;
+        }
+    }
+    String::from_utf8(decoded).map_err(invalid)
+}
+
+fn write_reply<W: Write>(writer: &mut W, reply: &Reply, close: bool) -> io::Result<()> {
+    let mut head = format!(
+        "HTTP/1.1 {} {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\n\
+         X-Consul-KnownLeader: true\r\nX-Consul-LastContact: 0\r\n",
+        reply.status.as_u16(),
+        reply.status.canonical_reason().unwrap_or(""),
+        reply.body.len()
+    );
+    if let Some(index) = reply.index {
+        head.push_str(&format!("X-Consul-Index: {}\r\n", index));
+    }
+    if close {
+        head.push_str("Connection: close\r\n");
+    }
+    head.push_str("\r\n");
+    writer.write_all(head.as_bytes())?;
+    writer.write_all(&reply.body)?;
+    writer.flush()
+}
+
+#[cfg(test)]
+mod testing_tests {
+    use super::*;
+
+    #[test]
+    fn read_request_test() {
+        let raw = b"PUT /v1/kv/app%2Fconfig?cas=5&flags=1 HTTP/1.1\r\nHost: localhost\r\n\
+                    Content-Length: 5\r\n\r\nvalue";
+
+        let (request, close) = read_request(&mut &raw[..]).unwrap();
+
+        assert_eq!(request.method, "PUT");
+        assert_eq!(request.path, "/v1/kv/app/config");
+        assert_eq!(request.query["cas"], "5");
+        assert_eq!(request.query["flags"], "1");
+        assert_eq!(request.body, b"value");
+        assert!(!close);
+    }
+
+    #[test]
+    fn read_chunked_request_test() {
+        let raw = b"POST /v1/txn HTTP/1.1\r\nTransfer-Encoding: chunked\r\n\
+                    Connection: close\r\n\r\n3\r\n[]\n\r\n0\r\n\r\n";
+
+        let (request, close) = read_request(&mut &raw[..]).unwrap();
+
+        assert_eq!(request.body, b"[]\n");
+        assert!(close);
+    }
+
+    #[test]
+    fn read_malformed_request_test() {
+        assert!(read_request(&mut &b"GET\r\n\r\n"[..]).is_err());
+        assert!(read_request(&mut &b"GET /v1/kv/%zz HTTP/1.1\r\n\r\n"[..]).is_err());
+    }
+}
@@ -0,0 +1,1203 @@
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::sync::atomic::Ordering;
+use std::time::{Duration, Instant};
+
+use reqwest::StatusCode;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use serde_json::{json, Value};
+
+use crate::agent::{
+    AgentCheck, AgentCheckRegistration, AgentService, AgentServiceCheck, AgentServiceRegistration,
+};
+use crate::catalog::Weights;
+use crate::duration::parse_duration;
+use crate::event::{id_to_index, UserEvent};
+use crate::kv::KVPair;
+use crate::session::SessionEntry;
+
+use super::state::{
+    uuid, Check, Kv, Node, Session, State, CONSUL_SERVICE, CRITICAL, PASSING, SERF_CHECK_ID,
+};
+use super::Shared;
+
+const DEFAULT_WAIT: Duration = Duration::from_secs(5 * 60);
+const MAX_WAIT: Duration = Duration::from_secs(10 * 60);
+// Blocked reads wake up at least this often to expire sessions and TTL checks.
+const SWEEP_INTERVAL: Duration = Duration::from_millis(200);
+const MAX_TXN_OPS: usize = 64;
+const DEFAULT_LOCK_DELAY_NS: u64 = 15_000_000_000;
+const SERVER_ADDRESS: &str = "127.0.0.1:8300";
+
+pub(super) struct Request {
+    pub method: String,
+    /// The percent-decoded path.
+    pub path: String,
+    pub query: HashMap<String, String>,
+    pub body: Vec<u8>,
+}
+
+pub(super) struct Reply {
+    pub status: StatusCode,
+    pub body: Vec<u8>,
+    pub index: Option<u64>,
+    /// The index does not grow, so reads only block while it is unchanged.
+    pub unordered_index: bool,
+}
+
+impl Reply {
+    fn json<T: Serialize>(value: &T) -> Reply {
+        Reply {
+            status: StatusCode::OK,
+            body: serde_json::to_vec(value).unwrap(),
+            index: None,
+            unordered_index: false,
+        }
+    }
+
+    fn empty() -> Reply {
+        Reply {
+            status: StatusCode::OK,
+            body: Vec::new(),
+            index: None,
+            unordered_index: false,
+        }
+    }
+
+    fn error<S: Into<String>>(status: StatusCode, message: S) -> Reply {
+        Reply {
+            status,
+            body: message.into().into_bytes(),
+            index: None,
+            unordered_index: false,
+        }
+    }
+
+    fn bad_request<S: Into<String>>(message: S) -> Reply {
+        Reply::error(StatusCode::BAD_REQUEST, message)
+    }
+
+    fn not_found() -> Reply {
+        Reply::error(StatusCode::NOT_FOUND, "")
+    }
+
+    fn with_index(mut self, index: u64) -> Reply {
+        self.index = Some(index.max(1));
+        self
+    }
+
+    fn with_unordered_index(mut self, index: u64) -> Reply {
+        self.unordered_index = true;
+        self.with_index(index)
+    }
+}
+
+/// Answers a request. Reads with an `index` block until the answer's index
+/// moves past it or `wait` runs out.
+pub(super) fn handle(shared: &Shared, request: &Request) -> Reply {
+    if request.method == "GET" {
+        return blocking_read(shared, request);
+    }
+    let mut state = shared.state.lock().unwrap();
+    state.sweep(Instant::now());
+    let reply = write(&mut state, request);
+    shared.changed.notify_all();
+    reply
+}
+
+fn blocking_read(shared: &Shared, request: &Request) -> Reply {
+    let wait_index = request
+        .query
+        .get("index")
+        .and_then(|i| i.parse::<u64>().ok())
+        .filter(|i| *i > 0);
+    let wait = request
+        .query
+        .get("wait")
+        .and_then(|w| parse_duration(w).ok())
+        .unwrap_or(DEFAULT_WAIT)
+        .min(MAX_WAIT);
+    let deadline = Instant::now() + wait;
+
+    let mut state = shared.state.lock().unwrap();
+    loop {
+        let now = Instant::now();
+        if state.sweep(now) {
+            shared.changed.notify_all();
+        }
+        let reply = read(&state, request);
+        let blocked = match (wait_index, reply.index) {
+            (Some(wait_index), Some(index)) if reply.unordered_index => index == wait_index,
+            (Some(wait_index), Some(index)) => index <= wait_index,
+            _ => false,
+        };
+        if !blocked || now >= deadline || shared.stopped.load(Ordering::SeqCst) {
+            return reply;
+        }
+        let timeout = (deadline - now).min(SWEEP_INTERVAL);
+        state = shared.changed.wait_timeout(state, timeout).unwrap().0;
+    }
+}
+
+fn read(state: &State, request: &Request) -> Reply {
+    let path = request.path.as_str();
+    let query = &request.query;
+    if let Some(key) = path.strip_prefix("/v1/kv/") {
+        return kv_get(state, key, query);
+    }
+    if let Some(id) = path.strip_prefix("/v1/session/info/") {
+        let sessions = sessions(state, |s| s.ID.as_deref() == Some(id));
+        return Reply::json(&sessions).with_index(state.session_index);
+    }
+    if let Some(node) = path.strip_prefix("/v1/session/node/") {
+        let sessions = sessions(state, |s| s.Node.as_deref() == Some(node));
+        return Reply::json(&sessions).with_index(state.session_index);
+    }
+    if let Some(id) = path.strip_prefix("/v1/agent/service/") {
+        return match local_node(state).services.get(id) {
+            Some(service) => Reply::json(service).with_index(state.catalog_index),
+            None => Reply::error(StatusCode::NOT_FOUND, format!("unknown service ID: {}", id)),
+        };
+    }
+    if let Some(name) = path.strip_prefix("/v1/catalog/service/") {
+        return catalog_service(state, query, |s| s.Service == name);
+    }
+    if let Some(name) = path.strip_prefix("/v1/catalog/connect/") {
+        return catalog_service(state, query, |s| is_connect_instance(s, name));
+    }
+    if let Some(name) = path.strip_prefix("/v1/catalog/node/") {
+        return catalog_node(state, name);
+    }
+    if let Some(name) = path.strip_prefix("/v1/catalog/node-services/") {
+        return catalog_node_services(state, name);
+    }
+    if path.starts_with("/v1/catalog/gateway-services/") {
+        // Gateways are configured with config entries, which are not kept.
+        return Reply::json(&Vec::<Value>::new()).with_index(state.catalog_index);
+    }
+    if let Some(name) = path.strip_prefix("/v1/health/service/") {
+        return health_service(state, query, |s| s.Service == name);
+    }
+    if let Some(name) = path.strip_prefix("/v1/health/connect/") {
+        return health_service(state, query, |s| is_connect_instance(s, name));
+    }
+    if path.starts_with("/v1/health/ingress/") {
+        // Ingress routes are configured with config entries, which are not kept.
+        return Reply::json(&Vec::<Value>::new()).with_index(state.catalog_index);
+    }
+    if let Some(name) = path.strip_prefix("/v1/coordinate/node/") {
+        let entries: Vec<Value> = coordinates(state).filter(|c| c["Node"] == name).collect();
+        if entries.is_empty() {
+            return Reply::not_found().with_index(state.catalog_index);
+        }
+        return Reply::json(&entries).with_index(state.catalog_index);
+    }
+    if let Some(service) = path.strip_prefix("/v1/health/checks/") {
+        return health_checks(state, |_, c| c.ServiceName == service);
+    }
+    if let Some(node) = path.strip_prefix("/v1/health/node/") {
+        return health_checks(state, |n, _| n == node);
+    }
+    if let Some(status) = path.strip_prefix("/v1/health/state/") {
+        if !["any", PASSING, "warning", CRITICAL].contains(&status) {
+            return Reply::bad_request("Invalid check state");
+        }
+        return health_checks(state, |_, c| status == "any" || c.Status == status);
+    }
+
+    match path {
+        "/v1/session/list" => {
+            let sessions = sessions(state, |_| true);
+            Reply::json(&sessions).with_index(state.session_index)
+        }
+        "/v1/event/list" => {
+            let events: Vec<&UserEvent> = state
+                .events
+                .iter()
+                .filter(|e| query.get("name").is_none_or(|name| e.Name == *name))
+                .collect();
+            // As in Consul, the index stands for the last event.
+            let index = events.last().and_then(|e| id_to_index(&e.ID)).unwrap_or(1);
+            Reply::json(&events).with_unordered_index(index)
+        }
+        "/v1/coordinate/nodes" => {
+            let entries: Vec<Value> = coordinates(state).collect();
+            Reply::json(&entries).with_index(state.catalog_index)
+        }
+        "/v1/coordinate/datacenters" => {
+            let servers: Vec<Value> = coordinates(state)
+                .filter(|c| state.servers.iter().any(|s| c["Node"] == s.as_str()))
+                .collect();
+            Reply::json(&json!([{
+                "Datacenter": state.datacenter,
+                "AreaID": "wan",
+                "Coordinates": servers,
+            }]))
+        }
+        "/v1/agent/self" => Reply::json(&json!({
+            "Config": {
+                "Datacenter": state.datacenter,
+                "NodeName": state.node,
+                "NodeID": local_node(state).id,
+                "Server": true,
+                "Version": "1.9.0",
+            },
+            "Member": member(state, &state.node, false),
+        })),
+        "/v1/agent/members" => {
+            let wan = query.get("wan").is_some_and(|w| w != "0");
+            let members: Vec<Value> = state
+                .servers
+                .iter()
+                .map(|name| member(state, name, wan))
+                .collect();
+            Reply::json(&members)
+        }
+        "/v1/agent/services" => {
+            let services: BTreeMap<&String, &AgentService> = local_node(state)
+                .services
+                .iter()
+                .filter(|(id, _)| id.as_str() != CONSUL_SERVICE)
+                .collect();
+            Reply::json(&services)
+        }
+        "/v1/agent/checks" => {
+            let checks: BTreeMap<&String, &AgentCheck> = local_node(state)
+                .checks
+                .iter()
+                .filter(|(id, _)| id.as_str() != SERF_CHECK_ID)
+                .map(|(id, c)| (id, &c.check))
+                .collect();
+            Reply::json(&checks)
+        }
+        "/v1/catalog/datacenters" => Reply::json(&state.datacenters),
+        "/v1/catalog/nodes" => {
+            let nodes: Vec<Value> = state
+                .nodes
+                .iter()
+                .filter(|(_, node)| matches_node_meta(node, query))
+                .map(|(name, node)| node_json(state, name, node))
+                .collect();
+            Reply::json(&nodes).with_index(state.catalog_index)
+        }
+        "/v1/catalog/services" => {
+            let mut services: BTreeMap<&String, BTreeSet<&String>> = BTreeMap::new();
+            for node in state.nodes.values() {
+                if !matches_node_meta(node, query) {
+                    continue;
+                }
+                for service in node.services.values() {
+                    services
+                        .entry(&service.Service)
+                        .or_default()
+                        .extend(service.Tags.iter().flatten());
+                }
+            }
+            Reply::json(&services).with_index(state.catalog_index)
+        }
+        "/v1/status/leader" => Reply::json(&SERVER_ADDRESS),
+        "/v1/status/peers" => Reply::json(&vec![SERVER_ADDRESS; state.servers.len()]),
+        _ => Reply::not_found(),
+    }
+}
+
+fn write(state: &mut State, request: &Request) -> Reply {
+    let path = request.path.as_str();
+    if let Some(key) = path.strip_prefix("/v1/kv/") {
+        return match request.method.as_str() {
+            "PUT" | "POST" => kv_put(state, key, request),
+            "DELETE" => kv_delete(state, key, &request.query),
+            _ => Reply::error(StatusCode::METHOD_NOT_ALLOWED, ""),
+        };
+    }
+    if request.method != "PUT" && request.method != "POST" {
+        return Reply::error(StatusCode::METHOD_NOT_ALLOWED, "");
+    }
+    if let Some(name) = path.strip_prefix("/v1/event/fire/") {
+        return event_fire(state, name, request);
+    }
+    if let Some(id) = path.strip_prefix("/v1/session/destroy/") {
+        state.invalidate_session(id);
+        return Reply::json(&true);
+    }
+    if let Some(id) = path.strip_prefix("/v1/session/renew/") {
+        return match state.sessions.get_mut(id) {
+            Some(session) => {
+                session.renew();
+                Reply::json(&[&session.entry])
+            }
+            None => Reply::error(
+                StatusCode::NOT_FOUND,
+                format!("Session id '{}' not found", id),
+            ),
+        };
+    }
+    if let Some(id) = path.strip_prefix("/v1/agent/service/deregister/") {
+        let index = state.next_index();
+        let node = state.node.clone();
+        return if state.remove_service(&node, id, index) {
+            Reply::empty()
+        } else {
+            Reply::error(
+                StatusCode::NOT_FOUND,
+                format!("Unknown service ID {:?}", id),
+            )
+        };
+    }
+    if let Some(id) = path.strip_prefix("/v1/agent/check/deregister/") {
+        let index = state.next_index();
+        let node = state.node.clone();
+        return if state.remove_check(&node, id, index) {
+            Reply::empty()
+        } else {
+            Reply::error(StatusCode::NOT_FOUND, format!("Unknown check ID {:?}", id))
+        };
+    }
+    for (prefix, status) in &[
+        ("/v1/agent/check/pass/", PASSING),
+        ("/v1/agent/check/warn/", "warning"),
+        ("/v1/agent/check/fail/", CRITICAL),
+    ] {
+        if let Some(id) = path.strip_prefix(prefix) {
+            let note = request.query.get("note").cloned().unwrap_or_default();
+            return update_check(state, id, status, &note);
+        }
+    }
+    if let Some(id) = path.strip_prefix("/v1/agent/check/update/") {
+        #[derive(Default, Deserialize)]
+        #[serde(default)]
+        struct CheckUpdate {
+            Status: String,
+            Output: String,
+        }
+        return match parse_body::<CheckUpdate>(&request.body) {
+            Ok(update) if [PASSING, "warning", CRITICAL].contains(&update.Status.as_str()) => {
+                update_check(state, id, &update.Status, &update.Output)
+            }
+            Ok(update) => Reply::bad_request(format!("Invalid check status: {:?}", update.Status)),
+            Err(reply) => reply,
+        };
+    }
+    if path.starts_with("/v1/agent/join/") || path.starts_with("/v1/agent/force-leave") {
+        return Reply::empty();
+    }
+
+    match path {
+        "/v1/txn" => txn(state, &request.body),
+        "/v1/session/create" => session_create(state, &request.body),
+        "/v1/agent/service/register" => match parse_body(&request.body) {
+            Ok(registration) => agent_service_register(state, registration),
+            Err(reply) => reply,
+        },
+        "/v1/agent/check/register" => match parse_body(&request.body) {
+            Ok(registration) => agent_check_register(state, registration),
+            Err(reply) => reply,
+        },
+        "/v1/agent/maintenance" => {
+            let enable = request.query.get("enable").map(String::as_str);
+            let reason = request.query.get("reason").cloned().unwrap_or_default();
+            maintenance(state, enable, reason)
+        }
+        "/v1/agent/reload" | "/v1/agent/leave" => Reply::empty(),
+        "/v1/catalog/register" => match parse_body(&request.body) {
+            Ok(registration) => catalog_register(state, registration),
+            Err(reply) => reply,
+        },
+        "/v1/catalog/deregister" => match parse_body(&request.body) {
+            Ok(deregistration) => catalog_deregister(state, deregistration),
+            Err(reply) => reply,
+        },
+        _ => Reply::not_found(),
+    }
+}
+
+fn parse_body<T: DeserializeOwned + Default>(body: &[u8]) -> Result<T, Reply> {
+    if body.is_empty() {
+        return Ok(T::default());
+    }
+    serde_json::from_slice(body)
+        .map_err(|e| Reply::bad_request(format!("Request decode failed: {}", e)))
+}
+
+fn query_u64(query: &HashMap<String, String>, name: &str) -> Result<Option<u64>, Reply> {
+    query
+        .get(name)
+        .map(|v| {
+            v.parse()
+                .map_err(|_| Reply::bad_request(format!("Invalid {}: {:?}", name, v)))
+        })
+        .transpose()
+}
+
+fn kv_get(state: &State, key: &str, query: &HashMap<String, String>) -> Reply {
+    if query.contains_key("keys") {
+        let index = state.kv.query_index(key, true);
+        let separator = query.get("separator").filter(|s| !s.is_empty());
+        let mut keys: Vec<&str> = Vec::new();
+        for pair in state.kv.list(key) {
+            // Keys are collapsed to their prefix up to the first separator.
+            let listed = match separator.and_then(|s| pair.Key[key.len()..].find(s.as_str())) {
+                Some(at) => &pair.Key[..key.len() + at + separator.unwrap().len()],
+                None => pair.Key.as_str(),
+            };
+            if keys.last() != Some(&listed) {
+                keys.push(listed);
+            }
+        }
+        return if keys.is_empty() {
+            Reply::not_found().with_index(index)
+        } else {
+            Reply::json(&keys).with_index(index)
+        };
+    }
+
+    let recurse = query.contains_key("recurse");
+    let index = state.kv.query_index(key, recurse);
+    let pairs: Vec<&KVPair> = if recurse {
+        state.kv.list(key).collect()
+    } else {
+        state.kv.get(key).into_iter().collect()
+    };
+    if pairs.is_empty() {
+        return Reply::not_found().with_index(index);
+    }
+    if query.contains_key("raw") && !recurse {
+        return Reply {
+            status: StatusCode::OK,
+            body: pairs[0].Value.clone(),
+            index: None,
+            unordered_index: false,
+        }
+        .with_index(index);
+    }
+    Reply::json(&pairs).with_index(index)
+}
+
+fn kv_put(state: &mut State, key: &str, request: &Request) -> Reply {
+    if key.is_empty() {
+        return Reply::bad_request("Missing key name");
+    }
+    let query = &request.query;
+    let (flags, cas) = match (query_u64(query, "flags"), query_u64(query, "cas")) {
+        (Ok(flags), Ok(cas)) => (flags.unwrap_or(0), cas),
+        (Err(reply), _) | (_, Err(reply)) => return reply,
+    };
+    let session = query.get("acquire").or_else(|| query.get("release"));
+    if let Some(session) = session {
+        if !state.sessions.contains_key(session) {
+            return Reply::error(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                format!("invalid session {:?}", session),
+            );
+        }
+    }
+
+    let index = state.next_index();
+    let value = &request.body;
+    let written = if let Some(session) = query.get("acquire") {
+        state.kv.acquire(key, value, flags, session, index)
+    } else if let Some(session) = query.get("release") {
+        state.kv.release(key, value, flags, session, index)
+    } else if let Some(cas) = cas {
+        state.kv.cas(key, value, flags, cas, index)
+    } else {
+        state.kv.set(key, value, flags, index);
+        true
+    };
+    Reply::json(&written)
+}
+
+fn kv_delete(state: &mut State, key: &str, query: &HashMap<String, String>) -> Reply {
+    let cas = match query_u64(query, "cas") {
+        Ok(cas) => cas,
+        Err(reply) => return reply,
+    };
+    let index = state.next_index();
+    let deleted = if query.contains_key("recurse") {
+        state.kv.delete_tree(key, index);
+        true
+    } else if let Some(cas) = cas {
+        state.kv.delete_cas(key, cas, index)
+    } else {
+        state.kv.delete(key, index);
+        true
+    };
+    Reply::json(&deleted)
+}
+
+#[derive(Default, Deserialize)]
+#[serde(default)]
+struct KVTxnOp {
+    Verb: String,
+    Key: String,
+    Value: Option<String>,
+    Flags: u64,
+    Index: u64,
+    Session: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct TxnOp {
+    KV: Option<KVTxnOp>,
+}
+
+/// Applies every operation to a copy of the KV store and keeps it only if all
+/// of them succeed.
+fn txn(state: &mut State, body: &[u8]) -> Reply {
+    let ops: Vec<TxnOp> = match parse_body(body) {
+        Ok(ops) => ops,
+        Err(reply) => return reply,
+    };
+    if ops.len() > MAX_TXN_OPS {
+        return Reply::error(
+            StatusCode::PAYLOAD_TOO_LARGE,
+            format!(
+                "Transaction contains too many operations ({} > {})",
+                ops.len(),
+                MAX_TXN_OPS
+            ),
+        );
+    }
+
+    let index = state.next_index();
+    let mut kv = state.kv.clone();
+    let mut results = Vec::new();
+    let mut errors = Vec::new();
+    for (op_index, op) in ops.iter().enumerate() {
+        let outcome = match op.KV {
+            Some(ref op) => apply_kv_op(&mut kv, state, op, index),
+            None => Err(String::from("only KV operations are supported")),
+        };
+        match outcome {
+            Ok(pairs) => results.extend(pairs.into_iter().map(|kv| json!({ "KV": kv }))),
+            Err(what) => errors.push(json!({ "OpIndex": op_index, "What": what })),
+        }
+    }
+
+    if errors.is_empty() {
+        state.kv = kv;
+        Reply::json(&json!({ "Results": results, "Errors": null }))
+    } else {
+        Reply {
+            status: StatusCode::CONFLICT,
+            ..Reply::json(&json!({ "Results": null, "Errors": errors }))
+        }
+    }
+}
+
+fn apply_kv_op(
+    kv: &mut Kv,
+    state: &State,
+    op: &KVTxnOp,
+    index: u64,
+) -> Result<Vec<KVPair>, String> {
+    let key = op.Key.as_str();
+    let value = match op.Value {
+        Some(ref value) => base64::decode(value).map_err(|e| e.to_string())?,
+        None => Vec::new(),
+    };
+    let session = op.Session.as_deref().unwrap_or("");
+    let missing = || format!("key {:?} doesn't exist", key);
+    let written = |kv: &Kv| {
+        let mut pair = kv.get(key).cloned().unwrap();
+        pair.Value = Vec::new();
+        Ok(vec![pair])
+    };
+
+    match op.Verb.as_str() {
+        "set" => {
+            kv.set(key, &value, op.Flags, index);
+            written(kv)
+        }
+        "cas" => {
+            if kv.cas(key, &value, op.Flags, op.Index, index) {
+                written(kv)
+            } else {
+                Err(format!("failed to set key {:?}, index is stale", key))
+            }
+        }
+        "lock" | "unlock" if !state.sessions.contains_key(session) => {
+            Err(format!("invalid session {:?}", session))
+        }
+        "lock" => {
+            if kv.acquire(key, &value, op.Flags, session, index) {
+                written(kv)
+            } else {
+                Err(format!(
+                    "failed to lock key {:?}, lock is already held",
+                    key
+                ))
+            }
+        }
+        "unlock" => {
+            if kv.release(key, &value, op.Flags, session, index) {
+                written(kv)
+            } else {
+                Err(format!(
+                    "failed to unlock key {:?}, lock isn't held, or is held by another session",
+                    key
+                ))
+            }
+        }
+        "get" => kv.get(key).cloned().map(|p| vec![p]).ok_or_else(missing),
+        "get-tree" => Ok(kv.list(key).cloned().collect()),
+        "check-index" => match kv.get(key) {
+            Some(pair) if pair.ModifyIndex == Some(op.Index) => Ok(vec![pair.clone()]),
+            Some(pair) => Err(format!(
+                "current modify index {} != {}",
+                pair.ModifyIndex.unwrap_or(0),
+                op.Index
+            )),
+            None => Err(missing()),
+        },
+        "check-session" => match kv.get(key) {
+            Some(pair) if pair.Session.as_deref() == Some(session) => Ok(vec![pair.clone()]),
+            Some(_) => Err(format!("failed session check for key {:?}", key)),
+            None => Err(missing()),
+        },
+        "check-not-exists" => match kv.get(key) {
+            Some(_) => Err(format!("key {:?} exists", key)),
+            None => Ok(Vec::new()),
+        },
+        "delete" => {
+            kv.delete(key, index);
+            Ok(Vec::new())
+        }
+        "delete-tree" => {
+            kv.delete_tree(key, index);
+            Ok(Vec::new())
+        }
+        "delete-cas" => {
+            if kv.delete_cas(key, op.Index, index) {
+                Ok(Vec::new())
+            } else {
+                Err(format!("failed to delete key {:?}, index is stale", key))
+            }
+        }
+        verb => Err(format!("unknown KV verb {:?}", verb)),
+    }
+}
+
+fn sessions<F: Fn(&SessionEntry) -> bool>(state: &State, filter: F) -> Vec<&SessionEntry> {
+    state
+        .sessions
+        .values()
+        .map(|s| &s.entry)
+        .filter(|e| filter(e))
+        .collect()
+}
+
+fn session_create(state: &mut State, body: &[u8]) -> Reply {
+    let mut entry: SessionEntry = match parse_body(body) {
+        Ok(entry) => entry,
+        Err(reply) => return reply,
+    };
+    let node = entry.Node.get_or_insert_with(|| state.node.clone());
+    if !state.nodes.contains_key(node.as_str()) {
+        return Reply::error(
+            StatusCode::INTERNAL_SERVER_ERROR,
+            format!("Missing node registration {:?}", node),
+        );
+    }
+    let ttl = match entry.TTL.as_deref().filter(|t| !t.is_empty()) {
+        Some(ttl) => match parse_duration(ttl) {
+            Ok(ttl) if ttl >= Duration::from_secs(10) && ttl <= Duration::from_secs(86400) => {
+                Some(ttl)
+            }
+            _ => return Reply::bad_request(format!("Invalid Session TTL {:?}", ttl)),
+        },
+        None => None,
+    };
+    let behavior = entry
+        .Behavior
+        .get_or_insert_with(|| String::from("release"));
+    if behavior != "release" && behavior != "delete" {
+        return Reply::bad_request(format!("Invalid Behavior setting {:?}", behavior));
+    }
+    entry.LockDelay.get_or_insert(DEFAULT_LOCK_DELAY_NS);
+    entry
+        .Checks
+        .get_or_insert_with(|| vec![String::from(SERF_CHECK_ID)]);
+
+    let index = state.next_index();
+    let id = uuid();
+    entry.ID = Some(id.clone());
+    entry.CreateIndex = Some(index);
+    state.sessions.insert(id.clone(), Session::new(entry, ttl));
+    state.session_index = index;
+    Reply::json(&json!({ "ID": id }))
+}
+
+fn local_node(state: &State) -> &Node {
+    &state.nodes[&state.node]
+}
+
+fn member(state: &State, name: &str, wan: bool) -> Value {
+    let name = if wan {
+        format!("{}.{}", name, state.datacenter)
+    } else {
+        name.to_owned()
+    };
+    json!({
+        "Name": name,
+        "Addr": "127.0.0.1",
+        "Port": if wan { 8302 } else { 8301 },
+        "Tags": { "role": "consul", "dc": state.datacenter },
+        "Status": 1,
+        "ProtocolMin": 1,
+        "ProtocolMax": 5,
+        "ProtocolCur": 2,
+        "DelegateMin": 2,
+        "DelegateMax": 5,
+        "DelegateCur": 4,
+    })
+}
+
+/// The check a service or check registration describes.
+fn new_check(
+    state: &State,
+    definition: &AgentServiceCheck,
+    id: String,
+    name: String,
+    service: Option<&AgentService>,
+) -> Result<Check, Reply> {
+    let ttl = match definition.TTL {
+        Some(ref ttl) => Some(
+            parse_duration(ttl)
+                .map_err(|_| Reply::bad_request(format!("Invalid TTL {:?}", ttl)))?,
+        ),
+        None => None,
+    };
+    let check = AgentCheck {
+        Node: state.node.clone(),
+        CheckID: id,
+        Name: definition.Name.clone().unwrap_or(name),
+        Status: definition
+            .Status
+            .clone()
+            .unwrap_or_else(|| String::from(CRITICAL)),
+        Notes: definition.Notes.clone().unwrap_or_default(),
+        Output: String::new(),
+        ServiceID: service.map(|s| s.ID.clone()).unwrap_or_default(),
+        ServiceName: service.map(|s| s.Service.clone()).unwrap_or_default(),
+    };
+    Ok(Check::new(check, ttl))
+}
+
+fn agent_service_register(state: &mut State, registration: AgentServiceRegistration) -> Reply {
+    if registration.Name.is_empty() {
+        return Reply::bad_request("Missing service name");
+    }
+    let id = match registration.ID {
+        Some(id) => id,
+        None => registration.Name.clone(),
+    };
+    let service = AgentService {
+        Kind: registration.Kind,
+        ID: id,
+        Service: registration.Name,
+        Tags: Some(registration.Tags.unwrap_or_default()),
+        Meta: Some(registration.Meta.unwrap_or_default()),
+        Port: registration.Port.unwrap_or(0),
+        Address: registration.Address.unwrap_or_default(),
+        TaggedAddresses: registration.TaggedAddresses,
+        Weights: Some(registration.Weights.unwrap_or(Weights {
+            Passing: 1,
+            Warning: 1,
+        })),
+        EnableTagOverride: registration.EnableTagOverride.unwrap_or(false),
+        Proxy: registration.Proxy,
+        Connect: registration.Connect,
+        ..Default::default()
+    };
+
+    let definitions: Vec<&AgentServiceCheck> = registration
+        .Check
+        .iter()
+        .chain(registration.Checks.iter().flatten())
+        .collect();
+    let mut checks = Vec::new();
+    for (n, definition) in definitions.iter().enumerate() {
+        let id = definition.CheckID.clone().unwrap_or_else(|| {
+            if definitions.len() == 1 {
+                format!("service:{}", service.ID)
+            } else {
+                format!("service:{}:{}", service.ID, n + 1)
+            }
+        });
+        let name = format!("Service '{}' check", service.Service);
+        match new_check(state, definition, id, name, Some(&service)) {
+            Ok(check) => checks.push(check),
+            Err(reply) => return reply,
+        }
+    }
+
+    let index = state.next_index();
+    let node = state.node.clone();
+    state.remove_service(&node, &service.ID, index);
+    state.put_service(&node, service, index);
+    for check in checks {
+        state.put_check(&node, check, index);
+    }
+    Reply::empty()
+}
+
+fn agent_check_register(state: &mut State, registration: AgentCheckRegistration) -> Reply {
+    let definition = &registration.Check;
+    let name = match definition.Name {
+        Some(ref name) if !name.is_empty() => name.clone(),
+        _ => return Reply::bad_request("Missing check name"),
+    };
+    let id = registration
+        .ID
+        .clone()
+        .or_else(|| definition.CheckID.clone())
+        .unwrap_or_else(|| name.clone());
+    let service = match registration.ServiceID {
+        Some(ref service_id) => match local_node(state).services.get(service_id) {
+            Some(service) => Some(service.clone()),
+            None => {
+                return Reply::bad_request(format!("ServiceID {:?} does not exist", service_id))
+            }
+        },
+        None => None,
+    };
+    let check = match new_check(state, definition, id, name, service.as_ref()) {
+        Ok(check) => check,
+        Err(reply) => return reply,
+    };
+    let index = state.next_index();
+    let node = state.node.clone();
+    state.put_check(&node, check, index);
+    Reply::empty()
+}
+
+fn update_check(state: &mut State, id: &str, status: &str, output: &str) -> Reply {
+    let node = state.node.clone();
+    if state.update_check(&node, id, status, output) {
+        Reply::empty()
+    } else {
+        Reply::error(StatusCode::NOT_FOUND, format!("Unknown check ID {:?}", id))
+    }
+}
+
+fn maintenance(state: &mut State, enable: Option<&str>, reason: String) -> Reply {
+    const MAINTENANCE_CHECK_ID: &str = "_node_maintenance";
+
+    let enable = match enable {
+        Some("true") => true,
+        Some("false") => false,
+        _ => return Reply::bad_request("Missing value for enable"),
+    };
+    let index = state.next_index();
+    let node = state.node.clone();
+    if enable {
+        let check = AgentCheck {
+            Node: node.clone(),
+            CheckID: MAINTENANCE_CHECK_ID.to_owned(),
+            Name: String::from("Node Maintenance Mode"),
+            Status: CRITICAL.to_owned(),
+            Notes: reason,
+            ..Default::default()
+        };
+        state.put_check(&node, Check::new(check, None), index);
+    } else {
+        state.remove_check(&node, MAINTENANCE_CHECK_ID, index);
+    }
+    Reply::empty()
+}
+
+#[derive(Default, Deserialize)]
+#[serde(default)]
+struct CatalogRegistration {
+    ID: String,
+    Node: String,
+    Address: String,
+    TaggedAddresses: HashMap<String, String>,
+    NodeMeta: HashMap<String, String>,
+    Service: Option<AgentService>,
+    Check: Option<AgentCheck>,
+    Checks: Vec<AgentCheck>,
+}
+
+#[derive(Default, Deserialize)]
+#[serde(default)]
+struct CatalogDeregistration {
+    Node: String,
+    ServiceID: String,
+    CheckID: String,
+}
+
+fn catalog_register(state: &mut State, registration: CatalogRegistration) -> Reply {
+    if registration.Node.is_empty() {
+        return Reply::bad_request("Must provide node");
+    }
+    if registration.Address.is_empty() {
+        return Reply::bad_request("Must provide address");
+    }
+    let mut service = registration.Service;
+    if let Some(ref mut service) = service {
+        if service.Service.is_empty() {
+            return Reply::bad_request("Must provide service name");
+        }
+        if service.ID.is_empty() {
+            service.ID = service.Service.clone();
+        }
+        if service.Weights.is_none() {
+            service.Weights = Some(Weights {
+                Passing: 1,
+                Warning: 1,
+            });
+        }
+    }
+
+    let index = state.next_index();
+    let name = registration.Node;
+    let node = state.upsert_node(&name, &registration.Address, index);
+    if !registration.ID.is_empty() {
+        node.id = registration.ID;
+    }
+    if !registration.TaggedAddresses.is_empty() {
+        node.tagged_addresses = registration.TaggedAddresses;
+    }
+    if !registration.NodeMeta.is_empty() {
+        node.meta = registration.NodeMeta;
+    }
+    if let Some(service) = service {
+        state.put_service(&name, service, index);
+    }
+    for mut check in registration.Check.into_iter().chain(registration.Checks) {
+        if check.CheckID.is_empty() {
+            check.CheckID = check.Name.clone();
+        }
+        if check.Status.is_empty() {
+            check.Status = String::from(CRITICAL);
+        }
+        check.Node = name.clone();
+        state.put_check(&name, Check::new(check, None), index);
+    }
+    Reply::json(&true)
+}
+
+fn catalog_deregister(state: &mut State, deregistration: CatalogDeregistration) -> Reply {
+    if deregistration.Node.is_empty() {
+        return Reply::bad_request("Must provide node");
+    }
+    let index = state.next_index();
+    let node = &deregistration.Node;
+    if !deregistration.ServiceID.is_empty() {
+        state.remove_service(node, &deregistration.ServiceID, index);
+    } else if !deregistration.CheckID.is_empty() {
+        state.remove_check(node, &deregistration.CheckID, index);
+    } else if state.nodes.remove(node).is_some() {
+        state.catalog_index = index;
+    }
+    Reply::json(&true)
+}
+
+fn event_fire(state: &mut State, name: &str, request: &Request) -> Reply {
+    if request.method != "PUT" {
+        return Reply::error(StatusCode::METHOD_NOT_ALLOWED, "");
+    }
+    let filter = |name: &str| request.query.get(name).cloned().unwrap_or_default();
+    if !filter("tag").is_empty() && filter("service").is_empty() {
+        return Reply::bad_request("Cannot provide tag filter without service filter.");
+    }
+    state.event_ltime += 1;
+    let event = UserEvent {
+        ID: uuid(),
+        Name: name.to_owned(),
+        Payload: request.body.clone(),
+        NodeFilter: filter("node"),
+        ServiceFilter: filter("service"),
+        TagFilter: filter("tag"),
+        Version: 1,
+        LTime: state.event_ltime,
+    };
+    // Agents keep a buffer of the last 256 events.
+    if state.events.len() == 256 {
+        state.events.pop_front();
+    }
+    state.events.push_back(event.clone());
+    Reply::json(&event)
+}
+
+/// A LAN coordinate per node, each a millisecond further from the origin than
+/// the one before it in name order.
+fn coordinates(state: &State) -> impl Iterator<Item = Value> + '_ {
+    state.nodes.keys().enumerate().map(|(n, name)| {
+        let mut vec = vec![0.0; 8];
+        vec[0] = n as f64 * 0.001;
+        json!({
+            "Node": name,
+            "Segment": "",
+            "Coord": {"Vec": vec, "Error": 1.5, "Adjustment": 0.0, "Height": 0.00001},
+        })
+    })
+}
+
+fn node_json(state: &State, name: &str, node: &Node) -> Value {
+    json!({
+        "ID": node.id,
+        "Node": name,
+        "Address": node.address,
+        "Datacenter": state.datacenter,
+        "TaggedAddresses": node.tagged_addresses,
+        "Meta": node.meta,
+        "CreateIndex": node.create_index,
+        "ModifyIndex": node.modify_index,
+    })
+}
+
+/// Whether the node has every `node-meta=key:value` pair of the query.
+fn matches_node_meta(node: &Node, query: &HashMap<String, String>) -> bool {
+    match query.get("node-meta") {
+        Some(filter) => {
+            let (key, value) = filter.split_at(filter.find(':').unwrap_or(filter.len()));
+            node.meta.get(key).map(String::as_str) == Some(value.trim_start_matches(':'))
+        }
+        None => true,
+    }
+}
+
+fn has_tag(service: &AgentService, query: &HashMap<String, String>) -> bool {
+    match query.get("tag") {
+        Some(tag) => service.Tags.iter().flatten().any(|t| t == tag),
+        None => true,
+    }
+}
+
+/// Whether the service is a proxy for `name` or a Connect native `name`.
+fn is_connect_instance(service: &AgentService, name: &str) -> bool {
+    let proxies = service.Kind.as_deref() == Some("connect-proxy")
+        && service
+            .Proxy
+            .as_ref()
+            .and_then(|p| p.DestinationServiceName.as_deref())
+            == Some(name);
+    let native = service.Service == name
+        && service
+            .Connect
+            .as_ref()
+            .and_then(|c| c.Native)
+            .unwrap_or(false);
+    proxies || native
+}
+
+fn catalog_service<F: Fn(&AgentService) -> bool>(
+    state: &State,
+    query: &HashMap<String, String>,
+    matches: F,
+) -> Reply {
+    let mut entries = Vec::new();
+    for (node_name, node) in &state.nodes {
+        if !matches_node_meta(node, query) {
+            continue;
+        }
+        for service in node.services.values() {
+            if !matches(service) || !has_tag(service, query) {
+                continue;
+            }
+            entries.push(json!({
+                "ID": node.id,
+                "Node": node_name,
+                "Address": node.address,
+                "Datacenter": state.datacenter,
+                "TaggedAddresses": node.tagged_addresses,
+                "NodeMeta": node.meta,
+                "ServiceKind": service.Kind.clone().unwrap_or_default(),
+                "ServiceID": service.ID,
+                "ServiceName": service.Service,
+                "ServiceAddress": service.Address,
+                "ServiceTags": service.Tags.clone().unwrap_or_default(),
+                "ServiceMeta": service.Meta.clone().unwrap_or_default(),
+                "ServicePort": service.Port,
+                "ServiceWeights": service.Weights,
+                "ServiceEnableTagOverride": service.EnableTagOverride,
+                "CreateIndex": service.CreateIndex,
+                "ModifyIndex": service.ModifyIndex,
+            }));
+        }
+    }
+    Reply::json(&entries).with_index(state.catalog_index)
+}
+
+fn catalog_node(state: &State, name: &str) -> Reply {
+    let body = match state.nodes.get(name) {
+        Some(node) => json!({
+            "Node": node_json(state, name, node),
+            "Services": node.services,
+        }),
+        None => Value::Null,
+    };
+    Reply::json(&body).with_index(state.catalog_index)
+}
+
+fn catalog_node_services(state: &State, name: &str) -> Reply {
+    let body = match state.nodes.get(name) {
+        Some(node) => json!({
+            "Node": node_json(state, name, node),
+            "Services": node.services.values().collect::<Vec<_>>(),
+        }),
+        None => Value::Null,
+    };
+    Reply::json(&body).with_index(state.catalog_index)
+}
+
+fn health_check_json(node: &Node, check: &AgentCheck) -> Value {
+    let tags = node
+        .services
+        .get(&check.ServiceID)
+        .and_then(|s| s.Tags.clone())
+        .unwrap_or_default();
+    let mut value = serde_json::to_value(check).unwrap();
+    value["ServiceTags"] = json!(tags);
+    value
+}
+
+fn health_checks<F: Fn(&str, &AgentCheck) -> bool>(state: &State, filter: F) -> Reply {
+    let filter = &filter;
+    let checks: Vec<Value> = state
+        .nodes
+        .iter()
+        .flat_map(|(name, node)| {
+            node.checks
+                .values()
+                .filter(move |c| filter(name, &c.check))
+                .map(move |c| health_check_json(node, &c.check))
+        })
+        .collect();
+    Reply::json(&checks).with_index(state.catalog_index)
+}
+
+fn health_service<F: Fn(&AgentService) -> bool>(
+    state: &State,
+    query: &HashMap<String, String>,
+    matches: F,
+) -> Reply {
+    let passing_only = query.contains_key("passing");
+    let mut entries = Vec::new();
+    for (node_name, node) in &state.nodes {
+        for service in node.services.values() {
+            if !matches(service) || !has_tag(service, query) {
+                continue;
+            }
+            // Node level checks count towards every service on the node.
+            let checks: Vec<&AgentCheck> = node
+                .checks
+                .values()
+                .map(|c| &c.check)
+                .filter(|c| c.ServiceID.is_empty() || c.ServiceID == service.ID)
+                .collect();
+            if passing_only && checks.iter().any(|c| c.Status != PASSING) {
+                continue;
+            }
+            entries.push(json!({
+                "Node": node_json(state, node_name, node),
+                "Service": service,
+                "Checks": checks.iter().map(|c| health_check_json(node, c)).collect::<Vec<_>>(),
+            }));
+        }
+    }
+    Reply::json(&entries).with_index(state.catalog_index)
+}
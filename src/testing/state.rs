@@ -0,0 +1,575 @@
+use std::collections::{BTreeMap, HashMap, VecDeque};
+use std::time::{Duration, Instant};
+
+use rand::Rng;
+
+use crate::agent::{AgentCheck, AgentService};
+use crate::catalog::Weights;
+use crate::event::UserEvent;
+use crate::kv::KVPair;
+use crate::session::SessionEntry;
+
+/// The check every node carries, as registered by the cluster itself.
+pub(super) const SERF_CHECK_ID: &str = "serfHealth";
+/// The service every server node runs.
+pub(super) const CONSUL_SERVICE: &str = "consul";
+
+pub(super) const PASSING: &str = "passing";
+pub(super) const CRITICAL: &str = "critical";
+
+/// The key/value table. It is cloned to apply a transaction, so a failed
+/// operation leaves the store untouched.
+#[derive(Clone, Default)]
+pub(super) struct Kv {
+    pub entries: BTreeMap<String, KVPair>,
+    // Deleted keys keep their last index, so that blocking queries on them or
+    // on a prefix containing them see the deletion.
+    tombstones: BTreeMap<String, u64>,
+    pub index: u64,
+}
+
+impl Kv {
+    pub fn get(&self, key: &str) -> Option<&KVPair> {
+        self.entries.get(key)
+    }
+
+    pub fn list<'a>(&'a self, prefix: &'a str) -> impl Iterator<Item = &'a KVPair> + 'a {
+        self.entries
+            .range(prefix.to_owned()..)
+            .take_while(move |(key, _)| key.starts_with(prefix))
+            .map(|(_, pair)| pair)
+    }
+
+    /// The index a read of `key`, or of everything under it with `recurse`,
+    /// answers with.
+    pub fn query_index(&self, key: &str, recurse: bool) -> u64 {
+        let index = if recurse {
+            let entries = self.list(key).map(|p| p.ModifyIndex.unwrap_or(0));
+            let tombstones = self
+                .tombstones
+                .range(key.to_owned()..)
+                .take_while(|(k, _)| k.starts_with(key))
+                .map(|(_, index)| *index);
+            entries.chain(tombstones).max().unwrap_or(0)
+        } else {
+            self.entries
+                .get(key)
+                .and_then(|p| p.ModifyIndex)
+                .or_else(|| self.tombstones.get(key).copied())
+                .unwrap_or(0)
+        };
+        if index == 0 {
+            self.index
+        } else {
+            index
+        }
+    }
+
+    pub fn set(&mut self, key: &str, value: &[u8], flags: u64, index: u64) {
+        let pair = self
+            .entries
+            .entry(key.to_owned())
+            .or_insert_with(|| KVPair {
+                Key: key.to_owned(),
+                CreateIndex: Some(index),
+                LockIndex: Some(0),
+                ..Default::default()
+            });
+        pair.Value = value.to_vec();
+        pair.Flags = Some(flags);
+        pair.ModifyIndex = Some(index);
+        self.tombstones.remove(key);
+        self.index = index;
+    }
+
+    /// Writes only if the key's `ModifyIndex` is `cas`, or if it does not exist
+    /// when `cas` is 0.
+    pub fn cas(&mut self, key: &str, value: &[u8], flags: u64, cas: u64, index: u64) -> bool {
+        if !self.matches(key, cas) {
+            return false;
+        }
+        self.set(key, value, flags, index);
+        true
+    }
+
+    fn matches(&self, key: &str, cas: u64) -> bool {
+        match self.entries.get(key) {
+            Some(pair) => pair.ModifyIndex == Some(cas),
+            None => cas == 0,
+        }
+    }
+
+    pub fn delete(&mut self, key: &str, index: u64) {
+        if self.entries.remove(key).is_some() {
+            self.tombstones.insert(key.to_owned(), index);
+            self.index = index;
+        }
+    }
+
+    pub fn delete_cas(&mut self, key: &str, cas: u64, index: u64) -> bool {
+        match self.entries.get(key) {
+            Some(pair) if pair.ModifyIndex == Some(cas) => {
+                self.delete(key, index);
+                true
+            }
+            Some(_) => false,
+            // Deleting a missing key with a non-zero index fails, as in Consul.
+            None => cas == 0,
+        }
+    }
+
+    pub fn delete_tree(&mut self, prefix: &str, index: u64) {
+        let keys: Vec<String> = self.list(prefix).map(|p| p.Key.clone()).collect();
+        for key in keys {
+            self.delete(&key, index);
+        }
+    }
+
+    /// Takes the lock on `key` for `session`. Fails if another session holds it.
+    pub fn acquire(
+        &mut self,
+        key: &str,
+        value: &[u8],
+        flags: u64,
+        session: &str,
+        index: u64,
+    ) -> bool {
+        let holder = self.entries.get(key).and_then(|p| p.Session.clone());
+        match holder {
+            Some(ref holder) if holder != session => false,
+            _ => {
+                self.set(key, value, flags, index);
+                let pair = self.entries.get_mut(key).unwrap();
+                if holder.is_none() {
+                    pair.LockIndex = Some(pair.LockIndex.unwrap_or(0) + 1);
+                    pair.Session = Some(session.to_owned());
+                }
+                true
+            }
+        }
+    }
+
+    /// Gives up the lock on `key`, which only the holding session can do.
+    pub fn release(
+        &mut self,
+        key: &str,
+        value: &[u8],
+        flags: u64,
+        session: &str,
+        index: u64,
+    ) -> bool {
+        match self.entries.get(key) {
+            Some(pair) if pair.Session.as_deref() == Some(session) => {
+                self.set(key, value, flags, index);
+                self.entries.get_mut(key).unwrap().Session = None;
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// Applies an invalidated session's behavior to the keys it holds.
+    fn invalidate(&mut self, session: &str, delete: bool, index: u64) {
+        let held: Vec<String> = self
+            .entries
+            .values()
+            .filter(|p| p.Session.as_deref() == Some(session))
+            .map(|p| p.Key.clone())
+            .collect();
+        for key in held {
+            if delete {
+                self.delete(&key, index);
+            } else {
+                let pair = self.entries.get_mut(&key).unwrap();
+                pair.Session = None;
+                pair.ModifyIndex = Some(index);
+                self.index = index;
+            }
+        }
+    }
+}
+
+pub(super) struct Session {
+    pub entry: SessionEntry,
+    ttl: Option<Duration>,
+    expires: Option<Instant>,
+}
+
+impl Session {
+    pub fn new(entry: SessionEntry, ttl: Option<Duration>) -> Self {
+        let mut session = Session {
+            entry,
+            ttl,
+            expires: None,
+        };
+        session.renew();
+        session
+    }
+
+    /// Pushes the expiry out. Like Consul, a session lives for twice its TTL
+    /// after the last renewal.
+    pub fn renew(&mut self) {
+        self.expires = self.ttl.map(|ttl| Instant::now() + ttl * 2);
+    }
+}
+
+pub(super) struct Check {
+    pub check: AgentCheck,
+    ttl: Option<Duration>,
+    expires: Option<Instant>,
+}
+
+impl Check {
+    pub fn new(check: AgentCheck, ttl: Option<Duration>) -> Self {
+        let mut check = Check {
+            check,
+            ttl,
+            expires: None,
+        };
+        check.touch();
+        check
+    }
+
+    /// Restarts the TTL after a status update.
+    pub fn touch(&mut self) {
+        self.expires = self.ttl.map(|ttl| Instant::now() + ttl);
+    }
+}
+
+pub(super) struct Node {
+    pub id: String,
+    pub address: String,
+    pub tagged_addresses: HashMap<String, String>,
+    pub meta: HashMap<String, String>,
+    pub create_index: u64,
+    pub modify_index: u64,
+    pub services: BTreeMap<String, AgentService>,
+    pub checks: BTreeMap<String, Check>,
+}
+
+pub(super) struct State {
+    pub datacenter: String,
+    pub datacenters: Vec<String>,
+    /// The node the agent endpoints act on.
+    pub node: String,
+    pub servers: Vec<String>,
+    pub kv: Kv,
+    pub sessions: BTreeMap<String, Session>,
+    pub session_index: u64,
+    pub nodes: BTreeMap<String, Node>,
+    pub catalog_index: u64,
+    /// The most recent user events, oldest first.
+    pub events: VecDeque<UserEvent>,
+    pub event_ltime: u64,
+    index: u64,
+}
+
+impl State {
+    pub fn new(datacenter: &str, datacenters: &[String], node: &str, servers: usize) -> Self {
+        let mut state = State {
+            datacenter: datacenter.to_owned(),
+            datacenters: datacenters.to_vec(),
+            node: node.to_owned(),
+            servers: Vec::new(),
+            kv: Kv::default(),
+            sessions: BTreeMap::new(),
+            session_index: 0,
+            nodes: BTreeMap::new(),
+            catalog_index: 0,
+            events: VecDeque::new(),
+            event_ltime: 0,
+            index: 0,
+        };
+        for n in 0..servers.max(1) {
+            let name = if n == 0 {
+                node.to_owned()
+            } else {
+                format!("{}-{}", node, n)
+            };
+            state.add_server(&name);
+        }
+        state
+    }
+
+    fn add_server(&mut self, name: &str) {
+        let index = self.next_index();
+        self.upsert_node(name, "127.0.0.1", index);
+        let service = AgentService {
+            ID: CONSUL_SERVICE.to_owned(),
+            Service: CONSUL_SERVICE.to_owned(),
+            Tags: Some(Vec::new()),
+            Port: 8300,
+            Weights: Some(Weights {
+                Passing: 1,
+                Warning: 1,
+            }),
+            ..Default::default()
+        };
+        self.put_service(name, service, index);
+        self.servers.push(name.to_owned());
+    }
+
+    /// Advances the store's index for a write.
+    pub fn next_index(&mut self) -> u64 {
+        self.index += 1;
+        self.index
+    }
+
+    pub fn upsert_node(&mut self, name: &str, address: &str, index: u64) -> &mut Node {
+        let node = self.nodes.entry(name.to_owned()).or_insert_with(|| {
+            let mut checks = BTreeMap::new();
+            checks.insert(
+                SERF_CHECK_ID.to_owned(),
+                Check::new(
+                    AgentCheck {
+                        Node: name.to_owned(),
+                        CheckID: SERF_CHECK_ID.to_owned(),
+                        Name: String::from("Serf Health Status"),
+                        Status: PASSING.to_owned(),
+                        Output: String::from("Agent alive and reachable"),
+                        ..Default::default()
+                    },
+                    None,
+                ),
+            );
+            Node {
+                id: uuid(),
+                address: String::new(),
+                tagged_addresses: HashMap::new(),
+                meta: HashMap::new(),
+                create_index: index,
+                modify_index: index,
+                services: BTreeMap::new(),
+                checks,
+            }
+        });
+        if !address.is_empty() {
+            node.address = address.to_owned();
+        }
+        node.modify_index = index;
+        self.catalog_index = index;
+        self.nodes.get_mut(name).unwrap()
+    }
+
+    pub fn put_service(&mut self, node: &str, mut service: AgentService, index: u64) {
+        let node = self.nodes.get_mut(node).unwrap();
+        service.CreateIndex = node
+            .services
+            .get(&service.ID)
+            .map_or(index, |s| s.CreateIndex);
+        service.ModifyIndex = index;
+        node.services.insert(service.ID.clone(), service);
+        self.catalog_index = index;
+    }
+
+    /// Removes a service and the checks that belong to it.
+    pub fn remove_service(&mut self, node: &str, service_id: &str, index: u64) -> bool {
+        let node = match self.nodes.get_mut(node) {
+            Some(node) => node,
+            None => return false,
+        };
+        if node.services.remove(service_id).is_none() {
+            return false;
+        }
+        node.checks.retain(|_, c| c.check.ServiceID != service_id);
+        self.catalog_index = index;
+        true
+    }
+
+    pub fn put_check(&mut self, node: &str, check: Check, index: u64) {
+        let node = self.nodes.get_mut(node).unwrap();
+        node.checks.insert(check.check.CheckID.clone(), check);
+        self.catalog_index = index;
+    }
+
+    pub fn remove_check(&mut self, node: &str, check_id: &str, index: u64) -> bool {
+        let removed = self
+            .nodes
+            .get_mut(node)
+            .is_some_and(|n| n.checks.remove(check_id).is_some());
+        if removed {
+            self.catalog_index = index;
+        }
+        removed
+    }
+
+    /// Sets a check's status and output, as a TTL update does.
+    pub fn update_check(&mut self, node: &str, check_id: &str, status: &str, output: &str) -> bool {
+        let check = match self
+            .nodes
+            .get_mut(node)
+            .and_then(|n| n.checks.get_mut(check_id))
+        {
+            Some(check) => check,
+            None => return false,
+        };
+        check.touch();
+        let changed = check.check.Status != status || check.check.Output != output;
+        check.check.Status = status.to_owned();
+        check.check.Output = output.to_owned();
+        if changed {
+            self.catalog_index = self.next_index();
+        }
+        true
+    }
+
+    /// Destroys a session and releases or deletes the keys it holds.
+    pub fn invalidate_session(&mut self, id: &str) -> bool {
+        let session = match self.sessions.remove(id) {
+            Some(session) => session,
+            None => return false,
+        };
+        let index = self.next_index();
+        self.session_index = index;
+        let delete = session.entry.Behavior.as_deref() == Some("delete");
+        self.kv.invalidate(id, delete, index);
+        true
+    }
+
+    /// Expires sessions and TTL checks whose time has run out. Returns whether
+    /// anything changed.
+    pub fn sweep(&mut self, now: Instant) -> bool {
+        let expired: Vec<String> = self
+            .sessions
+            .iter()
+            .filter(|(_, s)| s.expires.is_some_and(|e| e <= now))
+            .map(|(id, _)| id.clone())
+            .collect();
+        let mut changed = !expired.is_empty();
+        for id in expired {
+            self.invalidate_session(&id);
+        }
+
+        let mut lapsed = Vec::new();
+        for (name, node) in &self.nodes {
+            for (id, check) in &node.checks {
+                if check.expires.is_some_and(|e| e <= now) && check.check.Status != CRITICAL {
+                    lapsed.push((name.clone(), id.clone()));
+                }
+            }
+        }
+        for (node, id) in lapsed {
+            let output = format!("TTL expired for check {}", id);
+            self.update_check(&node, &id, CRITICAL, &output);
+            changed = true;
+        }
+        changed
+    }
+}
+
+/// A random ID in the UUID format Consul uses for sessions and nodes.
+pub(super) fn uuid() -> String {
+    let bytes: [u8; 16] = rand::thread_rng().gen();
+    let hex: String = bytes.iter().map(|b| format!("{:02x}", b)).collect();
+    format!(
+        "{}-{}-{}-{}-{}",
+        &hex[..8],
+        &hex[8..12],
+        &hex[12..16],
+        &hex[16..20],
+        &hex[20..]
+    )
+}
+
+#[cfg(test)]
+mod state_tests {
+    use super::*;
+
+    fn state() -> State {
+        State::new("dc1", &[String::from("dc1")], "node", 1)
+    }
+
+    fn session(state: &mut State, behavior: &str, ttl: Option<Duration>) -> String {
+        let id = uuid();
+        let entry = SessionEntry {
+            ID: Some(id.clone()),
+            Behavior: Some(behavior.to_owned()),
+            ..Default::default()
+        };
+        state.sessions.insert(id.clone(), Session::new(entry, ttl));
+        id
+    }
+
+    #[test]
+    fn kv_cas_test() {
+        let mut kv = Kv::default();
+
+        assert!(kv.cas("key", b"first", 0, 0, 1));
+        assert!(!kv.cas("key", b"again", 0, 0, 2));
+        assert!(!kv.cas("key", b"stale", 0, 7, 3));
+        assert!(kv.cas("key", b"second", 0, 1, 4));
+
+        let pair = kv.get("key").unwrap();
+        assert_eq!(pair.Value, b"second");
+        assert_eq!(pair.CreateIndex, Some(1));
+        assert_eq!(pair.ModifyIndex, Some(4));
+    }
+
+    #[test]
+    fn kv_query_index_test() {
+        let mut kv = Kv::default();
+        kv.set("app/a", b"a", 0, 3);
+        kv.set("app/b", b"b", 0, 5);
+        kv.set("other", b"c", 0, 6);
+
+        assert_eq!(kv.query_index("app/a", false), 3);
+        assert_eq!(kv.query_index("app/", true), 5);
+
+        kv.delete("app/b", 7);
+        assert_eq!(kv.query_index("app/", true), 7);
+        assert_eq!(kv.query_index("app/b", false), 7);
+        assert_eq!(kv.query_index("missing", false), 7);
+    }
+
+    #[test]
+    fn kv_lock_test() {
+        let mut kv = Kv::default();
+
+        assert!(kv.acquire("lock", b"one", 0, "s1", 1));
+        assert!(!kv.acquire("lock", b"two", 0, "s2", 2));
+        assert!(kv.acquire("lock", b"one", 0, "s1", 3));
+        assert_eq!(kv.get("lock").unwrap().LockIndex, Some(1));
+
+        assert!(!kv.release("lock", b"", 0, "s2", 4));
+        assert!(kv.release("lock", b"", 0, "s1", 5));
+        assert!(kv.get("lock").unwrap().Session.is_none());
+        assert!(kv.acquire("lock", b"two", 0, "s2", 6));
+        assert_eq!(kv.get("lock").unwrap().LockIndex, Some(2));
+    }
+
+    #[test]
+    fn invalidate_session_test() {
+        let mut state = state();
+        let release = session(&mut state, "release", None);
+        let delete = session(&mut state, "delete", None);
+        state.kv.acquire("released", b"", 0, &release, 1);
+        state.kv.acquire("deleted", b"", 0, &delete, 2);
+
+        assert!(state.invalidate_session(&release));
+        assert!(state.invalidate_session(&delete));
+        assert!(!state.invalidate_session(&delete));
+
+        assert!(state.kv.get("released").unwrap().Session.is_none());
+        assert!(state.kv.get("deleted").is_none());
+    }
+
+    #[test]
+    fn sweep_test() {
+        let mut state = state();
+        let id = session(&mut state, "release", Some(Duration::from_secs(10)));
+        let check = AgentCheck {
+            CheckID: String::from("ttl"),
+            Status: PASSING.to_owned(),
+            ..Default::default()
+        };
+        state.put_check("node", Check::new(check, Some(Duration::from_secs(10))), 1);
+
+        assert!(!state.sweep(Instant::now()));
+        assert!(state.sweep(Instant::now() + Duration::from_secs(30)));
+
+        assert!(!state.sessions.contains_key(&id));
+        let check = &state.nodes["node"].checks["ttl"].check;
+        assert_eq!(check.Status, CRITICAL);
+        assert_eq!(check.Output, "TTL expired for check ttl");
+    }
+}
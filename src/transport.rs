@@ -0,0 +1,195 @@
+use std::fmt::Debug;
+use std::io::Read;
+use std::path::PathBuf;
+use std::time::{Duration, Instant};
+
+use native_tls::TlsConnector;
+use reqwest::blocking::Client as HttpClient;
+use reqwest::header::HeaderMap;
+use reqwest::{Method, StatusCode};
+use url::Url;
+
+use crate::config::HttpOptions;
+use crate::errors::{Error, ErrorKind, Result, ResultExt};
+use crate::request::{tls_socket, unix_socket};
+use crate::tls::TlsConfig;
+
+/// A request to Consul with its query parameters, token header and body
+/// already filled in.
+#[derive(Clone, Debug)]
+pub struct HttpRequest {
+    pub method: Method,
+    pub url: Url,
+    pub headers: HeaderMap,
+    pub body: Vec<u8>,
+    /// How long to wait for the response, overriding the transport's default.
+    /// Set for blocking queries, which may legitimately take minutes.
+    pub timeout: Option<Duration>,
+}
+
+#[derive(Clone, Debug)]
+pub struct HttpResponse {
+    pub status: StatusCode,
+    pub headers: HeaderMap,
+    pub body: Vec<u8>,
+}
+
+/// The error for a failed reqwest request to `url`. Failing to connect is
+/// reported as `ErrorKind::ConnectFailed`, after which the request can safely
+/// go to a fallback address.
+pub(crate) fn reqwest_error(err: reqwest::Error, url: &Url) -> Error {
+    if err.is_connect() {
+        Error::with_chain(err, ErrorKind::ConnectFailed(url.to_string()))
+    } else {
+        Error::with_chain(err, "HTTP request to consul failed")
+    }
+}
+
+/// Sends the requests of a `Client`.
+///
+/// By default a `Client` uses `ReqwestTransport`, `UnixSocketTransport` for a
+/// `unix://` address, or `TlsTransport` for an `https://` address with a TLS
+/// server name. Setting `Config::transport` replaces it, e.g. with a
+/// scripted transport in tests or with one that wraps the default to add
+/// logging, metrics or headers. Responses with an error status are returned as
+/// `Ok`; turning them into errors is left to the client. Failing to reach the
+/// agent at all should be reported as `ErrorKind::ConnectFailed`, which sends
+/// even writes on to the fallback addresses.
+pub trait Transport: Debug + Send + Sync {
+    fn send(&self, request: HttpRequest) -> Result<HttpResponse>;
+}
+
+#[derive(Clone, Debug)]
+pub struct ReqwestTransport {
+    client: HttpClient,
+    options: HttpOptions,
+}
+
+impl ReqwestTransport {
+    pub fn new(client: HttpClient) -> Self {
+        ReqwestTransport::with_options(client, HttpOptions::default())
+    }
+
+    /// `options` are the settings `client` was built with. Their request
+    /// timeout is kept for the whole request here, as reqwest applies its
+    /// timeout to every read of a response separately.
+    pub fn with_options(client: HttpClient, options: HttpOptions) -> Self {
+        ReqwestTransport { client, options }
+    }
+}
+
+impl Transport for ReqwestTransport {
+    fn send(&self, request: HttpRequest) -> Result<HttpResponse> {
+        let start = Instant::now();
+        let deadline = request.timeout.or(self.options.request_timeout);
+        let url = request.url.clone();
+        let mut builder = self
+            .client
+            .request(request.method, request.url)
+            .headers(request.headers);
+        if !request.body.is_empty() {
+            builder = builder.body(request.body);
+        }
+        if let Some(timeout) = request.timeout {
+            builder = builder.timeout(timeout);
+        }
+        let mut response = builder.send().map_err(|err| reqwest_error(err, &url))?;
+        let status = response.status();
+        let headers = response.headers().clone();
+        let mut body = Vec::new();
+        let mut buf = [0; 8192];
+        loop {
+            let n = response
+                .read(&mut buf)
+                .chain_err(|| "Failed to read response body")?;
+            if n == 0 {
+                break;
+            }
+            body.extend_from_slice(&buf[..n]);
+            if deadline.is_some_and(|deadline| start.elapsed() > deadline) {
+                return Err(Error::from("HTTP request to consul timed out"));
+            }
+        }
+        Ok(HttpResponse {
+            status,
+            headers,
+            body,
+        })
+    }
+}
+
+/// Talks HTTP/1.1 to an agent listening on a unix socket.
+#[derive(Clone, Debug)]
+pub struct UnixSocketTransport {
+    path: PathBuf,
+    options: HttpOptions,
+}
+
+impl UnixSocketTransport {
+    pub fn new<P: Into<PathBuf>>(path: P) -> Self {
+        UnixSocketTransport::with_options(path, HttpOptions::default())
+    }
+
+    /// Applies the timeouts and user agent of `options`; its proxy settings
+    /// do not apply to a unix socket.
+    pub fn with_options<P: Into<PathBuf>>(path: P, options: HttpOptions) -> Self {
+        UnixSocketTransport {
+            path: path.into(),
+            options,
+        }
+    }
+}
+
+impl Transport for UnixSocketTransport {
+    fn send(&self, request: HttpRequest) -> Result<HttpResponse> {
+        unix_socket::send(
+            &self.path,
+            &request.method,
+            &request.url,
+            &request.headers,
+            &request.body,
+            request.timeout,
+            &self.options,
+        )
+    }
+}
+
+/// Talks HTTPS to agents whose certificate is verified against
+/// `TlsConfig::server_name` rather than the host in their address.
+#[derive(Clone, Debug)]
+pub struct TlsTransport {
+    connector: TlsConnector,
+    server_name: String,
+    options: HttpOptions,
+}
+
+impl TlsTransport {
+    /// Fails when `tls` has no server name. Applies the timeouts and user
+    /// agent of `options`; its proxy settings are not supported and ignored.
+    pub fn new(tls: &TlsConfig, options: HttpOptions) -> Result<Self> {
+        let server_name = tls
+            .server_name
+            .clone()
+            .ok_or_else(|| Error::from("A TLS server name is required"))?;
+        let connector = match tls.connector()? {
+            Some(connector) => connector,
+            None => TlsConnector::new().chain_err(|| "Failed to build TLS connector")?,
+        };
+        Ok(TlsTransport {
+            connector,
+            server_name,
+            options,
+        })
+    }
+
+    #[cfg(feature = "async")]
+    pub(crate) async fn send_async(&self, request: HttpRequest) -> Result<HttpResponse> {
+        tls_socket::send_async(&self.connector, &self.server_name, &request, &self.options).await
+    }
+}
+
+impl Transport for TlsTransport {
+    fn send(&self, request: HttpRequest) -> Result<HttpResponse> {
+        tls_socket::send(&self.connector, &self.server_name, &request, &self.options)
+    }
+}
@@ -0,0 +1,275 @@
+use std::collections::HashMap;
+use std::time::Instant;
+
+#[cfg(feature = "async")]
+use async_trait::async_trait;
+
+use serde::{Serialize, Serializer};
+
+use crate::errors::{Error, ErrorKind, Result};
+use crate::kv::KVPair;
+#[cfg(feature = "async")]
+use crate::request::async_requests;
+use crate::request::put_requests::put;
+#[cfg(feature = "async")]
+use crate::AsyncClient;
+use crate::{Client, WriteMeta, WriteOptions};
+
+/// A single KV operation of a transaction.
+///
+/// Operations that need an index (`Cas`, `CheckIndex`) read it from
+/// `KVPair::ModifyIndex`, and operations that need a session (`Lock`, `Unlock`,
+/// `CheckSession`) read it from `KVPair::Session`.
+#[derive(Clone, Eq, PartialEq, Debug)]
+pub enum KVTxnOp {
+    Set(KVPair),
+    Cas(KVPair),
+    Get(String),
+    CheckIndex(KVPair),
+    CheckSession(KVPair),
+    Lock(KVPair),
+    Unlock(KVPair),
+    Delete(String),
+    DeleteTree(String),
+}
+
+#[derive(Default, Serialize)]
+struct KVTxnOpBody<'a> {
+    Verb: &'static str,
+    Key: &'a str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    Value: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    Flags: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    Index: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    Session: Option<&'a str>,
+}
+
+#[derive(Serialize)]
+struct TxnOpBody<'a> {
+    KV: KVTxnOpBody<'a>,
+}
+
+impl<'a> KVTxnOpBody<'a> {
+    fn with_value(verb: &'static str, pair: &'a KVPair) -> Self {
+        KVTxnOpBody {
+            Verb: verb,
+            Key: &pair.Key,
+            Value: Some(base64::encode(&pair.Value)),
+            Flags: pair.Flags,
+            ..Default::default()
+        }
+    }
+
+    fn key_only(verb: &'static str, key: &'a str) -> Self {
+        KVTxnOpBody {
+            Verb: verb,
+            Key: key,
+            ..Default::default()
+        }
+    }
+}
+
+impl KVTxnOp {
+    fn body(&self) -> KVTxnOpBody<'_> {
+        use KVTxnOpBody as Body;
+
+        match self {
+            KVTxnOp::Set(pair) => Body::with_value("set", pair),
+            KVTxnOp::Cas(pair) => Body {
+                Index: Some(pair.ModifyIndex.unwrap_or(0)),
+                ..Body::with_value("cas", pair)
+            },
+            KVTxnOp::Get(key) => Body::key_only("get", key),
+            KVTxnOp::CheckIndex(pair) => Body {
+                Index: Some(pair.ModifyIndex.unwrap_or(0)),
+                ..Body::key_only("check-index", &pair.Key)
+            },
+            KVTxnOp::CheckSession(pair) => Body {
+                Session: pair.Session.as_deref(),
+                ..Body::key_only("check-session", &pair.Key)
+            },
+            KVTxnOp::Lock(pair) => Body {
+                Session: pair.Session.as_deref(),
+                ..Body::with_value("lock", pair)
+            },
+            KVTxnOp::Unlock(pair) => Body {
+                Session: pair.Session.as_deref(),
+                ..Body::with_value("unlock", pair)
+            },
+            KVTxnOp::Delete(key) => Body::key_only("delete", key),
+            KVTxnOp::DeleteTree(key) => Body::key_only("delete-tree", key),
+        }
+    }
+}
+
+impl Serialize for KVTxnOp {
+    fn serialize<S>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        TxnOpBody { KV: self.body() }.serialize(serializer)
+    }
+}
+
+#[derive(Clone, Default, Eq, PartialEq, Serialize, Deserialize, Debug)]
+#[serde(default)]
+pub struct TxnResult {
+    pub KV: Option<KVPair>,
+}
+
+#[derive(Clone, Default, Eq, PartialEq, Serialize, Deserialize, Debug)]
+#[serde(default)]
+pub struct TxnError {
+    pub OpIndex: usize,
+    pub What: String,
+}
+
+/// The outcome of a transaction. When it was committed `Results` holds one entry
+/// per operation; when it was rolled back `Errors` says which operations failed.
+#[derive(Clone, Default, Eq, PartialEq, Serialize, Deserialize, Debug)]
+#[serde(default)]
+pub struct TxnResponse {
+    pub Results: Option<Vec<TxnResult>>,
+    pub Errors: Option<Vec<TxnError>>,
+}
+
+impl TxnResponse {
+    pub fn is_committed(&self) -> bool {
+        self.Errors.as_ref().is_none_or(|e| e.is_empty())
+    }
+}
+
+/// https://www.consul.io/api-docs/txn
+///
+/// Consul accepts at most 64 operations per transaction.
+pub trait Txn {
+    fn txn(&self, ops: &[KVTxnOp], o: Option<&WriteOptions>) -> Result<(TxnResponse, WriteMeta)>;
+}
+
+impl Txn for Client {
+    fn txn(&self, ops: &[KVTxnOp], o: Option<&WriteOptions>) -> Result<(TxnResponse, WriteMeta)> {
+        let start = Instant::now();
+        put("/v1/txn", Some(&ops), &self.config, HashMap::new(), o)
+            .or_else(|e| rolled_back_response(e, start))
+    }
+}
+
+#[cfg(feature = "async")]
+#[async_trait]
+pub trait AsyncTxn {
+    async fn txn(
+        &self,
+        ops: &[KVTxnOp],
+        o: Option<&WriteOptions>,
+    ) -> Result<(TxnResponse, WriteMeta)>;
+}
+
+#[cfg(feature = "async")]
+#[async_trait]
+impl AsyncTxn for AsyncClient {
+    async fn txn(
+        &self,
+        ops: &[KVTxnOp],
+        o: Option<&WriteOptions>,
+    ) -> Result<(TxnResponse, WriteMeta)> {
+        let start = Instant::now();
+        async_requests::put("/v1/txn", Some(&ops), &self.config, HashMap::new(), o)
+            .await
+            .or_else(|e| rolled_back_response(e, start))
+    }
+}
+
+// A rolled back transaction is answered with a 409 whose body lists the
+// per-operation errors, so that is not a failure of the request itself.
+fn rolled_back_response(error: Error, start: Instant) -> Result<(TxnResponse, WriteMeta)> {
+    if let ErrorKind::ConsulError(409, body, _) = error.kind() {
+        if let Ok(response) = serde_json::from_str(body) {
+            return Ok((
+                response,
+                WriteMeta {
+                    request_time: Instant::now() - start,
+                },
+            ));
+        }
+    }
+    Err(error)
+}
+
+#[cfg(test)]
+mod txn_tests {
+    use super::*;
+
+    #[test]
+    fn serialize_ops_test() {
+        let ops = vec![
+            KVTxnOp::Set(KVPair {
+                Key: String::from("flags/enabled"),
+                Value: b"true".to_vec(),
+                Flags: Some(3),
+                ..Default::default()
+            }),
+            KVTxnOp::Cas(KVPair {
+                Key: String::from("flags/rollout"),
+                Value: b"50".to_vec(),
+                ModifyIndex: Some(42),
+                ..Default::default()
+            }),
+            KVTxnOp::CheckSession(KVPair {
+                Key: String::from("flags/lock"),
+                Session: Some(String::from("session-id")),
+                ..Default::default()
+            }),
+            KVTxnOp::DeleteTree(String::from("flags/old")),
+        ];
+
+        let json = serde_json::to_value(&ops).unwrap();
+
+        assert_eq!(
+            json,
+            serde_json::json!([
+                {"KV": {"Verb": "set", "Key": "flags/enabled", "Value": "dHJ1ZQ==", "Flags": 3}},
+                {"KV": {"Verb": "cas", "Key": "flags/rollout", "Value": "NTA=", "Index": 42}},
+                {"KV": {"Verb": "check-session", "Key": "flags/lock", "Session": "session-id"}},
+                {"KV": {"Verb": "delete-tree", "Key": "flags/old"}},
+            ])
+        );
+    }
+
+    #[test]
+    fn rolled_back_response_test() {
+        let body = r#"{"Results": null, "Errors": [{"OpIndex": 1, "What": "failed to set key"}]}"#;
+        let error = Error::from(ErrorKind::ConsulError(
+            409,
+            body.to_owned(),
+            String::from("/v1/txn"),
+        ));
+
+        let (response, _) = rolled_back_response(error, Instant::now()).unwrap();
+
+        assert!(!response.is_committed());
+        assert!(response.Results.is_none());
+        assert_eq!(
+            response.Errors.unwrap(),
+            vec![TxnError {
+                OpIndex: 1,
+                What: String::from("failed to set key"),
+            }]
+        );
+    }
+
+    #[test]
+    fn rolled_back_response_passes_other_errors_test() {
+        let error = Error::from(ErrorKind::ConsulError(
+            403,
+            String::from("Permission denied"),
+            String::from("/v1/txn"),
+        ));
+
+        let error = rolled_back_response(error, Instant::now()).unwrap_err();
+
+        assert!(error.kind().is_permission_denied());
+    }
+}
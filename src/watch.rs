@@ -0,0 +1,236 @@
+use std::thread;
+use std::time::Duration;
+
+use rand::{thread_rng, Rng};
+
+use crate::errors::{Error, Result};
+use crate::{QueryMeta, QueryOptions};
+
+pub(crate) const DEFAULT_MIN_BACKOFF: Duration = Duration::from_secs(1);
+pub(crate) const DEFAULT_MAX_BACKOFF: Duration = Duration::from_secs(60);
+
+type Query<'a, T> = Box<dyn FnMut(Option<&QueryOptions>) -> Result<(T, QueryMeta)> + 'a>;
+
+/// Repeats a blocking query and yields its value each time the index changes.
+///
+/// `query` is called with the watch's `QueryOptions` and `wait_index` set to the
+/// last index seen, so it works with any blocking read, for example
+/// `Watch::new(|o| client.get("config/app", o), None)` for a KV key or
+/// `Watch::new(|o| client.service("web", None, true, o), None)` for health.
+///
+/// Following https://www.consul.io/api-docs/features/blocking, the index is reset
+/// when it goes backwards and is never sent as 0. After an error the iterator
+/// yields the error and waits with exponential backoff and jitter before the
+/// next query.
+pub struct Watch<'a, T> {
+    query: Query<'a, T>,
+    options: QueryOptions,
+    last_index: Option<u64>,
+    failures: u32,
+    min_backoff: Duration,
+    max_backoff: Duration,
+}
+
+impl<'a, T> Watch<'a, T> {
+    pub fn new<F>(query: F, options: Option<QueryOptions>) -> Self
+    where
+        F: FnMut(Option<&QueryOptions>) -> Result<(T, QueryMeta)> + 'a,
+    {
+        Watch {
+            query: Box::new(query),
+            options: options.unwrap_or_default(),
+            last_index: None,
+            failures: 0,
+            min_backoff: DEFAULT_MIN_BACKOFF,
+            max_backoff: DEFAULT_MAX_BACKOFF,
+        }
+    }
+
+    /// Sets the backoff after the first error and the limit it doubles up to.
+    pub fn with_backoff(mut self, min: Duration, max: Duration) -> Self {
+        self.min_backoff = min;
+        self.max_backoff = max;
+        self
+    }
+
+    /// The index of the last value yielded, if any.
+    pub fn last_index(&self) -> Option<u64> {
+        self.last_index
+    }
+
+    fn backoff(&self) -> Duration {
+        backoff(self.failures, self.min_backoff, self.max_backoff)
+    }
+}
+
+/// The wait after `failures` consecutive failures: `min` doubled for each
+/// failure after the first up to `max`, less up to half of it as jitter.
+pub(crate) fn backoff(failures: u32, min: Duration, max: Duration) -> Duration {
+    let exponent = failures.saturating_sub(1).min(16);
+    let backoff = (min * 2u32.pow(exponent)).min(max);
+    let half = backoff / 2;
+    let jitter_ms = thread_rng().gen_range(0, half.as_millis() as u64 + 1);
+    half + Duration::from_millis(jitter_ms)
+}
+
+impl<'a, T> Iterator for Watch<'a, T> {
+    type Item = Result<T>;
+
+    fn next(&mut self) -> Option<Result<T>> {
+        loop {
+            if self.failures > 0 {
+                thread::sleep(self.backoff());
+            }
+
+            let mut options = self.options.clone();
+            options.wait_index = self.last_index;
+
+            let (value, meta) = match (self.query)(Some(&options)) {
+                Ok(result) => result,
+                Err(e) => {
+                    self.failures += 1;
+                    return Some(Err(e));
+                }
+            };
+
+            let index = match meta.last_index {
+                Some(index) => index.max(1),
+                None => {
+                    self.failures += 1;
+                    return Some(Err(Error::from(
+                        "Watched endpoint did not return an X-Consul-Index",
+                    )));
+                }
+            };
+            self.failures = 0;
+
+            // A lower index means the state was reset (e.g. a snapshot restore), so the
+            // value is treated as new rather than waiting for the old index to come back.
+            if self.last_index != Some(index) {
+                self.last_index = Some(index);
+                return Some(Ok(value));
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod watch_tests {
+    use super::*;
+
+    use std::cell::RefCell;
+    use std::collections::VecDeque;
+
+    fn meta(index: Option<u64>) -> QueryMeta {
+        QueryMeta {
+            last_index: index,
+            request_time: Duration::from_millis(1),
+            ..Default::default()
+        }
+    }
+
+    fn scripted<'a>(
+        responses: Vec<Result<(&'static str, QueryMeta)>>,
+        seen_indexes: &'a RefCell<Vec<Option<u64>>>,
+    ) -> Watch<'a, &'static str> {
+        let mut responses: VecDeque<_> = responses.into();
+        Watch::new(
+            move |o: Option<&QueryOptions>| {
+                seen_indexes.borrow_mut().push(o.unwrap().wait_index);
+                responses.pop_front().unwrap()
+            },
+            None,
+        )
+        .with_backoff(Duration::from_millis(1), Duration::from_millis(2))
+    }
+
+    #[test]
+    fn yields_only_on_index_change_test() {
+        let seen_indexes = RefCell::new(Vec::new());
+        let mut watch = scripted(
+            vec![
+                Ok(("first", meta(Some(10)))),
+                Ok(("first", meta(Some(10)))),
+                Ok(("second", meta(Some(12)))),
+            ],
+            &seen_indexes,
+        );
+
+        assert_eq!(watch.next().unwrap().unwrap(), "first");
+        assert_eq!(watch.next().unwrap().unwrap(), "second");
+        assert_eq!(watch.last_index(), Some(12));
+        assert_eq!(*seen_indexes.borrow(), vec![None, Some(10), Some(10)]);
+    }
+
+    #[test]
+    fn resets_when_index_goes_backwards_test() {
+        let seen_indexes = RefCell::new(Vec::new());
+        let mut watch = scripted(
+            vec![
+                Ok(("before restore", meta(Some(100)))),
+                Ok(("after restore", meta(Some(5)))),
+                Ok(("after restore", meta(Some(5)))),
+                Ok(("updated", meta(Some(6)))),
+            ],
+            &seen_indexes,
+        );
+
+        assert_eq!(watch.next().unwrap().unwrap(), "before restore");
+        assert_eq!(watch.next().unwrap().unwrap(), "after restore");
+        assert_eq!(watch.next().unwrap().unwrap(), "updated");
+        assert_eq!(
+            *seen_indexes.borrow(),
+            vec![None, Some(100), Some(5), Some(5)]
+        );
+    }
+
+    #[test]
+    fn never_waits_on_index_zero_test() {
+        let seen_indexes = RefCell::new(Vec::new());
+        let mut watch = scripted(
+            vec![Ok(("empty", meta(Some(0)))), Ok(("value", meta(Some(3))))],
+            &seen_indexes,
+        );
+
+        assert_eq!(watch.next().unwrap().unwrap(), "empty");
+        assert_eq!(watch.next().unwrap().unwrap(), "value");
+        assert_eq!(*seen_indexes.borrow(), vec![None, Some(1)]);
+    }
+
+    #[test]
+    fn yields_errors_and_recovers_test() {
+        let seen_indexes = RefCell::new(Vec::new());
+        let mut watch = scripted(
+            vec![
+                Ok(("first", meta(Some(7)))),
+                Err(Error::from("connection refused")),
+                Ok(("first", meta(None))),
+                Ok(("second", meta(Some(8)))),
+            ],
+            &seen_indexes,
+        );
+
+        assert_eq!(watch.next().unwrap().unwrap(), "first");
+        assert!(watch.next().unwrap().is_err());
+        assert!(watch.next().unwrap().is_err());
+        assert_eq!(watch.next().unwrap().unwrap(), "second");
+        assert_eq!(
+            *seen_indexes.borrow(),
+            vec![None, Some(7), Some(7), Some(7)]
+        );
+    }
+
+    #[test]
+    fn backoff_is_bounded_test() {
+        let mut watch = Watch::new(|_: Option<&QueryOptions>| Ok(((), meta(Some(1)))), None)
+            .with_backoff(Duration::from_millis(100), Duration::from_millis(400));
+
+        watch.failures = 1;
+        let first = watch.backoff();
+        assert!(first >= Duration::from_millis(50) && first <= Duration::from_millis(100));
+
+        watch.failures = 10;
+        let capped = watch.backoff();
+        assert!(capped >= Duration::from_millis(200) && capped <= Duration::from_millis(400));
+    }
+}
@@ -0,0 +1,133 @@
+extern crate consul;
+use consul::acl::{ACLLoginParams, ACLPolicy, ACLToken, ACL};
+use consul::errors::Result;
+use consul::transport::{HttpRequest, HttpResponse, Transport};
+use consul::{Client, Config};
+
+use reqwest::header::HeaderMap;
+use reqwest::{Method, StatusCode};
+
+use std::collections::VecDeque;
+use std::sync::{Arc, Mutex};
+
+/// Answers with the given bodies, in order, and records the requests.
+#[derive(Debug)]
+struct Recorder {
+    bodies: Mutex<VecDeque<&'static str>>,
+    requests: Arc<Mutex<Vec<HttpRequest>>>,
+}
+
+impl Transport for Recorder {
+    fn send(&self, request: HttpRequest) -> Result<HttpResponse> {
+        self.requests.lock().unwrap().push(request);
+        Ok(HttpResponse {
+            status: StatusCode::OK,
+            headers: HeaderMap::new(),
+            body: self
+                .bodies
+                .lock()
+                .unwrap()
+                .pop_front()
+                .expect("no response left for request")
+                .as_bytes()
+                .to_vec(),
+        })
+    }
+}
+
+fn client(bodies: &[&'static str]) -> (Client, Arc<Mutex<Vec<HttpRequest>>>) {
+    let requests = Arc::new(Mutex::new(Vec::new()));
+    let mut config = Config::new().unwrap().with_transport(Recorder {
+        bodies: Mutex::new(bodies.iter().copied().collect()),
+        requests: Arc::clone(&requests),
+    });
+    config.token = Some(String::from("management"));
+    (Client::new(config), requests)
+}
+
+#[test]
+fn token_create_test() {
+    let (client, requests) =
+        client(&[r#"{"AccessorID":"a1","SecretID":"s1","Description":"billing"}"#]);
+    let token = ACLToken {
+        Description: String::from("billing"),
+        ..Default::default()
+    };
+
+    let (created, _) = client.token_create(&token, None).unwrap();
+
+    assert_eq!(created.AccessorID.as_deref(), Some("a1"));
+    assert_eq!(created.SecretID.as_deref(), Some("s1"));
+    let requests = requests.lock().unwrap();
+    assert_eq!(requests[0].method, Method::PUT);
+    assert_eq!(requests[0].url.path(), "/v1/acl/token");
+    assert_eq!(requests[0].headers["X-Consul-Token"], "management");
+}
+
+#[test]
+fn token_clone_and_update_test() {
+    let (client, requests) = client(&[r#"{"AccessorID":"a2"}"#, r#"{"AccessorID":"a2"}"#]);
+
+    client
+        .token_clone("a1", Some("billing copy"), None)
+        .unwrap();
+    let token = ACLToken {
+        AccessorID: Some(String::from("a2")),
+        ..Default::default()
+    };
+    client.token_update(&token, None).unwrap();
+
+    let requests = requests.lock().unwrap();
+    assert_eq!(requests[0].url.path(), "/v1/acl/token/a1/clone");
+    assert_eq!(requests[0].body, br#"{"Description":"billing copy"}"#);
+    assert_eq!(requests[1].url.path(), "/v1/acl/token/a2");
+    assert!(client.token_update(&ACLToken::default(), None).is_err());
+}
+
+#[test]
+fn policy_read_by_name_test() {
+    let (client, requests) = client(&[
+        r#"{"ID":"p1","Name":"read-kv","Rules":"key_prefix \"\" { policy = \"read\" }"}"#,
+    ]);
+
+    let (policy, _): (ACLPolicy, _) = client.policy_read_by_name("read-kv", None).unwrap();
+
+    assert_eq!(policy.ID.as_deref(), Some("p1"));
+    assert!(policy.Rules.starts_with("key_prefix"));
+    let requests = requests.lock().unwrap();
+    assert_eq!(requests[0].method, Method::GET);
+    assert_eq!(requests[0].url.path(), "/v1/acl/policy/name/read-kv");
+}
+
+#[test]
+fn binding_rule_list_test() {
+    let (client, requests) = client(&["[]"]);
+
+    let (rules, _) = client.binding_rule_list(Some("kubernetes"), None).unwrap();
+
+    assert!(rules.is_empty());
+    let requests = requests.lock().unwrap();
+    assert_eq!(requests[0].url.path(), "/v1/acl/binding-rules");
+    assert_eq!(requests[0].url.query(), Some("authmethod=kubernetes"));
+}
+
+#[test]
+fn login_logout_test() {
+    let (client, requests) = client(&[r#"{"AccessorID":"a3","SecretID":"login-secret"}"#, ""]);
+    let params = ACLLoginParams {
+        AuthMethod: String::from("kubernetes"),
+        BearerToken: String::from("jwt"),
+        ..Default::default()
+    };
+
+    let (token, _) = client.login(&params, None).unwrap();
+    client.logout(&token.SecretID.unwrap(), None).unwrap();
+
+    let requests = requests.lock().unwrap();
+    assert_eq!(requests[0].method, Method::POST);
+    assert_eq!(requests[0].url.path(), "/v1/acl/login");
+    assert_eq!(requests[0].headers["X-Consul-Token"], "management");
+    assert_eq!(requests[1].method, Method::POST);
+    assert_eq!(requests[1].url.path(), "/v1/acl/logout");
+    assert_eq!(requests[1].headers["X-Consul-Token"], "login-secret");
+}
@@ -1,7 +1,13 @@
+mod common;
+
 extern crate consul;
-use consul::agent::Agent;
+use consul::agent::{Agent, AgentServiceCheck, AgentServiceRegistration};
+
+use consul::Client;
 
-use consul::{Client, Config};
+extern crate rand;
+use rand::distributions::Alphanumeric;
+use rand::{thread_rng, Rng};
 
 #[test]
 fn agent_checks_test() {
@@ -26,8 +32,47 @@ fn agent_reload_test() {
     client.reload();
 }
 
+#[test]
+fn agent_service_register_test() {
+    let client = set_up();
+
+    let service_id: String = thread_rng().sample_iter(&Alphanumeric).take(16).collect();
+    let check_id = format!("service:{}", service_id);
+    let registration = AgentServiceRegistration {
+        ID: Some(service_id.clone()),
+        Name: String::from("agent-test-service"),
+        Port: Some(8080),
+        Check: Some(AgentServiceCheck {
+            CheckID: Some(check_id.clone()),
+            TTL: Some(String::from("30s")),
+            ..Default::default()
+        }),
+        ..Default::default()
+    };
+
+    client.service_register(&registration).unwrap();
+
+    let services = client.services().unwrap();
+    assert!(services.contains_key(&service_id));
+
+    let service = client.service_by_id(&service_id).unwrap();
+    assert_eq!(service.Service, "agent-test-service");
+    assert_eq!(service.Port, 8080);
+
+    client.check_pass(&check_id, Some("ok")).unwrap();
+    assert_eq!(client.checks().unwrap()[&check_id].Status, "passing");
+
+    client
+        .check_update(&check_id, "critical", Some("down"))
+        .unwrap();
+    assert_eq!(client.checks().unwrap()[&check_id].Status, "critical");
+
+    client.service_deregister(&service_id).unwrap();
+    assert!(!client.services().unwrap().contains_key(&service_id));
+}
+
 fn set_up() -> Client {
-    let config = Config::new().unwrap();
+    let config = common::config();
     let client = Client::new(config);
 
     client
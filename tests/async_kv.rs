@@ -0,0 +1,38 @@
+#![cfg(feature = "async")]
+
+mod common;
+
+extern crate consul;
+use consul::kv::{AsyncKV, KVPair};
+use consul::AsyncClient;
+
+extern crate rand;
+use rand::distributions::Alphanumeric;
+use rand::{thread_rng, Rng};
+
+#[tokio::test]
+async fn async_kv_put_get_delete_test() {
+    let config = common::config();
+    let client = AsyncClient::new(config);
+
+    let unique_test_path: String = thread_rng().sample_iter(&Alphanumeric).take(16).collect();
+    let key = format!("{}/asynckey", unique_test_path);
+
+    let kv_pair = KVPair {
+        Key: key.clone(),
+        Value: b"asyncvalue".to_vec(),
+        ..Default::default()
+    };
+    assert!(client.put(&kv_pair, None).await.unwrap().0);
+
+    let (stored, _) = client.get(&key, None).await.unwrap();
+    assert_eq!(stored.unwrap().value_str().unwrap(), "asyncvalue");
+
+    let (listed, _) = client.list(&unique_test_path, None).await.unwrap();
+    assert_eq!(listed.len(), 1);
+
+    assert!(client.delete(&key, None).await.unwrap().0);
+
+    let (listed, _) = client.list(&unique_test_path, None).await.unwrap();
+    assert!(listed.is_empty());
+}
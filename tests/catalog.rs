@@ -1,10 +1,12 @@
+mod common;
+
 extern crate consul;
-use consul::{Client, Config};
+use consul::Client;
 
 #[test]
 fn ds_test() {
     use consul::catalog::Catalog;
-    let config = Config::new_from_env().unwrap();
+    let config = common::config();
     let client = Client::new(config);
     let r = client.datacenters().unwrap();
     assert_eq!(r.0, ["alpha", "beta"]);
@@ -13,7 +15,7 @@ fn ds_test() {
 #[test]
 fn ds_services_test() {
     use consul::catalog::Catalog;
-    let config = Config::new().unwrap();
+    let config = common::config();
     let client = Client::new(config);
     let r = client.services(Option::None).unwrap();
     assert_ne!(r.0.len(), 0);
@@ -22,3 +24,81 @@ fn ds_services_test() {
         Some(val) => assert_eq!(val.len(), 0), // consul has no tags
     }
 }
+
+fn registration(node: &str) -> consul::catalog::CatalogRegistration {
+    use consul::agent::{AgentService, AgentServiceConnectProxyConfig};
+    use consul::catalog::CatalogRegistration;
+    use std::collections::HashMap;
+
+    let mut meta = HashMap::new();
+    meta.insert(String::from("rack"), node.to_owned());
+    CatalogRegistration {
+        Node: node.to_owned(),
+        Address: String::from("10.1.0.7"),
+        NodeMeta: meta,
+        Service: Some(AgentService {
+            ID: format!("{}-proxy", node),
+            Service: format!("{}-proxy", node),
+            Kind: Some(String::from("connect-proxy")),
+            Tags: Some(vec![String::from("v2")]),
+            Port: 21000,
+            Proxy: Some(AgentServiceConnectProxyConfig {
+                DestinationServiceName: Some(format!("{}-web", node)),
+                ..Default::default()
+            }),
+            ..Default::default()
+        }),
+        ..Default::default()
+    }
+}
+
+#[test]
+fn register_and_read_test() {
+    use consul::catalog::{Catalog, CatalogDeregistration};
+    use consul::QueryOptions;
+    let client = Client::new(common::config());
+    let node = "catalog-register-test";
+    let proxy = format!("{}-proxy", node);
+
+    let (registered, _) = client.register(&registration(node), None).unwrap();
+    assert!(registered);
+
+    let (instances, _) = client.service(&proxy, Some("v2"), None).unwrap();
+    assert_eq!(instances.len(), 1);
+    assert_eq!(instances[0].Node, node);
+    assert_eq!(instances[0].Address, "10.1.0.7");
+    assert_eq!(instances[0].ServicePort, 21000);
+    assert_eq!(instances[0].ServiceKind, "connect-proxy");
+    let (untagged, _) = client.service(&proxy, Some("v1"), None).unwrap();
+    assert!(untagged.is_empty());
+
+    let mut other_rack = QueryOptions::default();
+    other_rack
+        .node_meta
+        .insert(String::from("rack"), String::from("elsewhere"));
+    let (filtered, _) = client.service(&proxy, None, Some(&other_rack)).unwrap();
+    assert!(filtered.is_empty());
+
+    let (proxies, _) = client.connect(&format!("{}-web", node), None).unwrap();
+    assert_eq!(proxies.len(), 1);
+    assert_eq!(proxies[0].ServiceID, proxy);
+
+    let (catalog_node, _) = client.node(node, None).unwrap();
+    let catalog_node = catalog_node.unwrap();
+    assert_eq!(catalog_node.Node.unwrap().Meta["rack"], node);
+    assert!(catalog_node.Services.contains_key(&proxy));
+    let (listed, _) = client.node_services(node, None).unwrap();
+    assert_eq!(listed.unwrap().Services[0].ID, proxy);
+
+    let (gateway, _) = client.gateway_services("no-such-gateway", None).unwrap();
+    assert!(gateway.is_empty());
+
+    let deregistration = CatalogDeregistration {
+        Node: node.to_owned(),
+        ..Default::default()
+    };
+    let (deregistered, _) = client.deregister(&deregistration, None).unwrap();
+    assert!(deregistered);
+    let (missing, _) = client.node(node, None).unwrap();
+    assert!(missing.is_none());
+}
@@ -0,0 +1,29 @@
+use consul::Config;
+
+/// The agent the integration tests run against: the docker-compose cluster
+/// (or any agent named by `CONSUL_HTTP_ADDR`), or with the `testing` feature
+/// an in-memory server laid out like it.
+#[cfg(not(feature = "testing"))]
+pub fn config() -> Config {
+    Config::new_from_env().unwrap()
+}
+
+#[cfg(feature = "testing")]
+pub fn config() -> Config {
+    use consul::testing::{TestServer, TestServerOptions};
+    use std::sync::OnceLock;
+
+    static SERVER: OnceLock<TestServer> = OnceLock::new();
+    SERVER
+        .get_or_init(|| {
+            TestServer::start_with(TestServerOptions {
+                datacenter: String::from("alpha"),
+                datacenters: vec![String::from("alpha"), String::from("beta")],
+                node: String::from("consul-server-alpha"),
+                servers: 3,
+            })
+            .unwrap()
+        })
+        .config()
+        .unwrap()
+}
@@ -0,0 +1,129 @@
+#![cfg(feature = "discover")]
+
+mod common;
+
+extern crate consul;
+use consul::agent::AgentService;
+use consul::catalog::{AsyncCatalog, CatalogDeregistration, CatalogRegistration};
+use consul::discover::{ConsulDiscover, ConsulResolver};
+use consul::resolver::Instance;
+use consul::{AsyncClient, QueryOptions};
+
+use std::collections::HashSet;
+use std::future::poll_fn;
+use std::net::{IpAddr, SocketAddr};
+use std::pin::Pin;
+use std::time::Duration;
+
+use hyper::client::HttpConnector;
+use hyper::service::Service;
+use tower_discover::{Change, Discover};
+
+async fn register(client: &AsyncClient, node: &str, address: &str, service: &str, port: u16) {
+    let registration = CatalogRegistration {
+        Node: node.to_owned(),
+        Address: address.to_owned(),
+        Service: Some(AgentService {
+            ID: service.to_owned(),
+            Service: service.to_owned(),
+            Port: port,
+            ..Default::default()
+        }),
+        ..Default::default()
+    };
+    client.register(&registration, None).await.unwrap();
+}
+
+async fn deregister(client: &AsyncClient, node: &str) {
+    let deregistration = CatalogDeregistration {
+        Node: node.to_owned(),
+        ..Default::default()
+    };
+    client.deregister(&deregistration, None).await.unwrap();
+}
+
+async fn next_change<D: Discover + Unpin>(discover: &mut D) -> Change<D::Key, D::Service>
+where
+    D::Error: std::fmt::Debug,
+{
+    let change = poll_fn(|cx| Pin::new(&mut *discover).poll_discover(cx));
+    tokio::time::timeout(Duration::from_secs(10), change)
+        .await
+        .unwrap()
+        .unwrap()
+}
+
+#[tokio::test]
+async fn discover_test() {
+    let client = AsyncClient::new(common::config());
+    let service = "discover-test-web";
+    register(&client, "discover-test-a", "10.3.0.1", service, 9090).await;
+    register(&client, "discover-test-b", "10.3.0.2", service, 9090).await;
+
+    let mut discover = ConsulDiscover::new(client.clone(), service, |i: &Instance| i.node.clone())
+        .with_query_options(QueryOptions {
+            wait_time: Some(Duration::from_secs(1)),
+            ..Default::default()
+        });
+
+    let a = SocketAddr::from(([10, 3, 0, 1], 9090));
+    let b = SocketAddr::from(([10, 3, 0, 2], 9090));
+    match next_change(&mut discover).await {
+        Change::Insert(addr, node) => assert_eq!((addr, node.as_str()), (a, "discover-test-a")),
+        Change::Remove(addr) => panic!("removed {}", addr),
+    }
+    match next_change(&mut discover).await {
+        Change::Insert(addr, node) => assert_eq!((addr, node.as_str()), (b, "discover-test-b")),
+        Change::Remove(addr) => panic!("removed {}", addr),
+    }
+
+    deregister(&client, "discover-test-a").await;
+    match next_change(&mut discover).await {
+        Change::Remove(addr) => assert_eq!(addr, a),
+        Change::Insert(addr, _) => panic!("inserted {}", addr),
+    }
+    deregister(&client, "discover-test-b").await;
+}
+
+#[tokio::test]
+async fn resolver_test() {
+    let client = AsyncClient::new(common::config());
+    let service = "discover-test-resolved";
+    register(&client, "discover-test-c", "10.3.0.3", service, 9090).await;
+    register(&client, "discover-test-d", "10.3.0.4", service, 9090).await;
+
+    let mut resolver = ConsulResolver::new(client.clone());
+    let ips: HashSet<IpAddr> = resolver
+        .call(service.parse().unwrap())
+        .await
+        .unwrap()
+        .collect();
+    let missing = resolver
+        .call("discover-test-missing".parse().unwrap())
+        .await;
+    deregister(&client, "discover-test-c").await;
+    deregister(&client, "discover-test-d").await;
+
+    let expected: HashSet<IpAddr> = vec![[10, 3, 0, 3].into(), [10, 3, 0, 4].into()]
+        .into_iter()
+        .collect();
+    assert_eq!(ips, expected);
+    assert_eq!(missing.unwrap_err().kind(), std::io::ErrorKind::NotFound);
+}
+
+#[tokio::test]
+async fn resolver_hyper_test() {
+    let config = common::config();
+    let agent = url::Url::parse(&config.address).unwrap();
+    let client = AsyncClient::new(config);
+    let service = "discover-test-agent";
+    register(&client, "discover-test-e", "127.0.0.1", service, 0).await;
+
+    let connector = HttpConnector::new_with_resolver(ConsulResolver::new(client.clone()));
+    let http = hyper::Client::builder().build::<_, hyper::Body>(connector);
+    let uri = format!("http://{}:{}/v1/agent/self", service, agent.port().unwrap());
+    let response = http.get(uri.parse().unwrap()).await.unwrap();
+    deregister(&client, "discover-test-e").await;
+
+    assert!(response.status().is_success());
+}
@@ -0,0 +1,77 @@
+mod common;
+
+extern crate consul;
+use consul::event::{Event, EventFilter, EventWatch};
+use consul::{Client, QueryOptions};
+
+extern crate rand;
+use rand::distributions::Alphanumeric;
+use rand::{thread_rng, Rng};
+
+use std::thread;
+use std::time::Duration;
+
+fn event_name() -> String {
+    let suffix: String = thread_rng().sample_iter(&Alphanumeric).take(16).collect();
+    format!("event-test-{}", suffix)
+}
+
+#[test]
+fn fire_and_list_test() {
+    let client = Client::new(common::config());
+    let name = event_name();
+    let filter = EventFilter {
+        service: Some(String::from("consul")),
+        ..Default::default()
+    };
+
+    let (fired, _) = client.fire(&name, b"flush", &filter, None).unwrap();
+    assert_eq!(fired.Name, name);
+    assert_eq!(fired.ServiceFilter, "consul");
+
+    let (events, meta) = client.list(Some(&name), None).unwrap();
+    let listed = events.iter().find(|e| e.ID == fired.ID).unwrap();
+    assert_eq!(listed.Payload, b"flush");
+    assert_eq!(meta.last_index, consul::event::id_to_index(&fired.ID));
+}
+
+#[test]
+fn fire_tag_without_service_test() {
+    let client = Client::new(common::config());
+    let filter = EventFilter {
+        tag: Some(String::from("v2")),
+        ..Default::default()
+    };
+    assert!(client.fire(&event_name(), b"", &filter, None).is_err());
+}
+
+#[test]
+fn event_watch_test() {
+    let client = Client::new(common::config());
+    let name = event_name();
+    let filter = EventFilter::default();
+    client.fire(&name, b"before", &filter, None).unwrap();
+
+    let firing_client = client.clone();
+    let firing_name = name.clone();
+    let firing = thread::spawn(move || {
+        thread::sleep(Duration::from_millis(500));
+        for payload in &[&b"first"[..], &b"second"[..]] {
+            firing_client
+                .fire(&firing_name, payload, &EventFilter::default(), None)
+                .unwrap();
+        }
+    });
+
+    let options = QueryOptions {
+        wait_time: Some(Duration::from_secs(5)),
+        ..Default::default()
+    };
+    let mut watch = EventWatch::new(&client, Some(&name), Some(options));
+    let first = watch.next().unwrap().unwrap();
+    let second = watch.next().unwrap().unwrap();
+    firing.join().unwrap();
+
+    assert_eq!(first.Payload, b"first");
+    assert_eq!(second.Payload, b"second");
+}
@@ -1,6 +1,8 @@
+mod common;
+
 extern crate consul;
-use consul::health::Health;
-use consul::{Client, Config};
+use consul::health::{CheckState, Health, HealthStatus};
+use consul::Client;
 
 extern crate rand;
 use rand::distributions::Alphanumeric;
@@ -37,8 +39,60 @@ fn health_service_test_non_existant() {
     assert!(meta_query.last_index.unwrap() > 0, "index must be positive");
 }
 
+#[test]
+fn health_node_test() {
+    let client = set_up();
+
+    let (service_entries, _) = client
+        .service("consul", Option::None, true, Option::None)
+        .unwrap();
+    let service_entry = service_entries.first().unwrap();
+    assert_eq!(
+        service_entry.aggregated_status(false),
+        HealthStatus::Passing
+    );
+
+    let (checks, query_meta) = client.node(&service_entry.Node.Node, None).unwrap();
+
+    assert!(checks.iter().any(|c| c.CheckID == "serfHealth"));
+    assert!(checks.iter().all(|c| c.Node == service_entry.Node.Node));
+    assert!(query_meta.last_index.unwrap() > 0, "index must be positive");
+}
+
+#[test]
+fn health_state_test() {
+    let client = set_up();
+
+    let (passing, _) = client.state(CheckState::Passing, None).unwrap();
+    let (any, _) = client.state(CheckState::Any, None).unwrap();
+
+    assert!(!passing.is_empty());
+    assert!(passing.iter().all(|c| c.Status == HealthStatus::Passing));
+    assert!(any.len() >= passing.len());
+}
+
+#[test]
+fn health_connect_and_ingress_test_non_existant() {
+    let client = set_up();
+
+    let non_existant_service_name: String =
+        thread_rng().sample_iter(&Alphanumeric).take(16).collect();
+
+    let (checks, _) = client.checks(&non_existant_service_name, None).unwrap();
+    let (connect, _) = client
+        .connect(&non_existant_service_name, None, false, None)
+        .unwrap();
+    let (ingress, _) = client
+        .ingress(&non_existant_service_name, None, false, None)
+        .unwrap();
+
+    assert!(checks.is_empty());
+    assert!(connect.is_empty());
+    assert!(ingress.is_empty());
+}
+
 fn set_up() -> Client {
-    let config = Config::new().unwrap();
+    let config = common::config();
     let client = Client::new(config);
 
     return client;
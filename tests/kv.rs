@@ -1,8 +1,10 @@
 extern crate base64;
 
+mod common;
+
 extern crate consul;
 use consul::kv::{KVPair, KV};
-use consul::{Client, Config};
+use consul::Client;
 
 extern crate rand;
 use rand::distributions::Alphanumeric;
@@ -19,7 +21,7 @@ fn kv_add_test() {
 
     let new_kv_pair = KVPair {
         Key: format!("{}/newkey", unique_test_path),
-        Value: String::from("newvalue"),
+        Value: b"newvalue".to_vec(),
         ..Default::default()
     };
 
@@ -31,6 +33,60 @@ fn kv_add_test() {
     tear_down(client, &unique_test_path);
 }
 
+#[test]
+fn kv_cas_test() {
+    let (client, unique_test_path) = set_up();
+
+    let key = format!("{}/secondkey", unique_test_path);
+    let current = client.get(&key, None).unwrap().0.unwrap();
+
+    let updated = KVPair {
+        Value: b"casvalue".to_vec(),
+        ..current.clone()
+    };
+    assert!(client.cas(&updated, None).unwrap().0);
+
+    // The stored index has moved on, so a second swap from the same read loses.
+    let stale = KVPair {
+        Value: b"stalevalue".to_vec(),
+        ..current
+    };
+    assert!(!client.cas(&stale, None).unwrap().0);
+
+    let kv_pair = client.get(&key, None).unwrap().0.unwrap();
+    assert_eq!(kv_pair.value_str().unwrap(), "casvalue");
+
+    let new_kv_pair = KVPair {
+        Key: format!("{}/newkey", unique_test_path),
+        Value: b"newvalue".to_vec(),
+        ..Default::default()
+    };
+    assert!(client.cas(&new_kv_pair, None).unwrap().0);
+    assert!(!client.cas(&new_kv_pair, None).unwrap().0);
+
+    tear_down(client, &unique_test_path);
+}
+
+#[test]
+fn kv_delete_cas_test() {
+    let (client, unique_test_path) = set_up();
+
+    let key = format!("{}/secondkey", unique_test_path);
+    let current = client.get(&key, None).unwrap().0.unwrap();
+    let modify_index = current.ModifyIndex.unwrap();
+
+    assert!(
+        !client
+            .delete_cas_with_index(&key, modify_index + 1, None)
+            .unwrap()
+            .0
+    );
+    assert!(client.delete_cas(&current, None).unwrap().0);
+    assert!(client.get(&key, None).unwrap().0.is_none());
+
+    tear_down(client, &unique_test_path);
+}
+
 #[test]
 fn kv_delete_test() {
     let (client, unique_test_path) = set_up();
@@ -65,7 +121,41 @@ fn kv_get_test() {
     let kv_pair = client.get(&key_to_get, None).unwrap();
 
     assert!(kv_pair.0.is_some());
-    assert!(kv_pair.0.unwrap().Value == "\"secondvalue\"");
+    assert_eq!(kv_pair.0.unwrap().value_str().unwrap(), "secondvalue");
+
+    tear_down(client, &unique_test_path);
+}
+
+#[test]
+fn kv_get_missing_key_test() {
+    let (client, unique_test_path) = set_up();
+
+    let key_to_get = format!("{}/missingkey", unique_test_path);
+    let kv_pair = client.get(&key_to_get, None).unwrap();
+
+    assert!(kv_pair.0.is_none());
+    assert!(kv_pair.1.last_index.unwrap() > 0, "index must be positive");
+
+    tear_down(client, &unique_test_path);
+}
+
+#[test]
+fn kv_binary_value_test() {
+    let (client, unique_test_path) = set_up();
+
+    let binary_value = vec![0x1f, 0x8b, 0x00, 0xff, 0xfe];
+    let binary_kv_pair = KVPair {
+        Key: format!("{}/binarykey", unique_test_path),
+        Value: binary_value.clone(),
+        ..Default::default()
+    };
+
+    client.put(&binary_kv_pair, None).unwrap();
+
+    let kv_pair = client.get(&binary_kv_pair.Key, None).unwrap().0.unwrap();
+
+    assert_eq!(kv_pair.value_bytes(), &binary_value[..]);
+    assert!(kv_pair.value_str().is_err());
 
     tear_down(client, &unique_test_path);
 }
@@ -96,7 +186,7 @@ fn kv_put_test() {
 
     let updated_kv_pair = KVPair {
         Key: format!("{}/secondkey", unique_test_path),
-        Value: String::from("updatedsecondvalue"),
+        Value: b"updatedsecondvalue".to_vec(),
         ..Default::default()
     };
 
@@ -106,13 +196,16 @@ fn kv_put_test() {
     let kv_pair = client.get(&key_to_get, None).unwrap();
 
     assert!(kv_pair.0.is_some());
-    assert!(kv_pair.0.unwrap().Value == "\"updatedsecondvalue\"");
+    assert_eq!(
+        kv_pair.0.unwrap().value_str().unwrap(),
+        "updatedsecondvalue"
+    );
 
     tear_down(client, &unique_test_path);
 }
 
 fn set_up() -> (Client, String) {
-    let config = Config::new().unwrap();
+    let config = common::config();
     let client = Client::new(config);
 
     let unique_test_path: String = thread_rng().sample_iter(&Alphanumeric).take(16).collect();
@@ -120,17 +213,17 @@ fn set_up() -> (Client, String) {
     let kv_pairs = vec![
         KVPair {
             Key: format!("{}/firstkey", unique_test_path),
-            Value: String::from("firstvalue"),
+            Value: b"firstvalue".to_vec(),
             ..Default::default()
         },
         KVPair {
             Key: format!("{}/secondkey", unique_test_path),
-            Value: String::from("secondvalue"),
+            Value: b"secondvalue".to_vec(),
             ..Default::default()
         },
         KVPair {
             Key: format!("{}/thirdkey", unique_test_path),
-            Value: String::from("thirdvalue"),
+            Value: b"thirdvalue".to_vec(),
             ..Default::default()
         },
     ];
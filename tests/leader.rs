@@ -0,0 +1,99 @@
+mod common;
+
+extern crate consul;
+use consul::leader::{LeaderElection, LeadershipEvent};
+use consul::lock::LockOptions;
+use consul::Client;
+
+extern crate rand;
+use rand::distributions::Alphanumeric;
+use rand::{thread_rng, Rng};
+
+use std::sync::mpsc;
+use std::thread;
+use std::time::Duration;
+
+#[test]
+fn leader_election_test() {
+    let (client, key) = set_up();
+
+    assert_eq!(LeaderElection::leader(&client, &key, None).unwrap().0, None);
+
+    let mut options = LockOptions::new(&key);
+    options.value = b"worker-1".to_vec();
+    let first = LeaderElection::campaign(client.clone(), options).unwrap();
+    assert_eq!(
+        first.events().recv_timeout(Duration::from_secs(10)),
+        Ok(LeadershipEvent::Elected)
+    );
+    assert!(first.is_leader());
+
+    let mut options = LockOptions::new(&key);
+    options.value = b"worker-2".to_vec();
+    options.lock_wait_time = Duration::from_secs(1);
+    let second = LeaderElection::campaign(client.clone(), options).unwrap();
+
+    let leader = LeaderElection::leader(&client, &key, None).unwrap().0;
+    assert_eq!(leader, Some(b"worker-1".to_vec()));
+    assert!(!second.is_leader());
+
+    first.resign().unwrap();
+    assert_eq!(
+        second.events().recv_timeout(Duration::from_secs(30)),
+        Ok(LeadershipEvent::Elected)
+    );
+    let leader = LeaderElection::leader(&client, &key, None).unwrap().0;
+    assert_eq!(leader, Some(b"worker-2".to_vec()));
+}
+
+#[test]
+fn watch_leader_test() {
+    let (client, key) = set_up();
+
+    let (sender, leaders) = mpsc::channel();
+    {
+        let client = client.clone();
+        let key = key.clone();
+        thread::spawn(move || {
+            for leader in LeaderElection::watch_leader(&client, &key, None) {
+                if sender.send(leader.unwrap()).is_err() {
+                    return;
+                }
+            }
+        });
+    }
+    let next = || leaders.recv_timeout(Duration::from_secs(30)).unwrap();
+    assert_eq!(next(), None);
+
+    let mut options = LockOptions::new(&key);
+    options.value = b"worker-1".to_vec();
+    let first = LeaderElection::campaign(client.clone(), options).unwrap();
+    assert_eq!(next(), Some(b"worker-1".to_vec()));
+
+    let mut options = LockOptions::new(&key);
+    options.value = b"worker-2".to_vec();
+    options.lock_wait_time = Duration::from_secs(1);
+    let second = LeaderElection::campaign(client.clone(), options).unwrap();
+
+    first.resign().unwrap();
+    assert_eq!(
+        second.events().recv_timeout(Duration::from_secs(30)),
+        Ok(LeadershipEvent::Elected)
+    );
+    // The key may be seen free between the two leaders, but no holder is
+    // reported twice in a row.
+    let mut leader = next();
+    if leader.is_none() {
+        leader = next();
+    }
+    assert_eq!(leader, Some(b"worker-2".to_vec()));
+    assert!(leaders.recv_timeout(Duration::from_secs(2)).is_err());
+}
+
+fn set_up() -> (Client, String) {
+    let config = common::config();
+    let client = Client::new(config);
+
+    let unique: String = thread_rng().sample_iter(&Alphanumeric).take(16).collect();
+    (client, format!("leader-test/{}", unique))
+}
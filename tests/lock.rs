@@ -0,0 +1,103 @@
+mod common;
+
+extern crate consul;
+use consul::kv::KV;
+use consul::lock::{Lock, LockOptions, LOCK_FLAG_VALUE};
+use consul::session::Session;
+use consul::Client;
+
+extern crate rand;
+use rand::distributions::Alphanumeric;
+use rand::{thread_rng, Rng};
+
+use std::sync::mpsc;
+use std::time::Duration;
+
+#[test]
+fn lock_unlock_test() {
+    let (client, key) = set_up();
+
+    let mut options = LockOptions::new(&key);
+    options.value = b"holder-1".to_vec();
+    let mut lock = Lock::new(client.clone(), options);
+
+    let lost = lock.lock(None).unwrap().unwrap();
+    assert!(lock.is_held());
+
+    let pair = client.get(&key, None).unwrap().0.unwrap();
+    assert_eq!(pair.Flags, Some(LOCK_FLAG_VALUE));
+    assert_eq!(pair.value_str().unwrap(), "holder-1");
+    assert!(pair.Session.is_some());
+
+    lock.unlock().unwrap();
+    assert!(!lock.is_held());
+    assert!(lost.recv().is_err());
+
+    let pair = client.get(&key, None).unwrap().0.unwrap();
+    assert!(pair.Session.is_none());
+}
+
+#[test]
+fn lock_try_once_when_held_test() {
+    let (client, key) = set_up();
+
+    let mut first = Lock::new(client.clone(), LockOptions::new(&key));
+    first.lock(None).unwrap().unwrap();
+
+    let mut options = LockOptions::new(&key);
+    options.lock_try_once = true;
+    let mut second = Lock::new(client, options);
+    assert!(second.lock(None).unwrap().is_none());
+    assert!(!second.is_held());
+}
+
+#[test]
+fn lock_cancelled_test() {
+    let (client, key) = set_up();
+
+    let mut first = Lock::new(client.clone(), LockOptions::new(&key));
+    first.lock(None).unwrap().unwrap();
+
+    let (cancel, cancelled) = mpsc::channel();
+    cancel.send(()).unwrap();
+    let mut second = Lock::new(client, LockOptions::new(&key));
+    assert!(second.lock(Some(&cancelled)).unwrap().is_none());
+}
+
+#[test]
+fn lock_lost_test() {
+    let (client, key) = set_up();
+
+    let mut lock = Lock::new(client.clone(), LockOptions::new(&key));
+    let lost = lock.lock(None).unwrap().unwrap();
+
+    let session = client.get(&key, None).unwrap().0.unwrap().Session.unwrap();
+    client.destroy(&session, None).unwrap();
+
+    assert_eq!(lost.recv_timeout(Duration::from_secs(20)), Ok(()));
+}
+
+#[test]
+fn lock_session_ttl_test() {
+    let (client, key) = set_up();
+
+    let mut options = LockOptions::new(&key);
+    options.session_ttl = Duration::from_millis(10_500);
+    let mut lock = Lock::new(client.clone(), options);
+    lock.lock(None).unwrap().unwrap();
+    lock.unlock().unwrap();
+
+    let mut options = LockOptions::new(&key);
+    options.session_ttl = Duration::from_secs(5);
+    let mut lock = Lock::new(client, options);
+    assert!(lock.lock(None).is_err());
+    assert!(!lock.is_held());
+}
+
+fn set_up() -> (Client, String) {
+    let config = common::config();
+    let client = Client::new(config);
+
+    let unique: String = thread_rng().sample_iter(&Alphanumeric).take(16).collect();
+    (client, format!("lock-test/{}", unique))
+}
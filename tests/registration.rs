@@ -0,0 +1,64 @@
+mod common;
+
+extern crate consul;
+use consul::agent::{Agent, AgentCheck, AgentServiceCheck, AgentServiceRegistration};
+use consul::registration::ServiceRegistration;
+use consul::Client;
+
+extern crate rand;
+use rand::distributions::Alphanumeric;
+use rand::{thread_rng, Rng};
+
+#[test]
+fn service_registration_test() {
+    let client = set_up();
+
+    let registration = ServiceRegistration::register(client.clone(), ttl_service()).unwrap();
+    let service_id = registration.service_id().to_owned();
+    let check_id = format!("service:{}", service_id);
+
+    assert!(client.services().unwrap().contains_key(&service_id));
+    assert_eq!(client.checks().unwrap()[&check_id].Status, "passing");
+
+    registration.deregister().unwrap();
+    assert!(!client.services().unwrap().contains_key(&service_id));
+}
+
+#[test]
+fn service_registration_with_health_test() {
+    let client = set_up();
+
+    let registration =
+        ServiceRegistration::register_with_health(client.clone(), ttl_service(), || AgentCheck {
+            Status: String::from("warning"),
+            Output: String::from("degraded"),
+            ..Default::default()
+        })
+        .unwrap();
+    let service_id = registration.service_id().to_owned();
+    let check = &client.checks().unwrap()[&format!("service:{}", service_id)];
+    assert_eq!(check.Status, "warning");
+    assert_eq!(check.Output, "degraded");
+
+    drop(registration);
+    assert!(!client.services().unwrap().contains_key(&service_id));
+}
+
+fn ttl_service() -> AgentServiceRegistration {
+    let service_id: String = thread_rng().sample_iter(&Alphanumeric).take(16).collect();
+    AgentServiceRegistration {
+        ID: Some(service_id),
+        Name: String::from("registration-test-service"),
+        Check: Some(AgentServiceCheck {
+            TTL: Some(String::from("10s")),
+            ..Default::default()
+        }),
+        ..Default::default()
+    }
+}
+
+fn set_up() -> Client {
+    let config = common::config();
+
+    Client::new(config)
+}
@@ -0,0 +1,87 @@
+mod common;
+
+extern crate consul;
+use consul::catalog::{Catalog, CatalogDeregistration, CatalogRegistration};
+use consul::resolver::{Balance, ResolverOptions, ServiceResolver};
+use consul::{Client, QueryOptions};
+
+use std::collections::HashSet;
+use std::net::SocketAddr;
+use std::thread;
+use std::time::{Duration, Instant};
+
+const SERVICE: &str = "resolver-test-web";
+
+fn register(client: &Client, node: &str, address: &str) {
+    use consul::agent::AgentService;
+    let registration = CatalogRegistration {
+        Node: node.to_owned(),
+        Address: address.to_owned(),
+        Service: Some(AgentService {
+            ID: format!("{}-web", node),
+            Service: SERVICE.to_owned(),
+            Port: 8080,
+            ..Default::default()
+        }),
+        ..Default::default()
+    };
+    client.register(&registration, None).unwrap();
+}
+
+fn deregister(client: &Client, node: &str) {
+    let deregistration = CatalogDeregistration {
+        Node: node.to_owned(),
+        ..Default::default()
+    };
+    client.deregister(&deregistration, None).unwrap();
+}
+
+fn options(balance: Balance, near: Option<&str>) -> ResolverOptions {
+    ResolverOptions {
+        balance,
+        near: near.map(str::to_owned),
+        query_options: QueryOptions {
+            wait_time: Some(Duration::from_secs(1)),
+            ..Default::default()
+        },
+        ..Default::default()
+    }
+}
+
+#[test]
+fn resolver_test() {
+    let client = Client::new(common::config());
+    register(&client, "resolver-test-a", "10.2.0.1");
+    register(&client, "resolver-test-b", "10.2.0.2");
+
+    let round_robin = ServiceResolver::new(&client, SERVICE, options(Balance::RoundRobin, None));
+    let nearest = ServiceResolver::new(
+        &client,
+        SERVICE,
+        options(Balance::LeastRtt, Some("resolver-test-a")),
+    );
+    let round_robin = round_robin.unwrap();
+    let nearest = nearest.unwrap();
+
+    let a = SocketAddr::from(([10, 2, 0, 1], 8080));
+    let b = SocketAddr::from(([10, 2, 0, 2], 8080));
+    let picked: HashSet<SocketAddr> = (0..2).filter_map(|_| round_robin.resolve()).collect();
+    assert_eq!(picked, vec![a, b].into_iter().collect());
+    assert_eq!(nearest.resolve(), Some(a));
+
+    deregister(&client, "resolver-test-a");
+    let deadline = Instant::now() + Duration::from_secs(10);
+    while round_robin.instances().len() != 1 && Instant::now() < deadline {
+        thread::sleep(Duration::from_millis(50));
+    }
+    assert_eq!(round_robin.resolve(), Some(b));
+
+    deregister(&client, "resolver-test-b");
+}
+
+#[test]
+fn resolver_least_rtt_needs_near_test() {
+    let client = Client::new(common::config());
+    let resolver = ServiceResolver::new(&client, SERVICE, options(Balance::LeastRtt, None));
+    assert!(resolver.is_err());
+}
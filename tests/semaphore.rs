@@ -0,0 +1,64 @@
+mod common;
+
+extern crate consul;
+use consul::semaphore::{Semaphore, SemaphoreOptions};
+use consul::Client;
+
+extern crate rand;
+use rand::distributions::Alphanumeric;
+use rand::{thread_rng, Rng};
+
+use std::time::Duration;
+
+#[test]
+fn semaphore_limit_test() {
+    let (client, prefix) = set_up();
+
+    let mut first = Semaphore::new(client.clone(), SemaphoreOptions::new(&prefix, 2)).unwrap();
+    let mut second = Semaphore::new(client.clone(), SemaphoreOptions::new(&prefix, 2)).unwrap();
+    first.acquire(None).unwrap().unwrap();
+    second.acquire(None).unwrap().unwrap();
+
+    let mut options = SemaphoreOptions::new(&prefix, 2);
+    options.try_once = true;
+    let mut third = Semaphore::new(client.clone(), options).unwrap();
+    assert!(third.acquire(None).unwrap().is_none());
+
+    first.release().unwrap();
+    assert!(third.acquire(None).unwrap().is_some());
+    assert!(third.is_held());
+}
+
+#[test]
+fn semaphore_limit_conflict_test() {
+    let (client, prefix) = set_up();
+
+    let mut first = Semaphore::new(client.clone(), SemaphoreOptions::new(&prefix, 2)).unwrap();
+    first.acquire(None).unwrap().unwrap();
+
+    let mut other = Semaphore::new(client, SemaphoreOptions::new(&prefix, 3)).unwrap();
+    assert!(other.acquire(None).is_err());
+}
+
+#[test]
+fn semaphore_session_ttl_test() {
+    let (client, prefix) = set_up();
+
+    let mut options = SemaphoreOptions::new(&prefix, 1);
+    options.session_ttl = Duration::from_millis(10_500);
+    let mut semaphore = Semaphore::new(client.clone(), options).unwrap();
+    semaphore.acquire(None).unwrap().unwrap();
+    semaphore.release().unwrap();
+
+    let mut options = SemaphoreOptions::new(&prefix, 1);
+    options.session_ttl = Duration::from_secs(5);
+    assert!(Semaphore::new(client, options).is_err());
+}
+
+fn set_up() -> (Client, String) {
+    let config = common::config();
+    let client = Client::new(config);
+
+    let unique: String = thread_rng().sample_iter(&Alphanumeric).take(16).collect();
+    (client, format!("semaphore-test/{}", unique))
+}
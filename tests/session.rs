@@ -1,6 +1,8 @@
+mod common;
+
 extern crate consul;
 use consul::session::{Session, SessionEntry};
-use consul::{Client, Config};
+use consul::Client;
 
 extern crate rand;
 use rand::distributions::Alphanumeric;
@@ -177,7 +179,7 @@ fn session_renew_test() {
 }
 
 fn set_up() -> (Client, String) {
-    let config = Config::new().unwrap();
+    let config = common::config();
     let client = Client::new(config);
 
     let unique_test_identifier: String = thread_rng().sample_iter(&Alphanumeric).take(16).collect();
@@ -0,0 +1,82 @@
+#![cfg(feature = "testing")]
+
+extern crate consul;
+use consul::catalog::Catalog;
+use consul::health::Health;
+use consul::kv::{KVPair, KV};
+use consul::testing::{TestServer, TestServerOptions};
+use consul::{Client, QueryOptions};
+
+use std::thread;
+use std::time::{Duration, Instant};
+
+#[test]
+fn blocking_query_test() {
+    let server = TestServer::start().unwrap();
+    let client = Client::new(server.config().unwrap());
+    let pair = KVPair {
+        Key: String::from("app/config"),
+        Value: b"first".to_vec(),
+        ..Default::default()
+    };
+    client.put(&pair, None).unwrap();
+    let (_, meta) = client.get(&pair.Key, None).unwrap();
+
+    let writer = client.clone();
+    let updated = KVPair {
+        Value: b"second".to_vec(),
+        ..pair.clone()
+    };
+    let handle = thread::spawn(move || {
+        thread::sleep(Duration::from_millis(300));
+        writer.put(&updated, None).unwrap();
+    });
+
+    let options = QueryOptions {
+        wait_index: meta.last_index,
+        wait_time: Some(Duration::from_secs(10)),
+        ..Default::default()
+    };
+    let start = Instant::now();
+    let (stored, blocked_meta) = client.get(&pair.Key, Some(&options)).unwrap();
+
+    assert!(start.elapsed() >= Duration::from_millis(300));
+    assert_eq!(stored.unwrap().value_str().unwrap(), "second");
+    assert!(blocked_meta.last_index > meta.last_index);
+    handle.join().unwrap();
+}
+
+#[test]
+fn blocking_query_timeout_test() {
+    let server = TestServer::start().unwrap();
+    let client = Client::new(server.config().unwrap());
+    let (_, meta) = client.list("app", None).unwrap();
+
+    let options = QueryOptions {
+        wait_index: meta.last_index,
+        wait_time: Some(Duration::from_secs(1)),
+        ..Default::default()
+    };
+    let start = Instant::now();
+    let (pairs, timed_out_meta) = client.list("app", Some(&options)).unwrap();
+
+    assert!(start.elapsed() >= Duration::from_secs(1));
+    assert!(pairs.is_empty());
+    assert_eq!(timed_out_meta.last_index, meta.last_index);
+}
+
+#[test]
+fn cluster_layout_test() {
+    let server = TestServer::start_with(TestServerOptions {
+        datacenters: vec![String::from("dc1"), String::from("dc2")],
+        servers: 2,
+        ..Default::default()
+    })
+    .unwrap();
+    let client = Client::new(server.config().unwrap());
+
+    assert_eq!(client.datacenters().unwrap().0, ["dc1", "dc2"]);
+    assert_eq!(client.nodes(None).unwrap().0.len(), 2);
+    let (entries, _) = Health::service(&client, "consul", None, true, None).unwrap();
+    assert_eq!(entries.len(), 2);
+}
@@ -0,0 +1,105 @@
+extern crate consul;
+use consul::catalog::Catalog;
+use consul::tls::TlsConfig;
+use consul::{Client, Config};
+
+use native_tls::{Identity, TlsAcceptor};
+
+use std::fs;
+use std::io::{Read, Write};
+use std::net::TcpListener;
+use std::path::PathBuf;
+use std::thread;
+
+fn fixture(name: &str) -> PathBuf {
+    PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+        .join("tests/fixtures/tls")
+        .join(name)
+}
+
+/// Serves `/v1/catalog/datacenters` over HTTPS with the certificate issued for
+/// `name` and returns the port.
+fn serve(name: &str) -> u16 {
+    let cert = fs::read(fixture(&format!("{}.pem", name))).unwrap();
+    let key = fs::read(fixture(&format!("{}-key.pem", name))).unwrap();
+    let acceptor = TlsAcceptor::new(Identity::from_pkcs8(&cert, &key).unwrap()).unwrap();
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let port = listener.local_addr().unwrap().port();
+
+    thread::spawn(move || {
+        for stream in listener.incoming() {
+            // Clients that reject the certificate end the handshake.
+            let mut stream = match acceptor.accept(stream.unwrap()) {
+                Ok(stream) => stream,
+                Err(_) => continue,
+            };
+            let mut request = Vec::new();
+            let mut buf = [0; 4096];
+            while !request.ends_with(b"\r\n\r\n") {
+                let n = stream.read(&mut buf).unwrap();
+                request.extend_from_slice(&buf[..n]);
+            }
+            stream
+                .write_all(b"HTTP/1.1 200 OK\r\nContent-Length: 9\r\n\r\n[\"alpha\"]")
+                .unwrap();
+        }
+    });
+    port
+}
+
+/// A config for the agent on `port`, reached by IP and verified as
+/// `localhost`.
+fn config(port: u16) -> Config {
+    Config::builder()
+        .address(format!("https://127.0.0.1:{}", port))
+        .no_proxy()
+        .tls(TlsConfig {
+            ca_file: Some(fixture("ca.pem")),
+            server_name: Some(String::from("localhost")),
+            ..Default::default()
+        })
+        .build()
+        .unwrap()
+}
+
+#[test]
+fn tls_server_name_test() {
+    let client = Client::new(config(serve("localhost")));
+
+    let (datacenters, _) = client.datacenters().unwrap();
+    assert_eq!(datacenters, vec![String::from("alpha")]);
+}
+
+#[test]
+fn tls_server_name_rejects_other_name_test() {
+    let client = Client::new(config(serve("other.example")));
+
+    assert!(client.datacenters().is_err());
+}
+
+#[test]
+fn tls_without_server_name_checks_host_test() {
+    let config = config(serve("localhost"))
+        .with_tls(TlsConfig {
+            ca_file: Some(fixture("ca.pem")),
+            ..Default::default()
+        })
+        .unwrap();
+
+    // The certificate is issued for localhost, not 127.0.0.1.
+    assert!(Client::new(config).datacenters().is_err());
+}
+
+#[cfg(feature = "async")]
+#[tokio::test]
+async fn async_tls_server_name_test() {
+    use consul::catalog::AsyncCatalog;
+    use consul::AsyncClient;
+
+    let client = AsyncClient::new(config(serve("localhost")));
+    let other = AsyncClient::new(config(serve("other.example")));
+
+    let (datacenters, _) = client.datacenters().await.unwrap();
+    assert_eq!(datacenters, vec![String::from("alpha")]);
+    assert!(other.datacenters().await.is_err());
+}
@@ -0,0 +1,428 @@
+extern crate consul;
+use consul::errors::{ErrorKind, Result};
+use consul::kv::{KVPair, KV};
+use consul::retry::RetryPolicy;
+use consul::transport::{HttpRequest, HttpResponse, ReqwestTransport, Transport};
+use consul::{Client, Config};
+
+use reqwest::header::{HeaderMap, HeaderValue};
+use reqwest::{Method, StatusCode};
+
+use std::collections::VecDeque;
+use std::io::{Read, Write};
+use std::net::TcpListener;
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// Answers with canned responses, in order, and records every request.
+#[derive(Debug, Default)]
+struct ScriptedTransport {
+    responses: Mutex<VecDeque<HttpResponse>>,
+    requests: Arc<Mutex<Vec<HttpRequest>>>,
+}
+
+impl ScriptedTransport {
+    fn respond(
+        self,
+        status: StatusCode,
+        headers: &[(&'static str, &'static str)],
+        body: &str,
+    ) -> Self {
+        let mut map = HeaderMap::new();
+        for (name, value) in headers {
+            map.insert(*name, HeaderValue::from_static(value));
+        }
+        self.responses.lock().unwrap().push_back(HttpResponse {
+            status,
+            headers: map,
+            body: body.as_bytes().to_vec(),
+        });
+        self
+    }
+}
+
+impl Transport for ScriptedTransport {
+    fn send(&self, request: HttpRequest) -> Result<HttpResponse> {
+        self.requests.lock().unwrap().push(request);
+        Ok(self
+            .responses
+            .lock()
+            .unwrap()
+            .pop_front()
+            .expect("no response left for request"))
+    }
+}
+
+/// Fails every request to `down`, as if nothing listened there, and hands the
+/// rest to the wrapped transport.
+#[derive(Debug)]
+struct UnreachableHost<T> {
+    down: &'static str,
+    inner: T,
+}
+
+impl<T: Transport> Transport for UnreachableHost<T> {
+    fn send(&self, request: HttpRequest) -> Result<HttpResponse> {
+        if request.url.host_str() == Some(self.down) {
+            return Err(ErrorKind::ConnectFailed(request.url.to_string()).into());
+        }
+        self.inner.send(request)
+    }
+}
+
+/// An address nothing listens on.
+fn dead_address() -> String {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    format!("http://{}", listener.local_addr().unwrap())
+}
+
+/// Answers every request with `true` and records its request line. Returns the
+/// address it listens on.
+fn serve_true() -> (String, Arc<Mutex<Vec<String>>>) {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let address = format!("http://{}", listener.local_addr().unwrap());
+    let requests = Arc::new(Mutex::new(Vec::new()));
+    let received = Arc::clone(&requests);
+    thread::spawn(move || {
+        for stream in listener.incoming() {
+            let mut stream = stream.unwrap();
+            let mut request = Vec::new();
+            let mut buf = [0; 4096];
+            while !request.windows(4).any(|w| w == b"\r\n\r\n") {
+                let n = stream.read(&mut buf).unwrap();
+                request.extend_from_slice(&buf[..n]);
+            }
+            let request = String::from_utf8_lossy(&request).into_owned();
+            received
+                .lock()
+                .unwrap()
+                .push(request.lines().next().unwrap().to_owned());
+            stream
+                .write_all(b"HTTP/1.1 200 OK\r\nContent-Length: 4\r\nConnection: close\r\n\r\ntrue")
+                .unwrap();
+        }
+    });
+    (address, requests)
+}
+
+/// Sends the start of a response to the first request and then nothing more
+/// until the client hangs up. Returns the address it listens on.
+fn stall() -> String {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let address = format!("http://{}", listener.local_addr().unwrap());
+    thread::spawn(move || {
+        let (mut stream, _) = listener.accept().unwrap();
+        let mut buf = [0; 4096];
+        let _ = stream.read(&mut buf).unwrap();
+        stream
+            .write_all(b"HTTP/1.1 200 OK\r\nContent-Length: 2\r\n\r\n[")
+            .unwrap();
+        while stream.read(&mut buf).unwrap_or(0) > 0 {}
+    });
+    address
+}
+
+fn retry_policy(max_attempts: u32) -> RetryPolicy {
+    RetryPolicy {
+        max_attempts,
+        base_delay: Duration::from_millis(1),
+        ..Default::default()
+    }
+}
+
+/// Adds a header to every request before handing it to the wrapped transport.
+#[derive(Debug)]
+struct HeaderMiddleware<T> {
+    inner: T,
+}
+
+impl<T: Transport> Transport for HeaderMiddleware<T> {
+    fn send(&self, mut request: HttpRequest) -> Result<HttpResponse> {
+        request
+            .headers
+            .insert("X-Request-Source", HeaderValue::from_static("tests"));
+        self.inner.send(request)
+    }
+}
+
+#[test]
+fn transport_get_test() {
+    let transport = ScriptedTransport::default().respond(
+        StatusCode::OK,
+        &[("X-Consul-Index", "12")],
+        r#"[{"Key":"app/config","Value":"dmFsdWU=","ModifyIndex":12}]"#,
+    );
+    let requests = Arc::clone(&transport.requests);
+    let mut config = Config::new().unwrap().with_transport(transport);
+    config.token = Some(String::from("secret"));
+    let client = Client::new(config);
+
+    let (pair, meta) = client.get("app/config", None).unwrap();
+
+    assert_eq!(pair.unwrap().value_str().unwrap(), "value");
+    assert_eq!(meta.last_index, Some(12));
+    let requests = requests.lock().unwrap();
+    assert_eq!(requests.len(), 1);
+    assert_eq!(requests[0].method, Method::GET);
+    assert_eq!(requests[0].url.path(), "/v1/kv/app/config");
+    assert_eq!(requests[0].headers["X-Consul-Token"], "secret");
+}
+
+#[test]
+fn transport_put_test() {
+    let transport = ScriptedTransport::default().respond(StatusCode::OK, &[], "true");
+    let requests = Arc::clone(&transport.requests);
+    let client = Client::new(
+        Config::new()
+            .unwrap()
+            .with_transport(HeaderMiddleware { inner: transport }),
+    );
+
+    let pair = KVPair {
+        Key: String::from("app/config"),
+        Value: b"value".to_vec(),
+        ..Default::default()
+    };
+    let (written, _) = client.put(&pair, None).unwrap();
+
+    assert!(written);
+    let requests = requests.lock().unwrap();
+    assert_eq!(requests[0].method, Method::PUT);
+    assert_eq!(requests[0].body, b"value");
+    assert_eq!(requests[0].headers["X-Request-Source"], "tests");
+}
+
+#[test]
+fn transport_error_status_test() {
+    let transport =
+        ScriptedTransport::default().respond(StatusCode::FORBIDDEN, &[], "Permission denied");
+    let client = Client::new(Config::new().unwrap().with_transport(transport));
+
+    let error = client.get("app/config", None).unwrap_err();
+
+    assert!(error.kind().is_permission_denied());
+}
+
+#[test]
+fn reqwest_transport_wraps_http_client_test() {
+    let config = Config::new().unwrap();
+    let transport = HeaderMiddleware {
+        inner: ReqwestTransport::new(config.http_client.clone()),
+    };
+    let config = config.with_transport(transport);
+
+    assert!(config.transport.is_some());
+}
+
+#[test]
+fn retry_get_test() {
+    let transport = ScriptedTransport::default()
+        .respond(StatusCode::SERVICE_UNAVAILABLE, &[], "No cluster leader")
+        .respond(StatusCode::TOO_MANY_REQUESTS, &[], "rate limit exceeded")
+        .respond(
+            StatusCode::OK,
+            &[],
+            r#"[{"Key":"app/config","Value":"dmFsdWU="}]"#,
+        );
+    let requests = Arc::clone(&transport.requests);
+    let mut config = Config::new().unwrap().with_transport(transport);
+    config.retry_policy = retry_policy(3);
+    let client = Client::new(config);
+
+    let (pair, _) = client.get("app/config", None).unwrap();
+
+    assert_eq!(pair.unwrap().value_str().unwrap(), "value");
+    assert_eq!(requests.lock().unwrap().len(), 3);
+}
+
+#[test]
+fn retry_gives_up_test() {
+    let transport = ScriptedTransport::default()
+        .respond(StatusCode::SERVICE_UNAVAILABLE, &[], "No cluster leader")
+        .respond(StatusCode::SERVICE_UNAVAILABLE, &[], "No cluster leader");
+    let requests = Arc::clone(&transport.requests);
+    let mut config = Config::new().unwrap().with_transport(transport);
+    config.retry_policy = retry_policy(2);
+    let client = Client::new(config);
+
+    let error = client.get("app/config", None).unwrap_err();
+
+    assert!(error.kind().is_server_error());
+    assert_eq!(requests.lock().unwrap().len(), 2);
+}
+
+#[test]
+fn retry_skips_writes_test() {
+    let transport =
+        ScriptedTransport::default().respond(StatusCode::SERVICE_UNAVAILABLE, &[], "unavailable");
+    let requests = Arc::clone(&transport.requests);
+    let mut config = Config::new().unwrap().with_transport(transport);
+    config.retry_policy = retry_policy(3);
+    let client = Client::new(config);
+
+    let pair = KVPair {
+        Key: String::from("app/config"),
+        Value: b"value".to_vec(),
+        ..Default::default()
+    };
+
+    assert!(client.put(&pair, None).is_err());
+    assert_eq!(requests.lock().unwrap().len(), 1);
+}
+
+#[test]
+fn retry_writes_when_allowed_test() {
+    let transport = ScriptedTransport::default()
+        .respond(StatusCode::SERVICE_UNAVAILABLE, &[], "unavailable")
+        .respond(StatusCode::OK, &[], "true");
+    let requests = Arc::clone(&transport.requests);
+    let mut config = Config::new().unwrap().with_transport(transport);
+    config.retry_policy = RetryPolicy {
+        retry_writes: true,
+        ..retry_policy(2)
+    };
+    let client = Client::new(config);
+
+    let pair = KVPair {
+        Key: String::from("app/config"),
+        Value: b"value".to_vec(),
+        ..Default::default()
+    };
+    let (written, _) = client.put(&pair, None).unwrap();
+
+    assert!(written);
+    let requests = requests.lock().unwrap();
+    assert_eq!(requests.len(), 2);
+    assert_eq!(requests[1].body, b"value");
+}
+
+#[test]
+fn fallback_address_test() {
+    let transport = ScriptedTransport::default().respond(StatusCode::OK, &[], "[]");
+    let requests = Arc::clone(&transport.requests);
+    let mut config = Config::new().unwrap().with_transport(UnreachableHost {
+        down: "10.0.0.1",
+        inner: transport,
+    });
+    config.address = String::from("http://10.0.0.1:8500");
+    config.fallback_addresses = vec![String::from("http://10.0.0.2:8500")];
+    let client = Client::new(config);
+
+    let (pair, _) = client.get("app/config", None).unwrap();
+
+    assert!(pair.is_none());
+    let requests = requests.lock().unwrap();
+    assert_eq!(requests.len(), 1);
+    assert_eq!(requests[0].url.host_str(), Some("10.0.0.2"));
+    assert_eq!(requests[0].url.path(), "/v1/kv/app/config");
+}
+
+#[test]
+fn fallback_address_write_test() {
+    let transport = ScriptedTransport::default().respond(StatusCode::OK, &[], "true");
+    let requests = Arc::clone(&transport.requests);
+    let mut config = Config::new().unwrap().with_transport(UnreachableHost {
+        down: "10.0.0.1",
+        inner: transport,
+    });
+    config.address = String::from("http://10.0.0.1:8500");
+    config.fallback_addresses = vec![String::from("http://10.0.0.2:8500")];
+    let client = Client::new(config);
+
+    let pair = KVPair {
+        Key: String::from("app/config"),
+        Value: b"value".to_vec(),
+        ..Default::default()
+    };
+    let (written, _) = client.put(&pair, None).unwrap();
+
+    assert!(written);
+    let requests = requests.lock().unwrap();
+    assert_eq!(requests.len(), 1);
+    assert_eq!(requests[0].url.host_str(), Some("10.0.0.2"));
+}
+
+#[test]
+fn fallback_address_write_over_http_test() {
+    let (address, requests) = serve_true();
+    let config = Config::builder()
+        .address(dead_address())
+        .fallback_addresses(vec![address])
+        .no_proxy()
+        .build()
+        .unwrap();
+    let client = Client::new(config);
+
+    let pair = KVPair {
+        Key: String::from("app/config"),
+        ..Default::default()
+    };
+    let (written, _) = client.put(&pair, None).unwrap();
+
+    assert!(written);
+    let requests = requests.lock().unwrap();
+    assert_eq!(requests.len(), 1);
+    assert!(requests[0].starts_with("PUT /v1/kv/app/config"));
+}
+
+#[cfg(feature = "async")]
+#[tokio::test]
+async fn async_fallback_address_write_test() {
+    use consul::kv::AsyncKV;
+    use consul::AsyncClient;
+
+    let (address, requests) = serve_true();
+    let config = Config::builder()
+        .address(dead_address())
+        .fallback_addresses(vec![address])
+        .no_proxy()
+        .build()
+        .unwrap();
+    let client = AsyncClient::new(config);
+
+    let pair = KVPair {
+        Key: String::from("app/config"),
+        ..Default::default()
+    };
+    let (written, _) = client.put(&pair, None).await.unwrap();
+
+    assert!(written);
+    let requests = requests.lock().unwrap();
+    assert_eq!(requests.len(), 1);
+    assert!(requests[0].starts_with("PUT /v1/kv/app/config"));
+}
+
+#[test]
+fn read_timeout_test() {
+    let config = Config::builder()
+        .address(stall())
+        .read_timeout(Duration::from_millis(300))
+        .no_proxy()
+        .build()
+        .unwrap();
+    let client = Client::new(config);
+
+    let started = Instant::now();
+    assert!(client.get("app/config", None).is_err());
+    assert!(started.elapsed() < Duration::from_secs(5));
+}
+
+#[cfg(feature = "async")]
+#[tokio::test]
+async fn async_read_timeout_test() {
+    use consul::kv::AsyncKV;
+    use consul::AsyncClient;
+
+    let config = Config::builder()
+        .address(stall())
+        .read_timeout(Duration::from_millis(300))
+        .no_proxy()
+        .build()
+        .unwrap();
+    let client = AsyncClient::new(config);
+
+    let started = Instant::now();
+    assert!(client.get("app/config", None).await.is_err());
+    assert!(started.elapsed() < Duration::from_secs(5));
+}
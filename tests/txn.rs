@@ -0,0 +1,82 @@
+mod common;
+
+extern crate consul;
+use consul::kv::{KVPair, KV};
+use consul::txn::{KVTxnOp, Txn};
+use consul::Client;
+
+extern crate rand;
+use rand::distributions::Alphanumeric;
+use rand::{thread_rng, Rng};
+
+#[test]
+fn txn_commit_test() {
+    let (client, unique_test_path) = set_up();
+
+    let ops = vec![
+        KVTxnOp::Set(KVPair {
+            Key: format!("{}/enabled", unique_test_path),
+            Value: b"true".to_vec(),
+            ..Default::default()
+        }),
+        KVTxnOp::Set(KVPair {
+            Key: format!("{}/rollout", unique_test_path),
+            Value: b"50".to_vec(),
+            ..Default::default()
+        }),
+        KVTxnOp::Get(format!("{}/enabled", unique_test_path)),
+    ];
+
+    let (response, _) = client.txn(&ops, None).unwrap();
+
+    assert!(response.is_committed());
+    let results = response.Results.unwrap();
+    assert_eq!(results.len(), 3);
+    assert_eq!(results[2].KV.as_ref().unwrap().value_str().unwrap(), "true");
+
+    let (pairs, _) = client.list(&unique_test_path, None).unwrap();
+    assert_eq!(pairs.len(), 2);
+
+    tear_down(&client, &unique_test_path);
+}
+
+#[test]
+fn txn_rollback_test() {
+    let (client, unique_test_path) = set_up();
+
+    let key = format!("{}/enabled", unique_test_path);
+    let ops = vec![
+        KVTxnOp::Set(KVPair {
+            Key: key.clone(),
+            Value: b"true".to_vec(),
+            ..Default::default()
+        }),
+        KVTxnOp::CheckIndex(KVPair {
+            Key: format!("{}/missing", unique_test_path),
+            ModifyIndex: Some(1),
+            ..Default::default()
+        }),
+    ];
+
+    let (response, _) = client.txn(&ops, None).unwrap();
+
+    assert!(!response.is_committed());
+    assert_eq!(response.Errors.unwrap()[0].OpIndex, 1);
+    assert!(client.get(&key, None).unwrap().0.is_none());
+
+    tear_down(&client, &unique_test_path);
+}
+
+fn set_up() -> (Client, String) {
+    let config = common::config();
+    let client = Client::new(config);
+
+    let unique_test_path: String = thread_rng().sample_iter(&Alphanumeric).take(16).collect();
+
+    (client, unique_test_path)
+}
+
+fn tear_down(client: &Client, unique_test_path: &str) {
+    let ops = vec![KVTxnOp::DeleteTree(unique_test_path.to_owned())];
+    client.txn(&ops, None).unwrap();
+}
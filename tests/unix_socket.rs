@@ -0,0 +1,210 @@
+#![cfg(unix)]
+
+extern crate consul;
+use consul::kv::{KVPair, KV};
+use consul::{Client, Config};
+
+use std::env;
+use std::io::{Read, Write};
+use std::os::unix::net::UnixListener;
+use std::path::PathBuf;
+use std::process;
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+
+fn listen(name: &str) -> (UnixListener, String) {
+    let socket: PathBuf = env::temp_dir().join(format!("consul-{}-{}.sock", name, process::id()));
+    let _ = std::fs::remove_file(&socket);
+    let listener = UnixListener::bind(&socket).unwrap();
+    (listener, format!("unix://{}", socket.display()))
+}
+
+/// Answers one request on a fresh socket and hands back what was received.
+fn serve_once(name: &str, response: &'static [u8]) -> (Config, JoinHandle<String>) {
+    let (listener, address) = listen(name);
+
+    let server = thread::spawn(move || {
+        let (mut stream, _) = listener.accept().unwrap();
+        let mut request = Vec::new();
+        let mut buf = [0; 4096];
+        // The request is complete once the headers and the announced body are in.
+        loop {
+            let n = stream.read(&mut buf).unwrap();
+            request.extend_from_slice(&buf[..n]);
+            let text = String::from_utf8_lossy(&request).to_string();
+            if let Some(end) = text.find("\r\n\r\n") {
+                let length = text
+                    .lines()
+                    .find_map(|l| l.strip_prefix("Content-Length: "))
+                    .map_or(0, |l| l.trim().parse().unwrap());
+                if request.len() >= end + 4 + length {
+                    break;
+                }
+            }
+        }
+        stream.write_all(response).unwrap();
+        String::from_utf8(request).unwrap()
+    });
+
+    let mut config = Config::new().unwrap();
+    config.address = address;
+    (config, server)
+}
+
+/// Accepts one connection on a fresh socket and never answers, handing back
+/// how long the client stayed connected.
+fn hang(name: &str) -> (String, JoinHandle<Duration>) {
+    let (listener, address) = listen(name);
+    let server = thread::spawn(move || {
+        let (mut stream, _) = listener.accept().unwrap();
+        let connected = Instant::now();
+        let mut buf = [0; 4096];
+        while stream.read(&mut buf).unwrap_or(0) > 0 {}
+        connected.elapsed()
+    });
+    (address, server)
+}
+
+#[test]
+fn unix_socket_get_test() {
+    let (config, server) = serve_once(
+        "get",
+        b"HTTP/1.1 200 OK\r\nX-Consul-Index: 7\r\nTransfer-Encoding: chunked\r\n\r\n\
+          39\r\n[{\"Key\":\"app/config\",\"Value\":\"dmFsdWU=\",\"ModifyIndex\":7}]\r\n0\r\n\r\n",
+    );
+    let client = Client::new(config);
+
+    let (pair, meta) = client.get("app/config", None).unwrap();
+
+    let request = server.join().unwrap();
+    assert!(request.starts_with("GET /v1/kv/app/config HTTP/1.1\r\n"));
+    assert_eq!(pair.unwrap().value_str().unwrap(), "value");
+    assert_eq!(meta.last_index, Some(7));
+}
+
+#[test]
+fn unix_socket_put_test() {
+    let (config, server) = serve_once("put", b"HTTP/1.1 200 OK\r\nContent-Length: 4\r\n\r\ntrue");
+    let client = Client::new(config);
+
+    let pair = KVPair {
+        Key: String::from("app/config"),
+        Value: b"value".to_vec(),
+        ..Default::default()
+    };
+    let (written, _) = client.put(&pair, None).unwrap();
+
+    let request = server.join().unwrap();
+    assert!(request.starts_with("PUT /v1/kv/app/config"));
+    assert!(request.ends_with("\r\n\r\nvalue"));
+    assert!(written);
+}
+
+#[test]
+fn unix_socket_error_status_test() {
+    let (config, server) = serve_once(
+        "error",
+        b"HTTP/1.1 403 Forbidden\r\nContent-Length: 17\r\n\r\nPermission denied",
+    );
+    let client = Client::new(config);
+
+    let error = client.get("app/config", None).unwrap_err();
+
+    server.join().unwrap();
+    assert!(error.kind().is_permission_denied());
+}
+
+#[cfg(feature = "async")]
+#[tokio::test]
+async fn async_unix_socket_get_test() {
+    use consul::kv::AsyncKV;
+    use consul::AsyncClient;
+
+    let (config, server) = serve_once(
+        "async-get",
+        b"HTTP/1.1 200 OK\r\nX-Consul-Index: 3\r\nContent-Length: 41\r\n\r\n\
+          [{\"Key\":\"app/config\",\"Value\":\"dmFsdWU=\"}]",
+    );
+    let client = AsyncClient::new(config);
+
+    let (pair, meta) = client.get("app/config", None).await.unwrap();
+
+    server.join().unwrap();
+    assert_eq!(pair.unwrap().value_str().unwrap(), "value");
+    assert_eq!(meta.last_index, Some(3));
+}
+
+#[test]
+fn unix_socket_user_agent_test() {
+    let (config, server) = serve_once(
+        "user-agent",
+        b"HTTP/1.1 200 OK\r\nContent-Length: 4\r\n\r\ntrue",
+    );
+    let config = Config::builder()
+        .address(config.address)
+        .user_agent("billing/1.4")
+        .build()
+        .unwrap();
+    let client = Client::new(config);
+
+    let pair = KVPair {
+        Key: String::from("app/config"),
+        ..Default::default()
+    };
+    client.put(&pair, None).unwrap();
+
+    let request = server.join().unwrap();
+    assert!(request.contains("\r\nUser-Agent: billing/1.4\r\n"));
+}
+
+#[test]
+fn unix_socket_timeout_test() {
+    let (address, server) = hang("timeout");
+    let config = Config::builder()
+        .address(address)
+        .request_timeout(Duration::from_millis(300))
+        .build()
+        .unwrap();
+    let client = Client::new(config);
+
+    let started = Instant::now();
+    assert!(client.get("app/config", None).is_err());
+    assert!(started.elapsed() < Duration::from_secs(5));
+    server.join().unwrap();
+}
+
+#[test]
+fn unix_socket_read_timeout_test() {
+    let (address, server) = hang("read-timeout");
+    let config = Config::builder()
+        .address(address)
+        .read_timeout(Duration::from_millis(300))
+        .build()
+        .unwrap();
+    let client = Client::new(config);
+
+    let started = Instant::now();
+    assert!(client.get("app/config", None).is_err());
+    assert!(started.elapsed() < Duration::from_secs(5));
+    server.join().unwrap();
+}
+
+#[cfg(feature = "async")]
+#[tokio::test]
+async fn async_unix_socket_timeout_test() {
+    use consul::kv::AsyncKV;
+    use consul::AsyncClient;
+
+    let (address, server) = hang("async-timeout");
+    let config = Config::builder()
+        .address(address)
+        .request_timeout(Duration::from_millis(300))
+        .build()
+        .unwrap();
+    let client = AsyncClient::new(config);
+
+    let started = Instant::now();
+    assert!(client.get("app/config", None).await.is_err());
+    assert!(started.elapsed() < Duration::from_secs(5));
+    server.join().unwrap();
+}
@@ -0,0 +1,66 @@
+mod common;
+
+extern crate consul;
+use consul::kv::{KVPair, KV};
+use consul::watch::Watch;
+use consul::{Client, QueryOptions};
+
+extern crate rand;
+use rand::distributions::Alphanumeric;
+use rand::{thread_rng, Rng};
+
+use std::thread;
+use std::time::Duration;
+
+#[test]
+fn watch_kv_key_test() {
+    let config = common::config();
+    let client = Client::new(config);
+
+    let unique_test_path: String = thread_rng().sample_iter(&Alphanumeric).take(16).collect();
+    let key = format!("{}/watched", unique_test_path);
+    let pair = KVPair {
+        Key: key.clone(),
+        Value: b"first".to_vec(),
+        ..Default::default()
+    };
+    client.put(&pair, None).unwrap();
+
+    let options = QueryOptions {
+        wait_time: Some(Duration::from_secs(10)),
+        ..Default::default()
+    };
+    let mut watch = Watch::new(|o| client.get(&key, o), Some(options));
+
+    let first = watch.next().unwrap().unwrap().unwrap();
+    assert_eq!(first.value_str().unwrap(), "first");
+
+    let writer = client.clone();
+    let updated = KVPair {
+        Value: b"second".to_vec(),
+        ..pair
+    };
+    let handle = thread::spawn(move || {
+        thread::sleep(Duration::from_millis(500));
+        writer.put(&updated, None).unwrap();
+    });
+
+    let second = watch.next().unwrap().unwrap().unwrap();
+    assert_eq!(second.value_str().unwrap(), "second");
+
+    handle.join().unwrap();
+    client.delete(&key, None).unwrap();
+}
+
+#[test]
+fn watch_kv_prefix_test() {
+    let config = common::config();
+    let client = Client::new(config);
+
+    let unique_test_path: String = thread_rng().sample_iter(&Alphanumeric).take(16).collect();
+    let mut watch = Watch::new(|o| client.list(&unique_test_path, o), None);
+
+    let initial = watch.next().unwrap().unwrap();
+    assert!(initial.is_empty());
+    assert!(watch.last_index().unwrap() > 0, "index must be positive");
+}